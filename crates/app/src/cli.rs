@@ -0,0 +1,82 @@
+//! Command-line subcommands, run instead of starting the companion
+
+use common::AppPaths;
+use common::schema::{self, SchemaKind};
+use common::storage::StorageService;
+use std::path::Path;
+
+const SCHEMA_USAGE: &str = "usage: pet schema <name> | pet schema --out <dir>";
+const SCRIPTS_USAGE: &str = "usage: pet scripts upgrade";
+
+/// Run the subcommand named by `args`, if any, returning its exit code.
+/// Returns `None` when no subcommand was given.
+pub fn run(args: &[String]) -> Option<i32> {
+    match args.first()?.as_str() {
+        "schema" => Some(schema_command(&args[1..])),
+        "scripts" => Some(scripts_command(&args[1..])),
+        _ => None,
+    }
+}
+
+/// `pet schema <name>` prints one JSON Schema; `pet schema --out <dir>`
+/// writes all of them
+fn schema_command(args: &[String]) -> i32 {
+    let result = match args {
+        [] => {
+            println!("{SCHEMA_USAGE}\n\navailable schemas:");
+            for kind in SchemaKind::ALL {
+                println!("  {kind}");
+            }
+            return 0;
+        }
+        [flag, dir] if flag == "--out" => schema::export_all(Path::new(dir)).map(|paths| {
+            for path in paths {
+                println!("{}", path.display());
+            }
+        }),
+        [name] => match SchemaKind::from_name(name) {
+            Some(kind) => kind.to_json().map(|json| println!("{json}")),
+            None => {
+                eprintln!("unknown schema '{name}'");
+                return 2;
+            }
+        },
+        _ => {
+            eprintln!("{SCHEMA_USAGE}");
+            return 2;
+        }
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("error: {e}");
+            1
+        }
+    }
+}
+
+/// `pet scripts upgrade` rewrites older script files in the current
+/// schema version, keeping the originals as `.bak` files
+fn scripts_command(args: &[String]) -> i32 {
+    if !matches!(args, [command] if command == "upgrade") {
+        eprintln!("{SCRIPTS_USAGE}");
+        return 2;
+    }
+
+    let upgrades =
+        match AppPaths::new().and_then(|paths| StorageService::new(paths).upgrade_scripts()) {
+            Ok(upgrades) => upgrades,
+            Err(e) => {
+                eprintln!("error: {e}");
+                return 1;
+            }
+        };
+    for (path, version) in &upgrades.upgraded {
+        println!("upgraded {} from version {version}", path.display());
+    }
+    for error in &upgrades.errors {
+        eprintln!("error: {error}");
+    }
+    i32::from(!upgrades.errors.is_empty())
+}
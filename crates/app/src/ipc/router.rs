@@ -16,6 +16,11 @@ pub enum AppCommand {
     OpenManager,
     OpenSettings,
     QuitApp,
+    /// The user dragged the pet to `x`, `y`
+    SavePetPosition {
+        x: i32,
+        y: i32,
+    },
 }
 
 /// Routes IPC messages between connected processes
@@ -76,6 +81,12 @@ impl MessageRouter {
             IpcMessage::QuitApp => {
                 let _ = self.cmd_tx.send(AppCommand::QuitApp).await;
             }
+            IpcMessage::PetDragCompleted { x, y, .. } => {
+                let _ = self
+                    .cmd_tx
+                    .send(AppCommand::SavePetPosition { x: *x, y: *y })
+                    .await;
+            }
             _ => {
                 info!(
                     "App received {:?} from {}",
@@ -1,3 +1,4 @@
+mod cli;
 mod process;
 
 use common::autostart;
@@ -9,6 +10,11 @@ use tracing::{error, info};
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = cli::run(&args) {
+        std::process::exit(code);
+    }
+
     tracing_subscriber::fmt()
         .with_env_filter(
             tracing_subscriber::EnvFilter::from_default_env()
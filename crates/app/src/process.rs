@@ -4,14 +4,17 @@ use app::ipc::router::AppCommand;
 use app::ipc::server::ClientWriter;
 #[cfg(unix)]
 use app::ipc::{IpcServer, MessageRouter};
-use common::config::{AppConfig, AppState, AppearanceSettings};
+use common::config::{AppConfig, AppState, AppearanceSettings, AudioSettings};
+use common::models::{Pet, WindowPosition};
 use common::paths::AppPaths;
+use common::triggers::TriggerTable;
 use common::{Result, autostart};
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
 use std::process::Stdio;
 use tokio::process::{Child, Command};
 use tokio::signal;
 use tokio::sync::mpsc as tokio_mpsc;
+use tokio::time::Instant;
 use tracing::{error, info, warn};
 #[cfg(unix)]
 use {
@@ -21,6 +24,10 @@ use {
     tokio::sync::Mutex,
 };
 
+/// How long the pet must stay put after a drag before its position is saved
+#[cfg(unix)]
+const POSITION_SAVE_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+
 pub struct ProcessManager {
     paths: AppPaths,
     tray: Option<Child>,
@@ -30,6 +37,16 @@ pub struct ProcessManager {
     pet_visible: bool,
     last_active_pet: Option<uuid::Uuid>,
     last_appearance: AppearanceSettings,
+    last_audio: AudioSettings,
+    last_triggers: TriggerTable,
+    /// Where the pet was last dragged to, and when to save it
+    pending_position: Option<(WindowPosition, Instant)>,
+    /// When the pet was hidden, while it is
+    #[cfg(unix)]
+    hidden_since: Option<Instant>,
+    /// Minutes the pet can stay hidden before the theater is stopped
+    #[cfg(unix)]
+    unload_hidden_after: Option<u32>,
     #[cfg(unix)]
     ipc_clients: Option<Arc<Mutex<HashMap<ProcessId, ClientWriter>>>>,
 }
@@ -45,6 +62,13 @@ impl ProcessManager {
             pet_visible: true,
             last_active_pet: None,
             last_appearance: AppearanceSettings::default(),
+            last_audio: AudioSettings::default(),
+            last_triggers: TriggerTable::default(),
+            pending_position: None,
+            #[cfg(unix)]
+            hidden_since: None,
+            #[cfg(unix)]
+            unload_hidden_after: None,
             #[cfg(unix)]
             ipc_clients: None,
         }
@@ -92,6 +116,9 @@ impl ProcessManager {
             return Ok(());
         }
 
+        // Open where the pet was last left
+        self.save_pet_position();
+
         let exe_path = Self::get_exe_path("pet-theater");
         info!("Starting theater process: {exe_path:?}");
 
@@ -225,6 +252,15 @@ impl ProcessManager {
         }
     }
 
+    /// Whether a process has connected over IPC
+    #[cfg(unix)]
+    async fn is_connected(&self, target: ProcessId) -> bool {
+        match self.ipc_clients {
+            Some(ref clients) => clients.lock().await.contains_key(&target),
+            None => false,
+        }
+    }
+
     fn update_state<F>(&self, f: F) -> Result<()>
     where
         F: FnOnce(&mut AppState),
@@ -239,6 +275,12 @@ impl ProcessManager {
         // Initialize tracking state from config
         self.last_active_pet = config.active_pet;
         self.last_appearance = config.appearance.clone();
+        self.last_audio = config.audio.clone();
+        self.last_triggers = active_triggers(config);
+        #[cfg(unix)]
+        {
+            self.unload_hidden_after = config.general.unload_hidden_after;
+        }
         self.pet_visible = AppState::load(&self.paths)
             .map(|s| s.pet_visible)
             .unwrap_or(true);
@@ -287,6 +329,8 @@ impl ProcessManager {
             self.start_child_processes(config);
 
             loop {
+                let save_position_at = self.pending_position.map(|(_, at)| at);
+                let unload_at = self.unload_hidden_at();
                 tokio::select! {
                     _ = signal::ctrl_c() => {
                         info!("Received shutdown signal");
@@ -301,6 +345,17 @@ impl ProcessManager {
                     Some(_event) = rx.recv() => {
                         self.handle_config_change().await;
                     }
+                    _ = tokio::time::sleep_until(save_position_at.unwrap_or_else(Instant::now)),
+                        if save_position_at.is_some() =>
+                    {
+                        self.save_pet_position();
+                    }
+                    _ = tokio::time::sleep_until(unload_at.unwrap_or_else(Instant::now)),
+                        if unload_at.is_some() =>
+                    {
+                        info!("Pet has been hidden for a while, stopping theater to free memory");
+                        let _ = self.stop_theater().await;
+                    }
                     result = listener.accept() => {
                         match result {
                             Ok((stream, _addr)) => {
@@ -372,9 +427,13 @@ impl ProcessManager {
         }
     }
 
-    /// Handle config.toml changes: auto_start, active_pet, appearance
+    /// Handle config.toml changes: auto_start, active_pet, appearance, audio,
+    /// triggers
     async fn handle_config_change(&mut self) {
         info!("Config file changed, reloading...");
+        // Save a pending position first, so a restarted Theater opens there
+        // and the reloaded config already has it
+        self.save_pet_position();
         let Ok(config) = AppConfig::load(&self.paths) else {
             return;
         };
@@ -383,20 +442,31 @@ impl ProcessManager {
         if let Err(e) = autostart::sync_autostart(config.general.auto_start) {
             error!("Failed to sync auto-start on config change: {e}");
         }
+        #[cfg(unix)]
+        {
+            self.unload_hidden_after = config.general.unload_hidden_after;
+        }
 
-        // Detect active_pet change -> restart theater
-        if config.active_pet != self.last_active_pet {
+        // Detect active_pet change -> swap the pet in the running theater
+        let pet_changed = config.active_pet != self.last_active_pet;
+        if pet_changed {
             info!(
                 "Active pet changed: {:?} -> {:?}",
                 self.last_active_pet, config.active_pet
             );
             self.last_active_pet = config.active_pet;
-            let _ = self.stop_theater().await;
-            if config.active_pet.is_some()
-                && self.pet_visible
-                && let Err(e) = self.start_theater()
-            {
-                error!("Failed to restart theater: {e}");
+            match config.get_active_pet() {
+                Some(pet) if self.theater.is_some() => self.swap_pet(pet.clone()).await,
+                Some(_) => {
+                    if self.pet_visible
+                        && let Err(e) = self.start_theater()
+                    {
+                        error!("Failed to start theater: {e}");
+                    }
+                }
+                None => {
+                    let _ = self.stop_theater().await;
+                }
             }
         }
 
@@ -411,10 +481,64 @@ impl ProcessManager {
                     pet_scale: config.appearance.pet_scale,
                     opacity: config.appearance.opacity,
                     always_on_top: config.appearance.always_on_top,
+                    click_through: config.appearance.click_through,
                 },
             )
             .await;
         }
+
+        // Detect audio change -> push to Theater via IPC
+        if config.audio != self.last_audio {
+            info!("Audio settings changed, pushing to Theater");
+            self.last_audio = config.audio.clone();
+            #[cfg(unix)]
+            self.send_to(
+                ProcessId::Theater,
+                IpcMessage::UpdateAudio {
+                    volume: config.audio.volume,
+                    muted: config.audio.muted,
+                },
+            )
+            .await;
+        }
+
+        // Detect trigger table change -> push to Theater via IPC. A new
+        // active pet brings its table along.
+        let triggers = active_triggers(&config);
+        if triggers != self.last_triggers {
+            self.last_triggers = triggers;
+            if !pet_changed {
+                info!("Trigger table changed, pushing to Theater");
+                #[cfg(unix)]
+                self.send_to(
+                    ProcessId::Theater,
+                    IpcMessage::UpdateTriggers {
+                        triggers: self.last_triggers.clone(),
+                    },
+                )
+                .await;
+            }
+        }
+    }
+
+    /// Switch the running Theater to `pet`, restarting it if it isn't
+    /// connected to be told
+    #[cfg_attr(not(unix), allow(unused_variables))]
+    async fn swap_pet(&mut self, pet: Pet) {
+        #[cfg(unix)]
+        if self.is_connected(ProcessId::Theater).await {
+            info!("Swapping the Theater to pet '{}'", pet.name);
+            self.send_to(ProcessId::Theater, IpcMessage::SwapPet { pet })
+                .await;
+            return;
+        }
+
+        let _ = self.stop_theater().await;
+        if self.pet_visible
+            && let Err(e) = self.start_theater()
+        {
+            error!("Failed to restart theater: {e}");
+        }
     }
 
     /// Handle commands from the IPC router. Returns true if the app should quit.
@@ -424,14 +548,7 @@ impl ProcessManager {
             AppCommand::TogglePetVisibility => {
                 self.pet_visible = !self.pet_visible;
                 info!("Pet visibility toggled: {}", self.pet_visible);
-
-                if self.pet_visible {
-                    if let Err(e) = self.start_theater() {
-                        error!("Failed to start theater: {e}");
-                    }
-                } else {
-                    let _ = self.stop_theater().await;
-                }
+                self.apply_pet_visibility().await;
 
                 let _ = self.update_state(|s| s.pet_visible = self.pet_visible);
                 self.send_to(
@@ -459,7 +576,76 @@ impl ProcessManager {
                 info!("Quit requested via IPC");
                 true
             }
+            AppCommand::SavePetPosition { x, y } => {
+                // Drags come in bursts; save once the pet has been left alone
+                let save_at = Instant::now() + POSITION_SAVE_DELAY;
+                self.pending_position = Some((WindowPosition::new(x, y), save_at));
+                false
+            }
+        }
+    }
+
+    /// Hide or show the pet in the running Theater, keeping it loaded.
+    /// Starts the Theater to show the pet if it was stopped while hidden.
+    #[cfg(unix)]
+    async fn apply_pet_visibility(&mut self) {
+        self.hidden_since = (!self.pet_visible).then(Instant::now);
+        if self.theater.is_none() {
+            if self.pet_visible
+                && let Err(e) = self.start_theater()
+            {
+                error!("Failed to start theater: {e}");
+            }
+            return;
+        }
+
+        if self.is_connected(ProcessId::Theater).await {
+            self.send_to(
+                ProcessId::Theater,
+                IpcMessage::SetPetVisible {
+                    visible: self.pet_visible,
+                },
+            )
+            .await;
+        } else if !self.pet_visible {
+            let _ = self.stop_theater().await;
+        }
+    }
+
+    /// When to stop the Theater of a hidden pet to free memory, if ever
+    #[cfg(unix)]
+    fn unload_hidden_at(&self) -> Option<Instant> {
+        unload_at(
+            self.unload_hidden_after,
+            self.hidden_since,
+            self.theater.is_some(),
+        )
+    }
+
+    /// Save where the pet was last dragged to, if that's not saved yet
+    fn save_pet_position(&mut self) {
+        let Some((position, _)) = self.pending_position.take() else {
+            return;
+        };
+        let mut config = match AppConfig::load(&self.paths) {
+            Ok(config) => config,
+            Err(e) => {
+                error!("Failed to load config to save pet position: {e}");
+                return;
+            }
+        };
+        if config.appearance.pet_position == position {
+            return;
+        }
+
+        config.appearance.pet_position = position;
+        if let Err(e) = config.save(&self.paths) {
+            error!("Failed to save pet position: {e}");
+            return;
         }
+        info!("Saved pet position ({}, {})", position.x, position.y);
+        // The Theater is already there; don't push it the change
+        self.last_appearance.pet_position = position;
     }
 
     async fn check_processes(&mut self) -> bool {
@@ -502,6 +688,7 @@ impl ProcessManager {
 
     async fn shutdown(&mut self) -> Result<()> {
         info!("Shutting down all processes...");
+        self.save_pet_position();
 
         if let Err(e) = self.stop_settings().await {
             error!("Error stopping settings: {}", e);
@@ -539,3 +726,49 @@ impl Drop for ProcessManager {
         }
     }
 }
+
+/// The active pet's trigger table, or the default one without a pet
+fn active_triggers(config: &AppConfig) -> TriggerTable {
+    config
+        .get_active_pet()
+        .map(|pet| pet.triggers.clone())
+        .unwrap_or_default()
+}
+
+/// When to stop a running Theater whose pet has been hidden since
+/// `hidden_since`, given the minutes it may stay hidden
+#[cfg(unix)]
+fn unload_at(
+    unload_after: Option<u32>,
+    hidden_since: Option<Instant>,
+    theater_running: bool,
+) -> Option<Instant> {
+    let minutes = unload_after?;
+    if !theater_running {
+        return None;
+    }
+    Some(hidden_since? + std::time::Duration::from_secs(u64::from(minutes) * 60))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn hidden_theater_unloads_after_the_configured_minutes() {
+        let hidden_since = Instant::now();
+        assert_eq!(unload_at(None, Some(hidden_since), true), None);
+
+        // Nothing to unload without a running theater
+        assert_eq!(unload_at(Some(5), Some(hidden_since), false), None);
+
+        assert_eq!(
+            unload_at(Some(5), Some(hidden_since), true),
+            Some(hidden_since + Duration::from_secs(5 * 60))
+        );
+
+        // Showing the pet again keeps the theater
+        assert_eq!(unload_at(Some(5), None, true), None);
+    }
+}
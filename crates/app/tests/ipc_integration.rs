@@ -1,8 +1,10 @@
 #![cfg(unix)]
 //! Integration tests for the IPC infrastructure
 
-use common::ipc::{IpcEnvelope, IpcMessage, MAX_MESSAGE_SIZE, ProcessId};
-use common::script::{Action, BehaviorScript, Keyframe};
+use common::ipc::{IpcEnvelope, IpcMessage, MAX_MESSAGE_SIZE, ProcessId, ScriptSource};
+use common::script::{
+    Action, BehaviorScript, Keyframe, KeyframeTime, SCRIPT_SCHEMA_VERSION, ScriptArgs,
+};
 use std::time::Duration;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::UnixStream;
@@ -44,23 +46,22 @@ fn process_id_all_variants_roundtrip() {
 #[test]
 fn execute_script_message_in_envelope() {
     let script = BehaviorScript {
+        schema_version: SCRIPT_SCHEMA_VERSION,
         id: "wave".to_string(),
         duration: Some(3.0),
         keyframes: vec![
             Keyframe {
-                time: 0.0,
-                action: Action::PlayAnimation {
-                    name: "wave_start".to_string(),
-                },
+                time: KeyframeTime::At(0.0),
+                action: Action::play_animation("wave_start"),
             },
             Keyframe {
-                time: 1.5,
-                action: Action::PlayAnimation {
-                    name: "wave_end".to_string(),
-                },
+                time: KeyframeTime::At(1.5),
+                action: Action::play_animation("wave_end"),
             },
         ],
-        next: Some("idle".to_string()),
+        tracks: Vec::new(),
+        repeat: None,
+        next: Some("idle".into()),
         interruptible: true,
     };
 
@@ -68,7 +69,8 @@ fn execute_script_message_in_envelope() {
         ProcessId::Brain,
         ProcessId::Theater,
         IpcMessage::ExecuteScript {
-            script: script.clone(),
+            script: script.clone().into(),
+            args: ScriptArgs::new(),
         },
     );
 
@@ -79,13 +81,14 @@ fn execute_script_message_in_envelope() {
     assert_eq!(decoded.target, ProcessId::Theater);
 
     if let IpcMessage::ExecuteScript {
-        script: decoded_script,
+        script: ScriptSource::Inline(decoded_script),
+        ..
     } = decoded.payload
     {
         assert_eq!(decoded_script.id, "wave");
         assert_eq!(decoded_script.duration, Some(3.0));
         assert_eq!(decoded_script.keyframes.len(), 2);
-        assert_eq!(decoded_script.next, Some("idle".to_string()));
+        assert_eq!(decoded_script.next, Some("idle".into()));
     } else {
         panic!("Expected ExecuteScript message");
     }
@@ -239,21 +242,25 @@ async fn message_routing_between_clients() {
 
     // Brain sends ExecuteScript back to Theater
     let script = BehaviorScript {
+        schema_version: SCRIPT_SCHEMA_VERSION,
         id: "react".to_string(),
         duration: Some(1.0),
         keyframes: vec![Keyframe {
-            time: 0.0,
-            action: Action::PlayAnimation {
-                name: "happy".to_string(),
-            },
+            time: KeyframeTime::At(0.0),
+            action: Action::play_animation("happy"),
         }],
+        tracks: Vec::new(),
+        repeat: None,
         next: None,
         interruptible: true,
     };
     let exec = IpcEnvelope::new(
         ProcessId::Brain,
         ProcessId::Theater,
-        IpcMessage::ExecuteScript { script },
+        IpcMessage::ExecuteScript {
+            script: script.into(),
+            args: ScriptArgs::new(),
+        },
     );
     write_envelope(&mut brain_client, &exec).await;
 
@@ -270,7 +277,11 @@ async fn message_routing_between_clients() {
 
     assert_eq!(received.source, ProcessId::Brain);
     assert_eq!(received.target, ProcessId::Theater);
-    if let IpcMessage::ExecuteScript { script } = received.payload {
+    if let IpcMessage::ExecuteScript {
+        script: ScriptSource::Inline(script),
+        ..
+    } = received.payload
+    {
         assert_eq!(script.id, "react");
     } else {
         panic!("Expected ExecuteScript message");
@@ -2,6 +2,7 @@ use crate::error::{Error, Result};
 use crate::models::{Pet, WindowPosition};
 use crate::paths::AppPaths;
 use config::{Config, File};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::Write;
@@ -10,12 +11,16 @@ use uuid::Uuid;
 
 // --- Settings group structs ---
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct GeneralSettings {
     #[serde(default)]
     pub auto_start: bool,
     #[serde(default = "default_language")]
     pub language: String,
+    /// Minutes the pet can stay hidden before the theater exits to free
+    /// memory; it stays loaded while hidden without a limit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unload_hidden_after: Option<u32>,
 }
 
 fn default_language() -> String {
@@ -27,11 +32,12 @@ impl Default for GeneralSettings {
         Self {
             auto_start: false,
             language: default_language(),
+            unload_hidden_after: None,
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct AppearanceSettings {
     #[serde(default = "default_scale")]
     pub pet_scale: f32,
@@ -41,6 +47,10 @@ pub struct AppearanceSettings {
     pub always_on_top: bool,
     #[serde(default = "default_opacity")]
     pub opacity: f32,
+    /// Let clicks on the empty parts of the pet's window through to the
+    /// windows below (not supported on X11)
+    #[serde(default = "default_true")]
+    pub click_through: bool,
 }
 
 fn default_scale() -> f32 {
@@ -62,11 +72,46 @@ impl Default for AppearanceSettings {
             pet_position: WindowPosition::default(),
             always_on_top: default_true(),
             opacity: default_opacity(),
+            click_through: default_true(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct AudioSettings {
+    /// Volume for all sound effects, from 0 to 1
+    #[serde(default = "default_volume")]
+    pub volume: f32,
+    #[serde(default)]
+    pub muted: bool,
+}
+
+fn default_volume() -> f32 {
+    0.8
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            volume: default_volume(),
+            muted: false,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl AudioSettings {
+    /// Volume sound effects play at, taking mute into account
+    #[must_use]
+    pub fn effective_volume(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.volume.clamp(0.0, 1.0)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct AiSettings {
     #[serde(default)]
     pub enabled: bool,
@@ -104,7 +149,7 @@ impl Default for AiSettings {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq)]
 pub enum AiProvider {
     #[default]
     OpenAi,
@@ -113,7 +158,7 @@ pub enum AiProvider {
     Custom,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PersonalityConfig {
     #[serde(default = "default_pet_name")]
     pub name: String,
@@ -136,20 +181,22 @@ impl Default for PersonalityConfig {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
 pub struct MeshySettings {
     pub api_key: Option<String>,
 }
 
 // --- Main config struct ---
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
 pub struct AppConfig {
     #[serde(default)]
     pub general: GeneralSettings,
     #[serde(default)]
     pub appearance: AppearanceSettings,
     #[serde(default)]
+    pub audio: AudioSettings,
+    #[serde(default)]
     pub ai: AiSettings,
     #[serde(default)]
     pub meshy: MeshySettings,
@@ -185,6 +232,7 @@ fn migrate_from_legacy(legacy: LegacyAppConfig) -> AppConfig {
             pet_position: legacy.pet_position,
             ..Default::default()
         },
+        audio: AudioSettings::default(),
         ai: AiSettings::default(),
         meshy: MeshySettings {
             api_key: legacy.meshy_api_key,
@@ -302,7 +350,9 @@ impl AppState {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::CameraFraming;
     use crate::paths::AppPaths;
+    use crate::triggers::Trigger;
     use tempfile::TempDir;
 
     fn test_paths() -> (TempDir, AppPaths) {
@@ -337,11 +387,13 @@ y = 200
             general: GeneralSettings {
                 auto_start: true,
                 language: "zh".to_string(),
+                unload_hidden_after: Some(30),
             },
             appearance: AppearanceSettings {
                 pet_scale: 2.0,
                 always_on_top: false,
                 opacity: 0.8,
+                click_through: false,
                 ..Default::default()
             },
             ..Default::default()
@@ -350,8 +402,10 @@ y = 200
         let deserialized: AppConfig = toml::from_str(&serialized).unwrap();
         assert!(deserialized.general.auto_start);
         assert_eq!(deserialized.general.language, "zh");
+        assert_eq!(deserialized.general.unload_hidden_after, Some(30));
         assert_eq!(deserialized.appearance.pet_scale, 2.0);
         assert!(!deserialized.appearance.always_on_top);
+        assert!(!deserialized.appearance.click_through);
     }
 
     #[test]
@@ -362,6 +416,80 @@ y = 200
         assert_eq!(deserialized.appearance.pet_scale, 1.0);
         assert_eq!(deserialized.general.language, "en");
         assert!(!deserialized.general.auto_start);
+        assert_eq!(deserialized.general.unload_hidden_after, None);
+        assert_eq!(deserialized.audio, AudioSettings::default());
+    }
+
+    #[test]
+    fn muted_audio_has_no_volume() {
+        let mut audio = AudioSettings {
+            volume: 0.5,
+            muted: false,
+        };
+        assert_eq!(audio.effective_volume(), 0.5);
+        audio.muted = true;
+        assert_eq!(audio.effective_volume(), 0.0);
+    }
+
+    #[test]
+    fn pet_triggers_load_from_file() {
+        let (_dir, paths) = test_paths();
+        paths.ensure_dirs().unwrap();
+
+        let content = r#"
+active_pet = "6f1c1d56-9f4a-4a57-8d0e-2a4c1f0b7e11"
+
+[[pets]]
+id = "6f1c1d56-9f4a-4a57-8d0e-2a4c1f0b7e11"
+name = "Duck"
+description = "A duck"
+model_path = "/tmp/duck.glb"
+created_at = "2026-01-01T00:00:00Z"
+
+[pets.triggers]
+idle_timeout = 90.0
+
+[pets.triggers.reactions]
+double_click = [{ script = "spin", weight = 2.0 }, "happy"]
+"#;
+        fs::write(paths.config_file(), content).unwrap();
+
+        let config = AppConfig::load(&paths).unwrap();
+        let triggers = &config.get_active_pet().unwrap().triggers;
+        assert_eq!(triggers.idle_timeout, 90.0);
+        assert!(!triggers.handles(Trigger::Click));
+        assert_eq!(triggers.reaction(Trigger::DoubleClick).len(), 2);
+    }
+
+    #[test]
+    fn pet_camera_framing_loads_from_file() {
+        let (_dir, paths) = test_paths();
+        paths.ensure_dirs().unwrap();
+
+        let content = r#"
+active_pet = "6f1c1d56-9f4a-4a57-8d0e-2a4c1f0b7e11"
+
+[[pets]]
+id = "6f1c1d56-9f4a-4a57-8d0e-2a4c1f0b7e11"
+name = "Duck"
+description = "A duck"
+model_path = "/tmp/duck.glb"
+created_at = "2026-01-01T00:00:00Z"
+
+[pets.camera]
+facing = 180.0
+"#;
+        fs::write(paths.config_file(), content).unwrap();
+
+        let config = AppConfig::load(&paths).unwrap();
+        let camera = config.get_active_pet().unwrap().camera;
+        assert_eq!(camera.facing, 180.0);
+        assert_eq!(camera.angle, CameraFraming::default().angle);
+        assert_eq!(camera.margin, CameraFraming::default().margin);
+
+        config.save(&paths).unwrap();
+        let saved = fs::read_to_string(paths.config_file()).unwrap();
+        assert!(saved.contains("[pets.camera]"));
     }
 
     #[test]
@@ -417,6 +545,7 @@ enabled = false
         assert!(!config.general.auto_start);
         assert_eq!(config.general.language, "zh");
         assert_eq!(config.appearance.pet_scale, 2.0);
+        assert!(config.appearance.click_through);
     }
 
     #[test]
@@ -425,5 +554,6 @@ enabled = false
         let config = AppConfig::load(&paths).unwrap();
         assert_eq!(config.appearance.pet_scale, 1.0);
         assert_eq!(config.general.language, "en");
+        assert!(config.appearance.click_through);
     }
 }
@@ -26,6 +26,9 @@ pub enum Error {
     #[error("Script not found: {0}")]
     ScriptNotFound(String),
 
+    #[error("Script error: {0}")]
+    Script(#[from] crate::script::ScriptError),
+
     #[error("Invalid configuration: {0}")]
     InvalidConfig(String),
 
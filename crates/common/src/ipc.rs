@@ -1,13 +1,17 @@
 //! IPC message types and frame encoding for inter-process communication
 
-use crate::script::BehaviorScript;
+use crate::models::Pet;
+use crate::script::{BehaviorScript, ScriptArgs};
+use crate::triggers::TriggerTable;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Maximum IPC message payload size (1 MB)
 pub const MAX_MESSAGE_SIZE: u32 = 1_048_576;
 
 /// Process identifier for IPC routing
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 pub enum ProcessId {
     App,
     Tray,
@@ -17,8 +21,24 @@ pub enum ProcessId {
     Settings,
 }
 
+/// The script an `ExecuteScript` message runs
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum ScriptSource {
+    /// A complete script, added to the theater's library
+    Inline(BehaviorScript),
+    /// The ID of a script or template in the theater's library
+    Library(String),
+}
+
+impl From<BehaviorScript> for ScriptSource {
+    fn from(script: BehaviorScript) -> Self {
+        Self::Inline(script)
+    }
+}
+
 /// IPC message envelope with routing metadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct IpcEnvelope {
     pub source: ProcessId,
     pub target: ProcessId,
@@ -27,25 +47,69 @@ pub struct IpcEnvelope {
 }
 
 /// IPC message payload variants
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub enum IpcMessage {
     // Theater -> Brain
     PetClicked,
+    /// A second click shortly after the first, sent in place of a second
+    /// `PetClicked`
+    PetDoubleClicked,
+    PetRightClicked,
+    /// The pet was pressed and held without being dragged
+    PetLongPressed,
+    /// The cursor moved onto (`hovering`) or off the pet's window
+    PetHovered {
+        hovering: bool,
+    },
+    /// The mouse wheel turned over the pet, in lines; positive is up
+    PetScrolled {
+        delta: f32,
+    },
+    /// The user started dragging the pet from `x`, `y`. Positions are the
+    /// top left corner of the pet's window below any speech bubble, in
+    /// physical screen pixels, like `pet_position`.
+    PetDragStarted {
+        x: i32,
+        y: i32,
+    },
+    /// The user let go of the pet at `x`, `y` after dragging it from
+    /// `start_x`, `start_y` for `duration` seconds. Also sent to the app,
+    /// which saves the position for the next start.
     PetDragCompleted {
         x: i32,
         y: i32,
+        #[serde(default)]
+        start_x: i32,
+        #[serde(default)]
+        start_y: i32,
+        #[serde(default)]
+        duration: f32,
     },
+    /// Animations and facial expressions the loaded model supports
     AnimationsAvailable {
         animations: Vec<String>,
+        #[serde(default)]
+        expressions: Vec<String>,
     },
 
     // Brain -> Theater
+    /// Switch to a script sent along with the message, or to a script or
+    /// template already in the theater's library, expanded with `args`
     ExecuteScript {
-        script: BehaviorScript,
+        script: ScriptSource,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        args: ScriptArgs,
     },
     AiThinking {
         is_thinking: bool,
     },
+    /// Show `text` in a speech bubble above the pet; without a duration
+    /// the bubble stays up for a reading time based on the text's length
+    Say {
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        duration: Option<f32>,
+    },
 
     // Tray -> App (commands)
     TogglePetVisibility,
@@ -63,6 +127,29 @@ pub enum IpcMessage {
         pet_scale: f32,
         opacity: f32,
         always_on_top: bool,
+        click_through: bool,
+    },
+
+    // App -> Theater (audio settings updates)
+    UpdateAudio {
+        volume: f32,
+        muted: bool,
+    },
+
+    // App -> Theater (the active pet's trigger table changed)
+    UpdateTriggers {
+        triggers: TriggerTable,
+    },
+
+    // App -> Theater (hide or show the pet, keeping the process running)
+    SetPetVisible {
+        visible: bool,
+    },
+
+    // App -> Theater (the active pet changed; load its model, expressions,
+    // sounds and trigger table without restarting)
+    SwapPet {
+        pet: Pet,
     },
 
     // Tray/Manager -> Brain
@@ -123,7 +210,7 @@ impl std::fmt::Display for ProcessId {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::script::{Action, BehaviorScript, Keyframe};
+    use crate::script::{Action, BehaviorScript, Keyframe, KeyframeTime, SCRIPT_SCHEMA_VERSION};
 
     #[test]
     fn encode_decode_roundtrip() {
@@ -156,14 +243,15 @@ mod tests {
     #[test]
     fn execute_script_message_roundtrip() {
         let script = BehaviorScript {
+            schema_version: SCRIPT_SCHEMA_VERSION,
             id: "test".to_string(),
             duration: Some(2.0),
             keyframes: vec![Keyframe {
-                time: 0.0,
-                action: Action::PlayAnimation {
-                    name: "idle".to_string(),
-                },
+                time: KeyframeTime::At(0.0),
+                action: Action::play_animation("idle"),
             }],
+            tracks: Vec::new(),
+            repeat: None,
             next: None,
             interruptible: true,
         };
@@ -171,13 +259,20 @@ mod tests {
         let envelope = IpcEnvelope::new(
             ProcessId::Brain,
             ProcessId::Theater,
-            IpcMessage::ExecuteScript { script },
+            IpcMessage::ExecuteScript {
+                script: script.into(),
+                args: ScriptArgs::new(),
+            },
         );
 
         let encoded = envelope.encode().unwrap();
         let decoded = IpcEnvelope::decode(&encoded[4..]).unwrap();
 
-        if let IpcMessage::ExecuteScript { script } = decoded.payload {
+        if let IpcMessage::ExecuteScript {
+            script: ScriptSource::Inline(script),
+            ..
+        } = decoded.payload
+        {
             assert_eq!(script.id, "test");
             assert_eq!(script.keyframes.len(), 1);
         } else {
@@ -185,6 +280,54 @@ mod tests {
         }
     }
 
+    #[test]
+    fn execute_script_message_calls_a_template() {
+        let envelope = IpcEnvelope::new(
+            ProcessId::Brain,
+            ProcessId::Theater,
+            IpcMessage::ExecuteScript {
+                script: ScriptSource::Library("walk_to".to_string()),
+                args: [("x".to_string(), 0.5.into())].into(),
+            },
+        );
+
+        let encoded = envelope.encode().unwrap();
+        let decoded = IpcEnvelope::decode(&encoded[4..]).unwrap();
+
+        if let IpcMessage::ExecuteScript {
+            script: ScriptSource::Library(id),
+            args,
+        } = decoded.payload
+        {
+            assert_eq!(id, "walk_to");
+            assert_eq!(args["x"], 0.5);
+        } else {
+            panic!("Expected ExecuteScript message");
+        }
+    }
+
+    #[test]
+    fn say_message_roundtrip() {
+        let envelope = IpcEnvelope::new(
+            ProcessId::Brain,
+            ProcessId::Theater,
+            IpcMessage::Say {
+                text: "Hello there!".to_string(),
+                duration: None,
+            },
+        );
+
+        let encoded = envelope.encode().unwrap();
+        let decoded = IpcEnvelope::decode(&encoded[4..]).unwrap();
+
+        if let IpcMessage::Say { text, duration } = decoded.payload {
+            assert_eq!(text, "Hello there!");
+            assert_eq!(duration, None);
+        } else {
+            panic!("Expected Say message");
+        }
+    }
+
     #[test]
     fn length_prefix_is_correct() {
         let envelope = IpcEnvelope::new(ProcessId::App, ProcessId::Theater, IpcMessage::Ping);
@@ -200,14 +343,20 @@ mod tests {
             ProcessId::Brain,
             IpcMessage::AnimationsAvailable {
                 animations: vec!["idle".to_string(), "walk".to_string()],
+                expressions: vec!["neutral".to_string(), "smile".to_string()],
             },
         );
 
         let encoded = envelope.encode().unwrap();
         let decoded = IpcEnvelope::decode(&encoded[4..]).unwrap();
 
-        if let IpcMessage::AnimationsAvailable { animations } = decoded.payload {
+        if let IpcMessage::AnimationsAvailable {
+            animations,
+            expressions,
+        } = decoded.payload
+        {
             assert_eq!(animations, vec!["idle", "walk"]);
+            assert_eq!(expressions, vec!["neutral", "smile"]);
         } else {
             panic!("Expected AnimationsAvailable message");
         }
@@ -236,6 +385,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn drag_completed_without_start_still_decodes() {
+        let message: IpcMessage =
+            serde_json::from_str(r#"{"PetDragCompleted": {"x": 10, "y": 20}}"#).unwrap();
+        if let IpcMessage::PetDragCompleted {
+            x,
+            y,
+            start_x,
+            start_y,
+            duration,
+        } = message
+        {
+            assert_eq!((x, y, start_x, start_y), (10, 20, 0, 0));
+            assert_eq!(duration, 0.0);
+        } else {
+            panic!("Expected PetDragCompleted message");
+        }
+    }
+
     #[test]
     fn pet_visibility_changed_roundtrip() {
         let envelope = IpcEnvelope::new(
@@ -261,6 +429,7 @@ mod tests {
                 pet_scale: 1.5,
                 opacity: 0.8,
                 always_on_top: false,
+                click_through: true,
             },
         );
         let encoded = envelope.encode().unwrap();
@@ -269,11 +438,13 @@ mod tests {
             pet_scale,
             opacity,
             always_on_top,
+            click_through,
         } = decoded.payload
         {
             assert!((pet_scale - 1.5).abs() < f32::EPSILON);
             assert!((opacity - 0.8).abs() < f32::EPSILON);
             assert!(!always_on_top);
+            assert!(click_through);
         } else {
             panic!("Expected UpdateAppearance message");
         }
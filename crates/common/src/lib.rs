@@ -4,8 +4,10 @@ pub mod error;
 pub mod ipc;
 pub mod models;
 pub mod paths;
+pub mod schema;
 pub mod script;
 pub mod storage;
+pub mod triggers;
 
 pub use config::{
     AiProvider, AiSettings, AppConfig, AppearanceSettings, GeneralSettings, MeshySettings,
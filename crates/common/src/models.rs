@@ -1,9 +1,12 @@
+use crate::triggers::TriggerTable;
 use jiff::Timestamp;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Pet {
     pub id: Uuid,
     pub name: String,
@@ -11,6 +14,16 @@ pub struct Pet {
     pub model_path: PathBuf,
     pub thumbnail_path: Option<PathBuf>,
     pub created_at: Timestamp,
+    /// Facial expressions by name, as morph target weights. Without a
+    /// mapping, each named morph target in the model is an expression.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub expressions: BTreeMap<String, Vec<MorphWeight>>,
+    /// Scripts started by clicks, drags and other interactions
+    #[serde(default, skip_serializing_if = "TriggerTable::is_default")]
+    pub triggers: TriggerTable,
+    /// How the camera frames the model
+    #[serde(default, skip_serializing_if = "CameraFraming::is_default")]
+    pub camera: CameraFraming,
 }
 
 impl Pet {
@@ -23,11 +36,60 @@ impl Pet {
             model_path,
             thumbnail_path: None,
             created_at: Timestamp::now(),
+            expressions: BTreeMap::new(),
+            triggers: TriggerTable::default(),
+            camera: CameraFraming::default(),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+/// How the camera frames a pet's model. The model is scaled to a standard
+/// height and stood on the ground, then the camera is fit around it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct CameraFraming {
+    /// Degrees the camera looks down on the model from; negative looks up
+    pub angle: f32,
+    /// Degrees the model is turned about its vertical axis, for models
+    /// that don't face the way the pet should
+    pub facing: f32,
+    /// Space left around the model, as a fraction of its size
+    pub margin: f32,
+}
+
+impl Default for CameraFraming {
+    /// A slightly raised view of the model as it was made, with a little
+    /// room around it
+    fn default() -> Self {
+        Self {
+            angle: 10.0,
+            facing: 0.0,
+            margin: 0.1,
+        }
+    }
+}
+
+impl CameraFraming {
+    #[must_use]
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Weight of one morph target (blend shape) in an expression
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct MorphWeight {
+    /// Index of the morph target in the model's mesh
+    pub target: usize,
+    #[serde(default = "full_weight")]
+    pub weight: f32,
+}
+
+fn full_weight() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, Default)]
 pub struct WindowPosition {
     pub x: i32,
     pub y: i32,
@@ -51,4 +113,120 @@ impl WindowPosition {
     pub fn new(x: i32, y: i32) -> Self {
         Self { x, y }
     }
+
+    /// Where a window of `size` placed here should open so it shows on one
+    /// of `screens`: here if its centre is on one of them, otherwise as
+    /// close as it fits on the nearest one. Left alone without any screens.
+    #[must_use]
+    pub fn clamp_to_screens(self, size: WindowSize, screens: &[ScreenArea]) -> Self {
+        let centre = (
+            i64::from(self.x) + i64::from(size.width / 2),
+            i64::from(self.y) + i64::from(size.height / 2),
+        );
+        let Some(nearest) = screens
+            .iter()
+            .min_by_key(|screen| screen.distance_squared(centre))
+        else {
+            return self;
+        };
+        if nearest.distance_squared(centre) == 0 {
+            return self;
+        }
+
+        Self::new(
+            clamp_axis(self.x, size.width, nearest.x, nearest.width),
+            clamp_axis(self.y, size.height, nearest.y, nearest.height),
+        )
+    }
+}
+
+/// A monitor's area of the desktop, in physical pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScreenArea {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ScreenArea {
+    /// Squared distance from `point` to the nearest pixel of this area;
+    /// zero inside it
+    fn distance_squared(&self, (x, y): (i64, i64)) -> i64 {
+        let dx = axis_distance(x, self.x, self.width);
+        let dy = axis_distance(y, self.y, self.height);
+        dx * dx + dy * dy
+    }
+}
+
+fn axis_distance(point: i64, start: i32, length: u32) -> i64 {
+    let start = i64::from(start);
+    let end = start + i64::from(length.max(1)) - 1;
+    (start - point).max(point - end).max(0)
+}
+
+/// Keep a window of `length` starting at `position` within a screen of
+/// `screen_length` starting at `start`, showing its start if it's too big
+fn clamp_axis(position: i32, length: u32, start: i32, screen_length: u32) -> i32 {
+    let end = start.saturating_add_unsigned(screen_length.saturating_sub(length));
+    position.clamp(start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: WindowSize = WindowSize {
+        width: 400,
+        height: 400,
+    };
+
+    fn screen(x: i32, y: i32, width: u32, height: u32) -> ScreenArea {
+        ScreenArea {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn positions_on_a_screen_stay_put() {
+        let screens = [screen(0, 0, 1920, 1080), screen(-1280, 0, 1280, 1024)];
+
+        for position in [
+            WindowPosition::new(100, 200),
+            WindowPosition::new(-1000, 300),
+            // Partly off the edge, but mostly on screen
+            WindowPosition::new(1700, 800),
+        ] {
+            assert_eq!(position.clamp_to_screens(SIZE, &screens), position);
+        }
+    }
+
+    #[test]
+    fn positions_off_screen_move_onto_the_nearest_screen() {
+        // The monitor on the left was unplugged
+        let screens = [screen(0, 0, 1920, 1080)];
+        assert_eq!(
+            WindowPosition::new(-1000, 300).clamp_to_screens(SIZE, &screens),
+            WindowPosition::new(0, 300)
+        );
+        assert_eq!(
+            WindowPosition::new(2500, -900).clamp_to_screens(SIZE, &screens),
+            WindowPosition::new(1520, 0)
+        );
+
+        let screens = [screen(0, 0, 1920, 1080), screen(1920, 0, 2560, 1440)];
+        assert_eq!(
+            WindowPosition::new(5000, 1200).clamp_to_screens(SIZE, &screens),
+            WindowPosition::new(4080, 1040)
+        );
+    }
+
+    #[test]
+    fn positions_stay_put_without_screens() {
+        let position = WindowPosition::new(-5000, -5000);
+        assert_eq!(position.clamp_to_screens(SIZE, &[]), position);
+    }
 }
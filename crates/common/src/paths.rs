@@ -69,6 +69,13 @@ impl AppPaths {
         self.data_dir.join("scripts")
     }
 
+    /// Sound effects for one pet, overriding the builtin sounds with the
+    /// same name
+    #[must_use]
+    pub fn sounds_dir(&self, pet_id: &uuid::Uuid) -> PathBuf {
+        self.data_dir.join("sounds").join(pet_id.to_string())
+    }
+
     #[must_use]
     pub fn logs_dir(&self) -> PathBuf {
         self.data_dir.join("logs")
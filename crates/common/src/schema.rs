@@ -0,0 +1,371 @@
+//! JSON Schemas for the types external tools read and write, generated
+//! from the Rust definitions so they follow the serde representation
+
+use crate::config::AppConfig;
+use crate::error::Result;
+use crate::ipc::IpcEnvelope;
+use crate::script::{Action, BehaviorScript};
+use schemars::{JsonSchema, Schema};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A type with an exported JSON Schema
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaKind {
+    BehaviorScript,
+    Action,
+    IpcEnvelope,
+    AppConfig,
+}
+
+impl SchemaKind {
+    pub const ALL: [Self; 4] = [
+        Self::BehaviorScript,
+        Self::Action,
+        Self::IpcEnvelope,
+        Self::AppConfig,
+    ];
+
+    /// Name used on the command line and in file names
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::BehaviorScript => "behavior-script",
+            Self::Action => "action",
+            Self::IpcEnvelope => "ipc-envelope",
+            Self::AppConfig => "app-config",
+        }
+    }
+
+    /// Look a schema up by its `name`
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    /// File name the schema is exported as
+    #[must_use]
+    pub fn file_name(self) -> String {
+        format!("{}.schema.json", self.name())
+    }
+
+    /// Generate the schema
+    #[must_use]
+    pub fn schema(self) -> Schema {
+        match self {
+            Self::BehaviorScript => schema_for::<BehaviorScript>(),
+            Self::Action => schema_for::<Action>(),
+            Self::IpcEnvelope => schema_for::<IpcEnvelope>(),
+            Self::AppConfig => schema_for::<AppConfig>(),
+        }
+    }
+
+    /// Generate the schema as pretty-printed JSON
+    pub fn to_json(self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&self.schema())?)
+    }
+}
+
+impl std::fmt::Display for SchemaKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+fn schema_for<T: JsonSchema>() -> Schema {
+    schemars::generate::SchemaSettings::draft2020_12()
+        .into_generator()
+        .into_root_schema_for::<T>()
+}
+
+/// Write every schema into `dir`, returning the written paths
+pub fn export_all(dir: &Path) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir)?;
+    SchemaKind::ALL
+        .into_iter()
+        .map(|kind| {
+            let path = dir.join(kind.file_name());
+            fs::write(&path, kind.to_json()?)?;
+            Ok(path)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipc::{IpcMessage, ProcessId, ScriptSource};
+    use crate::models::Pet;
+    use crate::script::{
+        BranchCase, Condition, Easing, Keyframe, KeyframeTime, LookTarget, Repeat,
+        SCRIPT_SCHEMA_VERSION, ScriptArgs, ScriptCall, ScriptChoice, Track,
+    };
+    use crate::triggers::{Trigger, TriggerTable};
+    use serde::Serialize;
+    use serde::de::DeserializeOwned;
+    use serde_json::Value;
+
+    /// Serialize `value`, check it against the schema, and check it
+    /// deserializes back to the same JSON
+    fn assert_in_sync<T: Serialize + DeserializeOwned>(kind: SchemaKind, value: &T) {
+        let schema = kind.schema();
+        let validator = jsonschema::validator_for(schema.as_value()).unwrap();
+
+        let json = serde_json::to_value(value).unwrap();
+        let errors: Vec<String> = validator
+            .iter_errors(&json)
+            .map(|e| format!("{} at {}", e, e.instance_path()))
+            .collect();
+        assert!(errors.is_empty(), "{kind}: {json}\n{}", errors.join("\n"));
+
+        let round_tripped: T = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(round_tripped).unwrap(), json);
+    }
+
+    fn assert_rejected(kind: SchemaKind, json: Value) {
+        let validator = jsonschema::validator_for(kind.schema().as_value()).unwrap();
+        assert!(!validator.is_valid(&json), "{kind} accepted {json}");
+    }
+
+    fn all_actions() -> Vec<Action> {
+        vec![
+            Action::play_animation("idle"),
+            Action::PlayAnimation {
+                name: "jump".to_string(),
+                crossfade: Some(0.1),
+                looping: Some(false),
+                speed: Some(1.5),
+            },
+            Action::MoveTo {
+                x: 0.5,
+                y: -0.5,
+                duration: Some(2.0),
+                easing: Some(Easing::ElasticOut),
+            },
+            Action::Scale {
+                factor: 1.2,
+                duration: None,
+                easing: None,
+            },
+            Action::SetExpression {
+                expression: "smile".to_string(),
+                duration: Some(0.5),
+                easing: None,
+            },
+            Action::Spin {
+                turns: 2.0,
+                duration: None,
+                easing: Some(Easing::Linear),
+            },
+            Action::Wait { duration: 1.0 },
+            Action::LookAt {
+                target: LookTarget::Forward,
+                duration: Some(1.0),
+            },
+            Action::TrackCursor { enabled: true },
+            Action::FollowCursor {
+                speed: None,
+                distance: Some(100.0),
+            },
+            Action::PlaySound {
+                name: "quack".to_string(),
+                volume: Some(0.5),
+            },
+            Action::Say {
+                text: "Hello!".to_string(),
+                duration: Some(2.5),
+            },
+            Action::Call {
+                script: "walk_to".to_string(),
+                args: [("x".to_string(), 0.3.into())].into(),
+            },
+            Action::Random {
+                scripts: vec!["walk".into(), ScriptChoice::weighted("idle", 2.0)],
+            },
+            Action::Branch {
+                cases: vec![BranchCase {
+                    when: Condition::All {
+                        conditions: vec![
+                            Condition::TimeOfDay {
+                                from: 22.0,
+                                to: 6.0,
+                            },
+                            Condition::Not {
+                                condition: Box::new(Condition::Mood {
+                                    min: Some(0.8),
+                                    max: None,
+                                }),
+                            },
+                        ],
+                    },
+                    scripts: vec!["sleep".into()],
+                }],
+                otherwise: vec!["idle".into()],
+            },
+            Action::Bounce {
+                height: 0.2,
+                duration: Some(0.5),
+                easing: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn every_schema_exports() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let paths = export_all(dir.path()).unwrap();
+        assert_eq!(paths.len(), SchemaKind::ALL.len());
+        for (kind, path) in SchemaKind::ALL.into_iter().zip(paths) {
+            let content = fs::read_to_string(path).unwrap();
+            let schema: Value = serde_json::from_str(&content).unwrap();
+            assert!(schema.get("$schema").is_some(), "{kind}");
+            assert_eq!(SchemaKind::from_name(kind.name()), Some(kind));
+        }
+    }
+
+    #[test]
+    fn actions_match_schema() {
+        for action in all_actions() {
+            assert_in_sync(SchemaKind::Action, &action);
+        }
+        assert_rejected(
+            SchemaKind::Action,
+            serde_json::json!({"type": "PlayAnimation", "name": "idle"}),
+        );
+        assert_rejected(SchemaKind::Action, serde_json::json!({"type": "move_to"}));
+    }
+
+    #[test]
+    fn scripts_match_schema() {
+        for script in BehaviorScript::builtin_scripts() {
+            assert_in_sync(SchemaKind::BehaviorScript, &script);
+        }
+
+        let script = BehaviorScript {
+            schema_version: SCRIPT_SCHEMA_VERSION,
+            id: "everything".to_string(),
+            duration: None,
+            keyframes: all_actions()
+                .into_iter()
+                .map(|action| Keyframe {
+                    time: KeyframeTime::At(0.0),
+                    action,
+                })
+                .collect(),
+            tracks: vec![Track {
+                name: "effects".to_string(),
+                keyframes: vec![],
+            }],
+            repeat: Some(Repeat { times: 3 }),
+            next: Some(ScriptCall::with_args(
+                "walk_to",
+                [("x".to_string(), 1.0.into())].into(),
+            )),
+            interruptible: false,
+        };
+        assert_in_sync(SchemaKind::BehaviorScript, &script);
+        assert_rejected(
+            SchemaKind::BehaviorScript,
+            serde_json::json!({"id": "x", "duration": null, "next": null}),
+        );
+    }
+
+    #[test]
+    fn ipc_messages_match_schema() {
+        let messages = vec![
+            IpcMessage::PetClicked,
+            IpcMessage::PetDoubleClicked,
+            IpcMessage::PetRightClicked,
+            IpcMessage::PetLongPressed,
+            IpcMessage::PetHovered { hovering: true },
+            IpcMessage::PetScrolled { delta: -1.5 },
+            IpcMessage::PetDragStarted { x: 0, y: 0 },
+            IpcMessage::PetDragCompleted {
+                x: 10,
+                y: -20,
+                start_x: 0,
+                start_y: 0,
+                duration: 0.75,
+            },
+            IpcMessage::AnimationsAvailable {
+                animations: vec!["idle".to_string()],
+                expressions: vec!["smile".to_string()],
+            },
+            IpcMessage::ExecuteScript {
+                script: BehaviorScript::happy().into(),
+                args: ScriptArgs::new(),
+            },
+            IpcMessage::ExecuteScript {
+                script: ScriptSource::Library("walk_to".to_string()),
+                args: ScriptArgs::from([("x".to_string(), 0.5.into())]),
+            },
+            IpcMessage::AiThinking { is_thinking: true },
+            IpcMessage::Say {
+                text: "Hello!".to_string(),
+                duration: None,
+            },
+            IpcMessage::TogglePetVisibility,
+            IpcMessage::PetVisibilityChanged { visible: false },
+            IpcMessage::SetPetVisible { visible: false },
+            IpcMessage::UpdateAppearance {
+                pet_scale: 1.5,
+                opacity: 0.8,
+                always_on_top: true,
+                click_through: false,
+            },
+            IpcMessage::UpdateAudio {
+                volume: 0.5,
+                muted: true,
+            },
+            IpcMessage::UpdateTriggers {
+                triggers: TriggerTable::default(),
+            },
+            IpcMessage::SwapPet {
+                pet: Pet::new(
+                    "Duck".to_string(),
+                    "A duck".to_string(),
+                    "models/duck.glb".into(),
+                ),
+            },
+            IpcMessage::UserTextInput {
+                text: "hello".to_string(),
+            },
+            IpcMessage::Shutdown,
+        ];
+        for message in messages {
+            let envelope = IpcEnvelope::new(ProcessId::Brain, ProcessId::Theater, message);
+            assert_in_sync(SchemaKind::IpcEnvelope, &envelope);
+        }
+        assert_rejected(
+            SchemaKind::IpcEnvelope,
+            serde_json::json!({
+                "source": "Brain",
+                "target": "Theater",
+                "payload": {"type": "pet_clicked"},
+                "timestamp": 0,
+            }),
+        );
+    }
+
+    #[test]
+    fn app_config_matches_schema() {
+        let mut config = AppConfig::default();
+        assert_in_sync(SchemaKind::AppConfig, &config);
+
+        let mut pet = Pet::new(
+            "Cat".to_string(),
+            "A cat".to_string(),
+            PathBuf::from("/tmp/cat.glb"),
+        );
+        pet.triggers.reactions.insert(
+            Trigger::DoubleClick,
+            vec![ScriptChoice::weighted("spin", 2.0), "happy".into()],
+        );
+        pet.camera.facing = 90.0;
+        config.active_pet = Some(pet.id);
+        config.add_pet(pet);
+        config.ai.api_key = Some("key".to_string());
+        config.general.unload_hidden_after = Some(30);
+        assert_in_sync(SchemaKind::AppConfig, &config);
+    }
+}
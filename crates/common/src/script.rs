@@ -1,120 +1,2201 @@
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A behavior script.
+///
+/// `keyframes` forms the main track. Additional `tracks` play in parallel
+/// with it, each advancing through its own keyframes. Scripts with a
+/// `duration` complete when it elapses; scripts without one complete
+/// once every track has run out of keyframes. With `repeat`, the script
+/// plays that many times before completing.
+///
+/// Scripts written before `schema_version` existed are version 1 and are
+/// upgraded to [`SCRIPT_SCHEMA_VERSION`] as they are loaded.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct BehaviorScript {
+    #[serde(default = "unversioned_schema_version")]
+    pub schema_version: u32,
     pub id: String,
     pub duration: Option<f32>,
+    #[serde(default)]
     pub keyframes: Vec<Keyframe>,
-    pub next: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tracks: Vec<Track>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat: Option<Repeat>,
+    pub next: Option<ScriptCall>,
     pub interruptible: bool,
 }
 
 impl BehaviorScript {
-    /// Load a behavior script from a RON file
-    pub fn load_from_ron<P: AsRef<Path>>(path: P) -> Result<Self, ScriptError> {
-        let content = fs::read_to_string(&path).map_err(|e| ScriptError::Io {
-            path: path.as_ref().display().to_string(),
+    /// Load a behavior script from a RON, TOML or JSON file, upgrading it
+    /// to the current schema version.
+    ///
+    /// The format is taken from the file extension, or detected from the
+    /// content if the extension is not recognised.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ScriptError> {
+        Self::load_versioned(path).map(|(script, _)| script)
+    }
+
+    /// Load a behavior script, also returning the schema version the file
+    /// was written in
+    pub fn load_versioned<P: AsRef<Path>>(path: P) -> Result<(Self, u32), ScriptError> {
+        Self::load_migrated(path.as_ref(), &MIGRATIONS)
+    }
+
+    fn load_migrated(path: &Path, migrations: &[Migration]) -> Result<(Self, u32), ScriptError> {
+        let content = read_script_file(path)?;
+        let format =
+            ScriptFormat::from_path(path).unwrap_or_else(|| ScriptFormat::detect(&content));
+        Self::parse_migrated(&content, format, migrations).map_err(|e| e.with_path(path))
+    }
+
+    /// Upgrade a script file to the current schema version, keeping the
+    /// original next to it as `<file>.v<version>.bak`.
+    ///
+    /// Returns the version the file was upgraded from, or `None` if it was
+    /// already current and has been left untouched.
+    pub fn upgrade_file<P: AsRef<Path>>(path: P) -> Result<Option<u32>, ScriptError> {
+        Self::upgrade_file_migrated(path.as_ref(), &MIGRATIONS)
+    }
+
+    fn upgrade_file_migrated(
+        path: &Path,
+        migrations: &[Migration],
+    ) -> Result<Option<u32>, ScriptError> {
+        let (script, version) = Self::load_migrated(path, migrations)?;
+        if version == latest_schema_version(migrations) {
+            return Ok(None);
+        }
+
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(format!(".v{version}.bak"));
+        let backup = PathBuf::from(backup);
+        fs::copy(path, &backup).map_err(|e| ScriptError::Io {
+            path: backup,
             source: e,
         })?;
-        Self::parse_ron(&content)
+        script.save(path)?;
+        Ok(Some(version))
+    }
+
+    /// Save the script, in the format given by the file extension
+    /// (TOML if it is not recognised)
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ScriptError> {
+        let path = path.as_ref();
+        let format = ScriptFormat::from_path(path).unwrap_or(ScriptFormat::Toml);
+        let content = format.serialize(self)?;
+        fs::write(path, content).map_err(|e| ScriptError::Io {
+            path: path.to_path_buf(),
+            source: e,
+        })
+    }
+
+    /// Parse a behavior script in the given format, upgrading it to the
+    /// current schema version
+    pub fn parse(content: &str, format: ScriptFormat) -> Result<Self, ScriptError> {
+        Self::parse_versioned(content, format).map(|(script, _)| script)
+    }
+
+    /// Parse a behavior script, also returning the schema version it was
+    /// written in
+    pub fn parse_versioned(
+        content: &str,
+        format: ScriptFormat,
+    ) -> Result<(Self, u32), ScriptError> {
+        Self::parse_migrated(content, format, &MIGRATIONS)
+    }
+
+    /// Parse a behavior script, upgrading it with `migrations` rather than
+    /// this build's own
+    fn parse_migrated(
+        content: &str,
+        format: ScriptFormat,
+        migrations: &[Migration],
+    ) -> Result<(Self, u32), ScriptError> {
+        let latest = latest_schema_version(migrations);
+        let parse_error = |message: String| ScriptError::Parse {
+            path: None,
+            format,
+            line: None,
+            column: None,
+            message,
+            source: None,
+        };
+
+        let document: serde_json::Value = format.parse(content)?;
+        let version = match document.get("schema_version") {
+            None => unversioned_schema_version(),
+            Some(version) => version
+                .as_u64()
+                .and_then(|v| u32::try_from(v).ok())
+                .filter(|&v| v > 0)
+                .ok_or_else(|| parse_error(format!("invalid schema_version {version}")))?,
+        };
+
+        match version.cmp(&latest) {
+            std::cmp::Ordering::Greater => Err(ScriptError::UnsupportedVersion {
+                path: None,
+                version,
+            }),
+            // Parse the text itself so errors keep their positions
+            std::cmp::Ordering::Equal => Ok((format.parse(content)?, version)),
+            std::cmp::Ordering::Less => {
+                // Start from the typed script where the old document still
+                // fits the current types: reading RON straight into a
+                // `Value` drops unit variants such as `easing: cubic_out`
+                let typed = format.parse::<Self>(content);
+                let mut document = match &typed {
+                    Ok(script) => {
+                        serde_json::to_value(script).map_err(|e| parse_error(e.to_string()))?
+                    }
+                    Err(_) => document,
+                };
+                migrate(&mut document, version, migrations);
+                match serde_json::from_value(document) {
+                    Ok(script) => Ok((script, version)),
+                    // The upgraded document is still invalid; report the
+                    // problem against the original text where possible
+                    Err(e) => Err(typed.err().unwrap_or_else(|| parse_error(e.to_string()))),
+                }
+            }
+        }
+    }
+
+    /// Serialize the script to the given format
+    pub fn to_format(&self, format: ScriptFormat) -> Result<String, ScriptError> {
+        format.serialize(self)
+    }
+
+    /// Load a behavior script from a RON file
+    pub fn load_from_ron<P: AsRef<Path>>(path: P) -> Result<Self, ScriptError> {
+        let path = path.as_ref();
+        let content = read_script_file(path)?;
+        Self::parse_ron(&content).map_err(|e| e.with_path(path))
     }
 
     /// Parse a behavior script from a RON string
     pub fn parse_ron(content: &str) -> Result<Self, ScriptError> {
-        ron::from_str(content).map_err(ScriptError::Parse)
+        Self::parse(content, ScriptFormat::Ron)
     }
 
     /// Serialize the script to RON format
     pub fn to_ron(&self) -> Result<String, ScriptError> {
-        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
-            .map_err(ScriptError::Serialize)
+        self.to_format(ScriptFormat::Ron)
+    }
+}
+
+/// File formats scripts can be written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScriptFormat {
+    Ron,
+    Toml,
+    Json,
+}
+
+impl ScriptFormat {
+    pub const ALL: [Self; 3] = [Self::Ron, Self::Toml, Self::Json];
+
+    /// File extension for the format, without the dot
+    #[must_use]
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Ron => "ron",
+            Self::Toml => "toml",
+            Self::Json => "json",
+        }
+    }
+
+    /// Format matching the path's extension, if any
+    #[must_use]
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        Self::ALL
+            .into_iter()
+            .find(|format| format.extension().eq_ignore_ascii_case(extension))
+    }
+
+    /// Guess the format from the first meaningful line of `content`
+    #[must_use]
+    pub fn detect(content: &str) -> Self {
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() {
+                continue;
+            }
+            // `#` starts a TOML comment, `#![...]` a RON extension attribute
+            if line.starts_with("#![") || line.starts_with("//") || line.starts_with("/*") {
+                return Self::Ron;
+            }
+            if line.starts_with('#') {
+                continue;
+            }
+            return match line.chars().next() {
+                Some('{') => Self::Json,
+                Some('(') => Self::Ron,
+                Some('[') => Self::Toml,
+                // `key = value` in TOML, `Name(` in RON
+                _ => match (line.find('='), line.find('(')) {
+                    (_, None) => Self::Toml,
+                    (Some(eq), Some(paren)) if eq < paren => Self::Toml,
+                    _ => Self::Ron,
+                },
+            };
+        }
+        Self::Toml
+    }
+
+    /// Parse a script or template in this format
+    pub fn parse<T: DeserializeOwned>(self, content: &str) -> Result<T, ScriptError> {
+        let parse_error =
+            |line, column, message, source: Box<dyn std::error::Error + Send + Sync>| {
+                ScriptError::Parse {
+                    path: None,
+                    format: self,
+                    line,
+                    column,
+                    message,
+                    source: Some(source),
+                }
+            };
+        match self {
+            Self::Ron => ron::from_str(content).map_err(|e| {
+                parse_error(
+                    Some(e.span.start.line),
+                    Some(e.span.start.col),
+                    e.code.to_string(),
+                    Box::new(e),
+                )
+            }),
+            Self::Toml => toml::from_str(content).map_err(|e| {
+                let (line, column) = e
+                    .span()
+                    .map(|span| line_column(content, span.start))
+                    .unzip();
+                parse_error(line, column, e.message().to_string(), Box::new(e))
+            }),
+            Self::Json => serde_json::from_str(content).map_err(|e| {
+                // serde_json appends the position, which we print ourselves
+                let position = format!(" at line {} column {}", e.line(), e.column());
+                let message = e.to_string();
+                let message = message
+                    .strip_suffix(&position)
+                    .unwrap_or(&message)
+                    .to_string();
+                parse_error(Some(e.line()), Some(e.column()), message, Box::new(e))
+            }),
+        }
+    }
+
+    /// Serialize a script or template to this format
+    pub fn serialize<T: Serialize>(self, value: &T) -> Result<String, ScriptError> {
+        let serialize_error = |message: String| ScriptError::Serialize {
+            format: self,
+            message,
+        };
+        match self {
+            Self::Ron => ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+                .map_err(|e| serialize_error(e.to_string())),
+            Self::Toml => toml::to_string_pretty(value).map_err(|e| serialize_error(e.to_string())),
+            Self::Json => {
+                serde_json::to_string_pretty(value).map_err(|e| serialize_error(e.to_string()))
+            }
+        }
+    }
+
+    /// Read and parse a file, taking the format from its extension or,
+    /// failing that, its content
+    pub fn load<T: DeserializeOwned>(path: &Path) -> Result<T, ScriptError> {
+        let content = read_script_file(path)?;
+        let format = Self::from_path(path).unwrap_or_else(|| Self::detect(&content));
+        format.parse(&content).map_err(|e| e.with_path(path))
+    }
+}
+
+impl std::fmt::Display for ScriptFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ron => write!(f, "RON"),
+            Self::Toml => write!(f, "TOML"),
+            Self::Json => write!(f, "JSON"),
+        }
+    }
+}
+
+/// Schema version of scripts written by this build
+pub const SCRIPT_SCHEMA_VERSION: u32 = 1;
+
+/// Version of scripts that predate the `schema_version` field
+fn unversioned_schema_version() -> u32 {
+    1
+}
+
+/// Upgrades a script document from one schema version to the next
+type Migration = fn(&mut serde_json::Value);
+
+/// `MIGRATIONS[n]` upgrades a version `n + 1` document to version `n + 2`
+const MIGRATIONS: [Migration; SCRIPT_SCHEMA_VERSION as usize - 1] = [];
+
+/// The schema version `migrations` upgrade scripts to
+fn latest_schema_version(migrations: &[Migration]) -> u32 {
+    migrations.len() as u32 + 1
+}
+
+/// Upgrade a script document written in `version` step by step to the
+/// latest schema version
+fn migrate(document: &mut serde_json::Value, version: u32, migrations: &[Migration]) {
+    for step in migrations.iter().skip(version.saturating_sub(1) as usize) {
+        step(document);
+    }
+    if let Some(fields) = document.as_object_mut() {
+        fields.insert(
+            "schema_version".to_string(),
+            latest_schema_version(migrations).into(),
+        );
     }
 }
 
+fn read_script_file(path: &Path) -> Result<String, ScriptError> {
+    fs::read_to_string(path).map_err(|e| ScriptError::Io {
+        path: path.to_path_buf(),
+        source: e,
+    })
+}
+
+/// 1-based line and column of a byte offset
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+    (line, column)
+}
+
 /// Errors that can occur when loading or saving scripts
 #[derive(Debug)]
 pub enum ScriptError {
     Io {
-        path: String,
+        path: PathBuf,
         source: std::io::Error,
     },
-    Parse(ron::error::SpannedError),
-    Serialize(ron::Error),
+    Parse {
+        /// File the script was read from, if any
+        path: Option<PathBuf>,
+        format: ScriptFormat,
+        /// 1-based position of the error, when the parser reports one
+        line: Option<usize>,
+        column: Option<usize>,
+        message: String,
+        /// Error reported by the parser itself
+        source: Option<Box<dyn std::error::Error + Send + Sync>>,
+    },
+    Serialize {
+        format: ScriptFormat,
+        message: String,
+    },
+    /// The script was written by a newer version of the application
+    UnsupportedVersion { path: Option<PathBuf>, version: u32 },
+}
+
+impl ScriptError {
+    /// File the error relates to, if known
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::Io { path, .. } => Some(path),
+            Self::Parse { path, .. } | Self::UnsupportedVersion { path, .. } => path.as_deref(),
+            Self::Serialize { .. } => None,
+        }
+    }
+
+    /// Attach the file a parse error came from
+    #[must_use]
+    pub fn with_path(mut self, file: &Path) -> Self {
+        if let Self::Parse { ref mut path, .. } | Self::UnsupportedVersion { ref mut path, .. } =
+            self
+        {
+            *path = Some(file.to_path_buf());
+        }
+        self
+    }
 }
 
 impl std::fmt::Display for ScriptError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Io { path, source } => {
-                write!(f, "failed to read script file '{}': {}", path, source)
+                write!(
+                    f,
+                    "failed to read script file '{}': {}",
+                    path.display(),
+                    source
+                )
+            }
+            Self::Parse {
+                path,
+                format,
+                line,
+                column,
+                message,
+                ..
+            } => {
+                if let Some(path) = path {
+                    write!(f, "{}:", path.display())?;
+                }
+                if let Some(line) = line {
+                    write!(f, "{line}:")?;
+                    if let Some(column) = column {
+                        write!(f, "{column}:")?;
+                    }
+                }
+                if path.is_some() || line.is_some() {
+                    write!(f, " ")?;
+                }
+                write!(f, "failed to parse {format} script: {message}")
+            }
+            Self::Serialize { format, message } => {
+                write!(f, "failed to serialize script to {format}: {message}")
+            }
+            Self::UnsupportedVersion { path, version } => {
+                if let Some(path) = path {
+                    write!(f, "{}: ", path.display())?;
+                }
+                write!(
+                    f,
+                    "script schema version {version} is newer than the supported version {SCRIPT_SCHEMA_VERSION}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Parse { source, .. } => source.as_deref().map(|e| e as _),
+            Self::Serialize { .. } | Self::UnsupportedVersion { .. } => None,
+        }
+    }
+}
+
+/// How many times a script plays in total before it completes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Repeat {
+    pub times: u32,
+}
+
+impl BehaviorScript {
+    /// Number of times the script plays before it completes
+    #[must_use]
+    pub fn play_count(&self) -> u32 {
+        self.repeat.map_or(1, |r| r.times)
+    }
+}
+
+/// A named keyframe sequence, e.g. "animation", "transform", "effects"
+/// or "speech"
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Track {
+    pub name: String,
+    pub keyframes: Vec<Keyframe>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Keyframe {
+    pub time: KeyframeTime,
+    pub action: Action,
+}
+
+/// When a keyframe fires.
+///
+/// Written as seconds from the start of the script, or as a string
+/// relative to the previous keyframe on the same track: `"+0.5"` fires
+/// 0.5s after that keyframe fired, `"after"` once its action has finished
+/// and `"after+0.5"` 0.5s after that. Relative keyframes following a
+/// `Wait` are measured from the end of the wait.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(try_from = "KeyframeTimeRepr", into = "KeyframeTimeRepr")]
+pub enum KeyframeTime {
+    /// Seconds from the start of the script
+    At(f32),
+    /// Seconds after the previous keyframe fired
+    AfterStart(f32),
+    /// Seconds after the previous keyframe's action finished
+    AfterEnd(f32),
+}
+
+impl KeyframeTime {
+    /// The time in seconds for absolute times, or the offset for
+    /// relative ones
+    #[must_use]
+    pub fn seconds(self) -> f32 {
+        match self {
+            Self::At(seconds) | Self::AfterStart(seconds) | Self::AfterEnd(seconds) => seconds,
+        }
+    }
+
+    #[must_use]
+    pub fn is_relative(self) -> bool {
+        !matches!(self, Self::At(_))
+    }
+}
+
+impl From<f32> for KeyframeTime {
+    fn from(seconds: f32) -> Self {
+        Self::At(seconds)
+    }
+}
+
+impl std::fmt::Display for KeyframeTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::At(seconds) => write!(f, "{seconds}"),
+            Self::AfterStart(offset) => write!(f, "+{offset}"),
+            Self::AfterEnd(0.0) => write!(f, "after"),
+            Self::AfterEnd(offset) => write!(f, "after+{offset}"),
+        }
+    }
+}
+
+impl std::str::FromStr for KeyframeTime {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        let offset = |offset: &str| {
+            let offset = offset.trim();
+            offset
+                .strip_suffix('s')
+                .unwrap_or(offset)
+                .trim_end()
+                .parse::<f32>()
+                .map_err(|_| format!("invalid keyframe time '{text}'"))
+        };
+        if let Some(rest) = text.strip_prefix("after") {
+            match rest.trim_start().strip_prefix('+') {
+                Some(delay) => offset(delay).map(Self::AfterEnd),
+                None if rest.trim().is_empty() => Ok(Self::AfterEnd(0.0)),
+                None => Err(format!("invalid keyframe time '{text}'")),
+            }
+        } else if let Some(delay) = text.strip_prefix('+') {
+            offset(delay).map(Self::AfterStart)
+        } else {
+            offset(text).map(Self::At)
+        }
+    }
+}
+
+/// Keyframe times as written: absolute seconds, or a relative time string
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum KeyframeTimeRepr {
+    Seconds(f32),
+    Relative(#[schemars(regex(pattern = r"^\s*(\+|after)"))] String),
+}
+
+impl TryFrom<KeyframeTimeRepr> for KeyframeTime {
+    type Error = String;
+
+    fn try_from(repr: KeyframeTimeRepr) -> Result<Self, Self::Error> {
+        match repr {
+            KeyframeTimeRepr::Seconds(seconds) => Ok(Self::At(seconds)),
+            KeyframeTimeRepr::Relative(text) => text.parse(),
+        }
+    }
+}
+
+impl From<KeyframeTime> for KeyframeTimeRepr {
+    fn from(time: KeyframeTime) -> Self {
+        match time {
+            KeyframeTime::At(seconds) => Self::Seconds(seconds),
+            relative => Self::Relative(relative.to_string()),
+        }
+    }
+}
+
+/// Where relative keyframe times on a track are measured from
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TimelineAnchor {
+    /// When the previous keyframe fired, or its wait ended
+    pub start: f32,
+    /// When the previous keyframe's action finished
+    pub end: f32,
+}
+
+impl TimelineAnchor {
+    /// When `keyframe` fires, if it follows the keyframes already passed
+    #[must_use]
+    pub fn time_of(&self, keyframe: &Keyframe) -> f32 {
+        match keyframe.time {
+            KeyframeTime::At(seconds) => seconds,
+            KeyframeTime::AfterStart(offset) => self.start + offset,
+            KeyframeTime::AfterEnd(offset) => self.end + offset,
+        }
+    }
+
+    /// Move past `keyframe`, returning when it fires
+    pub fn advance(&mut self, keyframe: &Keyframe) -> f32 {
+        let time = self.time_of(keyframe);
+        let end = time + keyframe.action.timeline_duration();
+        // A wait holds back everything after it on the track
+        let start = match keyframe.action {
+            Action::Wait { .. } => end,
+            _ => time,
+        };
+        *self = Self { start, end };
+        time
+    }
+}
+
+impl BehaviorScript {
+    /// Number of tracks, counting the main `keyframes` list as track 0
+    #[must_use]
+    pub fn track_count(&self) -> usize {
+        1 + self.tracks.len()
+    }
+
+    /// Keyframes of the given track; index 0 is the main track
+    #[must_use]
+    pub fn track_keyframes(&self, index: usize) -> &[Keyframe] {
+        match index {
+            0 => &self.keyframes,
+            i => self.tracks.get(i - 1).map_or(&[], |t| &t.keyframes),
+        }
+    }
+
+    /// When each keyframe on the given track fires, with relative times
+    /// resolved
+    #[must_use]
+    pub fn keyframe_times(&self, index: usize) -> Vec<f32> {
+        let mut anchor = TimelineAnchor::default();
+        self.track_keyframes(index)
+            .iter()
+            .map(|keyframe| anchor.advance(keyframe))
+            .collect()
+    }
+
+    /// Name of the given track; the main track has none
+    #[must_use]
+    pub fn track_name(&self, index: usize) -> Option<&str> {
+        index
+            .checked_sub(1)
+            .and_then(|i| self.tracks.get(i))
+            .map(|t| t.name.as_str())
+    }
+
+    fn track_keyframes_mut(&mut self) -> impl Iterator<Item = &mut Vec<Keyframe>> {
+        std::iter::once(&mut self.keyframes).chain(self.tracks.iter_mut().map(|t| &mut t.keyframes))
+    }
+}
+
+/// Default tween duration for `MoveTo`, in seconds
+pub const DEFAULT_MOVE_DURATION: f32 = 1.0;
+/// Default tween duration for `Scale`, in seconds
+pub const DEFAULT_SCALE_DURATION: f32 = 0.3;
+/// Default tween duration per full `Spin` turn, in seconds
+pub const DEFAULT_SPIN_TURN_DURATION: f32 = 0.8;
+/// Default duration of a single `Bounce`, in seconds
+pub const DEFAULT_BOUNCE_DURATION: f32 = 0.4;
+/// Default blend time for `SetExpression`, in seconds
+pub const DEFAULT_EXPRESSION_DURATION: f32 = 0.25;
+/// Default time `PlayAnimation` crossfades from the previous clip, in
+/// seconds
+pub const DEFAULT_CROSSFADE_DURATION: f32 = 0.25;
+/// Default time `LookAt` keeps looking at its target, in seconds
+pub const DEFAULT_LOOK_DURATION: f32 = 2.0;
+/// Default `FollowCursor` speed, in screen pixels per second
+pub const DEFAULT_FOLLOW_SPEED: f32 = 300.0;
+/// Default distance `FollowCursor` stops at from the cursor, in screen pixels
+pub const DEFAULT_FOLLOW_DISTANCE: f32 = 150.0;
+/// Shortest time a `Say` bubble stays up without an explicit duration
+pub const MIN_SAY_DURATION: f32 = 2.0;
+/// Reading time per character of `Say` text, in seconds
+pub const SAY_SECONDS_PER_CHAR: f32 = 0.06;
+
+/// A single step of a behavior script.
+///
+/// `duration` and `easing` are optional on tweened actions; when omitted
+/// the theater uses the per-action `DEFAULT_*_DURATION` and easing.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Action {
+    /// Play one of the model's animation clips, blending from the clip
+    /// playing before over `crossfade` seconds. Clips loop unless
+    /// `looping` is false, when they stop on their last frame; `speed`
+    /// scales the clip's own playback rate.
+    PlayAnimation {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        crossfade: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        looping: Option<bool>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        speed: Option<f32>,
+    },
+    MoveTo {
+        x: f32,
+        y: f32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        duration: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        easing: Option<Easing>,
+    },
+    Scale {
+        factor: f32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        duration: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        easing: Option<Easing>,
+    },
+    /// Blend the model's morph targets to a named facial expression
+    SetExpression {
+        expression: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        duration: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        easing: Option<Easing>,
+    },
+    Spin {
+        turns: f32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        duration: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        easing: Option<Easing>,
+    },
+    Wait {
+        duration: f32,
+    },
+    /// Turn the pet's head (or the whole model, without a head bone)
+    /// towards `target` for `duration` seconds
+    LookAt {
+        #[serde(default)]
+        target: LookTarget,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        duration: Option<f32>,
+    },
+    /// Keep looking at the cursor until tracking is turned off again
+    TrackCursor {
+        enabled: bool,
+    },
+    /// Move the window across the desktop towards the cursor, at `speed`
+    /// pixels per second, until the pet is within `distance` pixels of it
+    FollowCursor {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        speed: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        distance: Option<f32>,
+    },
+    /// Play a sound effect by name, at `volume` (0 to 1) of the global
+    /// volume
+    PlaySound {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        volume: Option<f32>,
+    },
+    /// Show `text` in a speech bubble above the pet for `duration` seconds,
+    /// after any bubbles already showing
+    Say {
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        duration: Option<f32>,
+    },
+    /// Run another script to completion, then resume this one. The caller
+    /// is paused on every track while the sub-script plays.
+    Call {
+        script: String,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        args: ScriptArgs,
+    },
+    Random {
+        scripts: Vec<ScriptChoice>,
+    },
+    /// Switch to one of the `scripts` of the first case whose condition
+    /// holds, or to one of `otherwise` if none does
+    Branch {
+        cases: Vec<BranchCase>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        otherwise: Vec<ScriptChoice>,
+    },
+    Bounce {
+        height: f32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        duration: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        easing: Option<Easing>,
+    },
+}
+
+impl Action {
+    /// Play the named clip with the default crossfade, looping and speed
+    #[must_use]
+    pub fn play_animation(name: impl Into<String>) -> Self {
+        Self::PlayAnimation {
+            name: name.into(),
+            crossfade: None,
+            looping: None,
+            speed: None,
+        }
+    }
+
+    /// Tween duration in seconds, falling back to the per-action default.
+    /// Returns `None` for actions that are not tweened.
+    #[must_use]
+    pub fn tween_duration(&self) -> Option<f32> {
+        match self {
+            Self::MoveTo { duration, .. } => Some(duration.unwrap_or(DEFAULT_MOVE_DURATION)),
+            Self::Scale { duration, .. } => Some(duration.unwrap_or(DEFAULT_SCALE_DURATION)),
+            Self::Spin {
+                turns, duration, ..
+            } => Some(duration.unwrap_or(turns.abs() * DEFAULT_SPIN_TURN_DURATION)),
+            Self::Bounce { duration, .. } => Some(duration.unwrap_or(DEFAULT_BOUNCE_DURATION)),
+            Self::SetExpression { duration, .. } => {
+                Some(duration.unwrap_or(DEFAULT_EXPRESSION_DURATION))
+            }
+            _ => None,
+        }
+    }
+
+    /// How long a `PlayAnimation` crossfades from the previous clip,
+    /// falling back to the default. Returns `None` for other actions.
+    #[must_use]
+    pub fn crossfade_duration(&self) -> Option<f32> {
+        match self {
+            Self::PlayAnimation { crossfade, .. } => {
+                Some(crossfade.unwrap_or(DEFAULT_CROSSFADE_DURATION))
+            }
+            _ => None,
+        }
+    }
+
+    /// How long a `LookAt` holds its gaze, falling back to the default.
+    /// Returns `None` for other actions.
+    #[must_use]
+    pub fn look_duration(&self) -> Option<f32> {
+        match self {
+            Self::LookAt { duration, .. } => Some(duration.unwrap_or(DEFAULT_LOOK_DURATION)),
+            _ => None,
+        }
+    }
+
+    /// How long a `Say` bubble stays up, falling back to a reading time
+    /// based on the text's length. Returns `None` for other actions.
+    #[must_use]
+    pub fn speech_duration(&self) -> Option<f32> {
+        match self {
+            Self::Say { text, duration } => Some(duration.unwrap_or_else(|| reading_time(text))),
+            _ => None,
+        }
+    }
+
+    /// How long the action occupies its track, for keyframes timed
+    /// `"after"` it: the wait for `Wait`, the bubble for `Say`, the gaze
+    /// for `LookAt`, the tween for tweened actions and zero otherwise
+    #[must_use]
+    pub fn timeline_duration(&self) -> f32 {
+        match self {
+            Self::Wait { duration } => *duration,
+            Self::Say { .. } => self.speech_duration().unwrap_or(0.0),
+            Self::LookAt {
+                target: LookTarget::Cursor,
+                ..
+            } => self.look_duration().unwrap_or(0.0),
+            _ => self.tween_duration().unwrap_or(0.0),
+        }
+    }
+
+    /// Easing curve, falling back to the per-action default.
+    /// Returns `None` for actions that are not tweened.
+    #[must_use]
+    pub fn tween_easing(&self) -> Option<Easing> {
+        match self {
+            Self::MoveTo { easing, .. } => Some(easing.unwrap_or(Easing::CubicOut)),
+            Self::Scale { easing, .. } => Some(easing.unwrap_or(Easing::BackOut)),
+            Self::Spin { easing, .. } => Some(easing.unwrap_or(Easing::CubicInOut)),
+            Self::Bounce { easing, .. } => Some(easing.unwrap_or(Easing::Linear)),
+            Self::SetExpression { easing, .. } => Some(easing.unwrap_or(Easing::EaseInOut)),
+            _ => None,
+        }
+    }
+}
+
+/// Time to read `text` in a speech bubble, in seconds
+#[must_use]
+pub fn reading_time(text: &str) -> f32 {
+    (text.chars().count() as f32 * SAY_SECONDS_PER_CHAR).max(MIN_SAY_DURATION)
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+/// Arguments bound to a template's parameters, by name
+pub type ScriptArgs = BTreeMap<String, serde_json::Value>;
+
+/// A reference to a script or template, with template arguments.
+///
+/// Written either as a bare script ID or as `{ script, args }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(from = "ScriptCallRepr", into = "ScriptCallRepr")]
+pub struct ScriptCall {
+    pub script: String,
+    pub args: ScriptArgs,
+}
+
+impl ScriptCall {
+    #[must_use]
+    pub fn with_args(script: impl Into<String>, args: ScriptArgs) -> Self {
+        Self {
+            script: script.into(),
+            args,
+        }
+    }
+
+    /// ID for the script a template call expands to: the template's ID,
+    /// followed by the arguments as canonical JSON when there are any, so
+    /// calls with different arguments get separate scripts
+    #[must_use]
+    pub fn instance_id(&self) -> String {
+        if self.args.is_empty() {
+            return self.script.clone();
+        }
+        // Arguments are sorted by name, so equal calls get equal IDs
+        let args = serde_json::to_string(&self.args).unwrap_or_default();
+        format!("{}{args}", self.script)
+    }
+}
+
+impl From<&str> for ScriptCall {
+    fn from(script: &str) -> Self {
+        Self::with_args(script, ScriptArgs::new())
+    }
+}
+
+impl From<String> for ScriptCall {
+    fn from(script: String) -> Self {
+        Self::with_args(script, ScriptArgs::new())
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum ScriptCallRepr {
+    Id(String),
+    Call {
+        script: String,
+        #[serde(default)]
+        args: ScriptArgs,
+    },
+}
+
+impl From<ScriptCallRepr> for ScriptCall {
+    fn from(repr: ScriptCallRepr) -> Self {
+        match repr {
+            ScriptCallRepr::Id(script) => script.into(),
+            ScriptCallRepr::Call { script, args } => Self::with_args(script, args),
+        }
+    }
+}
+
+impl From<ScriptCall> for ScriptCallRepr {
+    fn from(call: ScriptCall) -> Self {
+        if call.args.is_empty() {
+            Self::Id(call.script)
+        } else {
+            Self::Call {
+                script: call.script,
+                args: call.args,
+            }
+        }
+    }
+}
+
+/// A script to switch to, with a relative weight for random selection.
+///
+/// Written either as a bare script ID (weight 1, no arguments) or as
+/// `{ script, weight, args }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(from = "ScriptChoiceRepr", into = "ScriptChoiceRepr")]
+pub struct ScriptChoice {
+    pub script: String,
+    pub weight: f32,
+    pub args: ScriptArgs,
+}
+
+impl ScriptChoice {
+    #[must_use]
+    pub fn weighted(script: impl Into<String>, weight: f32) -> Self {
+        Self {
+            script: script.into(),
+            weight,
+            args: ScriptArgs::new(),
+        }
+    }
+
+    /// The call this choice makes when selected
+    #[must_use]
+    pub fn call(&self) -> ScriptCall {
+        ScriptCall::with_args(self.script.clone(), self.args.clone())
+    }
+}
+
+impl From<&str> for ScriptChoice {
+    fn from(script: &str) -> Self {
+        Self::weighted(script, default_weight())
+    }
+}
+
+impl From<String> for ScriptChoice {
+    fn from(script: String) -> Self {
+        Self::weighted(script, default_weight())
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum ScriptChoiceRepr {
+    Id(String),
+    Weighted {
+        script: String,
+        #[serde(default = "default_weight")]
+        weight: f32,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        args: ScriptArgs,
+    },
+}
+
+impl From<ScriptChoiceRepr> for ScriptChoice {
+    fn from(repr: ScriptChoiceRepr) -> Self {
+        match repr {
+            ScriptChoiceRepr::Id(script) => script.into(),
+            ScriptChoiceRepr::Weighted {
+                script,
+                weight,
+                args,
+            } => Self {
+                script,
+                weight,
+                args,
+            },
+        }
+    }
+}
+
+impl From<ScriptChoice> for ScriptChoiceRepr {
+    fn from(choice: ScriptChoice) -> Self {
+        if choice.weight == default_weight() && choice.args.is_empty() {
+            Self::Id(choice.script)
+        } else {
+            Self::Weighted {
+                script: choice.script,
+                weight: choice.weight,
+                args: choice.args,
+            }
+        }
+    }
+}
+
+/// One arm of a `Branch` action
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BranchCase {
+    pub when: Condition,
+    pub scripts: Vec<ScriptChoice>,
+}
+
+/// Predicate over the pet's state, evaluated when a `Branch` runs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Condition {
+    /// Local time is within `[from, to)` hours; wraps past midnight
+    /// when `from > to`
+    TimeOfDay {
+        from: f32,
+        to: f32,
+    },
+    /// No interaction for at least `seconds`
+    IdleFor {
+        seconds: f32,
+    },
+    /// At least `count` clicks within the last minute
+    ClicksInLastMinute {
+        count: u32,
+    },
+    /// Mood is within `[min, max]`; either bound may be omitted
+    Mood {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max: Option<f32>,
+    },
+    All {
+        conditions: Vec<Condition>,
+    },
+    Any {
+        conditions: Vec<Condition>,
+    },
+    Not {
+        condition: Box<Condition>,
+    },
+}
+
+/// Snapshot of the pet's state that conditions are evaluated against
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PetState {
+    /// Local time of day in hours, `[0, 24)`
+    pub hour: f32,
+    /// Seconds since the user last interacted with the pet
+    pub idle_seconds: f32,
+    /// Number of clicks within the last minute
+    pub clicks_last_minute: u32,
+    /// Current mood, from 0 (unhappy) to 1 (happy)
+    pub mood: f32,
+}
+
+impl PetState {
+    /// Current local time of day in hours
+    #[must_use]
+    pub fn local_hour() -> f32 {
+        let time = jiff::Zoned::now().time();
+        f32::from(time.hour()) + f32::from(time.minute()) / 60.0 + f32::from(time.second()) / 3600.0
+    }
+}
+
+impl Condition {
+    #[must_use]
+    pub fn evaluate(&self, state: &PetState) -> bool {
+        match self {
+            Self::TimeOfDay { from, to } => {
+                if from <= to {
+                    (*from..*to).contains(&state.hour)
+                } else {
+                    state.hour >= *from || state.hour < *to
+                }
+            }
+            Self::IdleFor { seconds } => state.idle_seconds >= *seconds,
+            Self::ClicksInLastMinute { count } => state.clicks_last_minute >= *count,
+            Self::Mood { min, max } => {
+                min.is_none_or(|min| state.mood >= min) && max.is_none_or(|max| state.mood <= max)
+            }
+            Self::All { conditions } => conditions.iter().all(|c| c.evaluate(state)),
+            Self::Any { conditions } => conditions.iter().any(|c| c.evaluate(state)),
+            Self::Not { condition } => !condition.evaluate(state),
+        }
+    }
+}
+
+impl Action {
+    /// For `Call`, the sub-script to run. Returns `None` for other actions.
+    #[must_use]
+    pub fn sub_script(&self) -> Option<ScriptCall> {
+        match self {
+            Self::Call { script, args } => {
+                Some(ScriptCall::with_args(script.clone(), args.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    /// For `Branch`, the choices of the first case whose condition holds,
+    /// falling back to `otherwise`. Returns `None` for other actions.
+    #[must_use]
+    pub fn branch_choices(&self, state: &PetState) -> Option<&[ScriptChoice]> {
+        let Self::Branch { cases, otherwise } = self else {
+            return None;
+        };
+        Some(
+            cases
+                .iter()
+                .find(|case| case.when.evaluate(state))
+                .map_or(otherwise.as_slice(), |case| case.scripts.as_slice()),
+        )
+    }
+}
+
+/// What `LookAt` turns the pet towards
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LookTarget {
+    /// The mouse cursor
+    #[default]
+    Cursor,
+    /// Straight ahead again, ending any look or cursor tracking
+    Forward,
+}
+
+/// Easing curves for tweened actions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    ElasticIn,
+    ElasticOut,
+    BackIn,
+    BackOut,
+    BounceIn,
+    BounceOut,
+}
+
+impl Easing {
+    /// Map linear progress `t` in `[0, 1]` onto the curve.
+    ///
+    /// Elastic and back curves overshoot, so the result may leave `[0, 1]`.
+    #[must_use]
+    pub fn apply(self, t: f32) -> f32 {
+        use std::f32::consts::TAU;
+
+        const BACK_C1: f32 = 1.70158;
+        const BACK_C3: f32 = BACK_C1 + 1.0;
+        const ELASTIC_C4: f32 = TAU / 3.0;
+
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => t,
+            Self::EaseIn => t * t,
+            Self::EaseOut => 1.0 - (1.0 - t).powi(2),
+            Self::EaseInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+            Self::CubicIn => t * t * t,
+            Self::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Self::CubicInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Self::ElasticIn => {
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else {
+                    -(2.0_f32.powf(10.0 * t - 10.0)) * ((t * 10.0 - 10.75) * ELASTIC_C4).sin()
+                }
+            }
+            Self::ElasticOut => {
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else {
+                    2.0_f32.powf(-10.0 * t) * ((t * 10.0 - 0.75) * ELASTIC_C4).sin() + 1.0
+                }
+            }
+            Self::BackIn => BACK_C3 * t * t * t - BACK_C1 * t * t,
+            Self::BackOut => 1.0 + BACK_C3 * (t - 1.0).powi(3) + BACK_C1 * (t - 1.0).powi(2),
+            Self::BounceIn => 1.0 - bounce_out(1.0 - t),
+            Self::BounceOut => bounce_out(t),
+        }
+    }
+}
+
+fn bounce_out(t: f32) -> f32 {
+    const N1: f32 = 7.5625;
+    const D1: f32 = 2.75;
+
+    if t < 1.0 / D1 {
+        N1 * t * t
+    } else if t < 2.0 / D1 {
+        let t = t - 1.5 / D1;
+        N1 * t * t + 0.75
+    } else if t < 2.5 / D1 {
+        let t = t - 2.25 / D1;
+        N1 * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D1;
+        N1 * t * t + 0.984375
+    }
+}
+
+/// A parameterised script.
+///
+/// `script` is a behavior script whose string values may reference the
+/// declared `params`: a value of exactly `"$name"` naming a parameter is
+/// replaced by the argument itself (so it may bind numbers), and `${name}`
+/// inside a longer string is replaced by the argument's text. Other
+/// strings starting with `$` are left alone, and `$$` is a literal `$`.
+/// The expanded script takes the template's `id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptTemplate {
+    pub id: String,
+    #[serde(default)]
+    pub params: Vec<TemplateParam>,
+    pub script: serde_json::Value,
+}
+
+/// A declared template parameter; parameters without a default must be
+/// bound by every call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateParam {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<serde_json::Value>,
+}
+
+impl TemplateParam {
+    #[must_use]
+    pub fn required(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            default: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_default(name: impl Into<String>, default: impl Into<serde_json::Value>) -> Self {
+        Self {
+            name: name.into(),
+            default: Some(default.into()),
+        }
+    }
+}
+
+/// Errors that can occur when invoking a script or template
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateError {
+    UnknownScript(String),
+    NotATemplate(String),
+    Unbound {
+        template: String,
+        params: Vec<String>,
+    },
+    UnexpectedArguments {
+        template: String,
+        args: Vec<String>,
+    },
+    Invalid {
+        template: String,
+        message: String,
+    },
+}
+
+impl std::fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownScript(id) => write!(f, "unknown script '{id}'"),
+            Self::NotATemplate(id) => {
+                write!(f, "script '{id}' is not a template and takes no arguments")
+            }
+            Self::Unbound { template, params } => write!(
+                f,
+                "template '{template}': unbound parameters: {}",
+                params.join(", ")
+            ),
+            Self::UnexpectedArguments { template, args } => write!(
+                f,
+                "template '{template}': unexpected arguments: {}",
+                args.join(", ")
+            ),
+            Self::Invalid { template, message } => {
+                write!(
+                    f,
+                    "template '{template}' expands to an invalid script: {message}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+impl ScriptTemplate {
+    /// Expand the template with the given arguments, falling back to
+    /// parameter defaults.
+    ///
+    /// Fails if a parameter is left without a value, if the script
+    /// references an undeclared parameter, or if an argument matches no
+    /// parameter.
+    pub fn instantiate(&self, args: &ScriptArgs) -> Result<BehaviorScript, TemplateError> {
+        let unexpected: Vec<String> = args
+            .keys()
+            .filter(|name| !self.params.iter().any(|p| &p.name == *name))
+            .cloned()
+            .collect();
+        if !unexpected.is_empty() {
+            return Err(TemplateError::UnexpectedArguments {
+                template: self.id.clone(),
+                args: unexpected,
+            });
+        }
+
+        let bindings: BTreeMap<&str, &serde_json::Value> = self
+            .params
+            .iter()
+            .filter_map(|p| {
+                args.get(&p.name)
+                    .or(p.default.as_ref())
+                    .map(|value| (p.name.as_str(), value))
+            })
+            .collect();
+
+        let mut unbound = BTreeSet::new();
+        for param in &self.params {
+            if !bindings.contains_key(param.name.as_str()) {
+                unbound.insert(param.name.clone());
+            }
+        }
+
+        let mut body = self.script.clone();
+        substitute(&mut body, &bindings, &mut unbound);
+        if !unbound.is_empty() {
+            return Err(TemplateError::Unbound {
+                template: self.id.clone(),
+                params: unbound.into_iter().collect(),
+            });
+        }
+
+        if let Some(fields) = body.as_object_mut() {
+            fields.insert("id".to_string(), self.id.clone().into());
+        }
+        serde_json::from_value(body).map_err(|e| TemplateError::Invalid {
+            template: self.id.clone(),
+            message: e.to_string(),
+        })
+    }
+
+    /// Walk to a point and back to idle.
+    ///
+    /// Parameters: `x`, `y` (default 0), `duration` (default 1s).
+    #[must_use]
+    pub fn walk_to() -> Self {
+        Self {
+            id: "walk_to".to_string(),
+            params: vec![
+                TemplateParam::required("x"),
+                TemplateParam::with_default("y", 0.0),
+                TemplateParam::with_default("duration", 1.0),
+            ],
+            script: serde_json::json!({
+                "duration": "$duration",
+                "keyframes": [
+                    { "time": 0.0, "action": { "type": "play_animation", "name": "walk" } },
+                    {
+                        "time": 0.0,
+                        "action": {
+                            "type": "move_to",
+                            "x": "$x",
+                            "y": "$y",
+                            "duration": "$duration",
+                        },
+                    },
+                ],
+                "next": "idle",
+                "interruptible": true,
+            }),
+        }
+    }
+
+    #[must_use]
+    pub fn builtin_templates() -> Vec<Self> {
+        vec![Self::walk_to()]
+    }
+}
+
+/// Replace parameter references in every string of `value`, collecting
+/// the names of references that have no binding
+fn substitute(
+    value: &mut serde_json::Value,
+    bindings: &BTreeMap<&str, &serde_json::Value>,
+    unbound: &mut BTreeSet<String>,
+) {
+    match value {
+        serde_json::Value::String(text) => {
+            // Declared parameters without a value are reported already
+            if let Some(&bound) = text.strip_prefix('$').and_then(|name| bindings.get(name)) {
+                *value = bound.clone();
+            } else if text.contains('$') {
+                *text = interpolate(text, bindings, unbound);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                substitute(item, bindings, unbound);
+            }
+        }
+        serde_json::Value::Object(fields) => {
+            for field in fields.values_mut() {
+                substitute(field, bindings, unbound);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Replace `${name}` references in `text` with their arguments' text,
+/// and `$$` with a literal `$`. Any other `$` is left as it is.
+fn interpolate(
+    text: &str,
+    bindings: &BTreeMap<&str, &serde_json::Value>,
+    unbound: &mut BTreeSet<String>,
+) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        if let Some(escaped) = after.strip_prefix('$') {
+            result.push('$');
+            rest = escaped;
+        } else if let Some(reference) = after.strip_prefix('{')
+            && let Some(len) = reference.find('}')
+        {
+            let name = &reference[..len];
+            match bindings.get(name) {
+                Some(serde_json::Value::String(bound)) => result.push_str(bound),
+                Some(bound) => result.push_str(&bound.to_string()),
+                None => {
+                    unbound.insert(name.to_string());
+                }
+            }
+            rest = &reference[len + 1..];
+        } else {
+            result.push('$');
+            rest = after;
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// What a script is validated against.
+///
+/// Checks whose context is `None` are skipped, e.g. animation names
+/// before the model has finished loading.
+#[derive(Debug, Clone, Default)]
+pub struct ValidationContext {
+    /// Animation names available on the current model
+    pub animations: Option<HashSet<String>>,
+    /// Expression names available on the current model
+    pub expressions: Option<HashSet<String>>,
+    /// Sound names available to the current pet
+    pub sounds: Option<HashSet<String>>,
+    /// Script IDs that `next`, `Call`, `Random` and `Branch` may reference
+    pub scripts: Option<HashSet<String>>,
+}
+
+impl ValidationContext {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check animation names against the given list
+    #[must_use]
+    pub fn with_animations<I, S>(mut self, animations: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.animations = Some(animations.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Check expression names against the given list
+    #[must_use]
+    pub fn with_expressions<I, S>(mut self, expressions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.expressions = Some(expressions.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Check sound names against the given list
+    #[must_use]
+    pub fn with_sounds<I, S>(mut self, sounds: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.sounds = Some(sounds.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Check script references against the given IDs
+    #[must_use]
+    pub fn with_scripts<I, S>(mut self, scripts: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.scripts = Some(scripts.into_iter().map(Into::into).collect());
+        self
+    }
+}
+
+/// How serious a validation finding is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The script plays, but probably not as intended
+    Warning,
+    /// The script cannot play correctly and should be rejected
+    Error,
+}
+
+/// The kind of problem found in a script
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiagnosticKind {
+    EmptyId,
+    InvalidDuration(f32),
+    InvalidTime(f32),
+    UnsortedKeyframe { time: f32, previous: f32 },
+    KeyframeAfterDuration { time: f32, duration: f32 },
+    UnknownAnimation(String),
+    UnknownExpression(String),
+    UnknownSound(String),
+    UnknownScript(String),
+    NoChoices,
+    EmptyText,
+    OutOfRange { field: &'static str, value: f32 },
+    DuplicateTrack(String),
+}
+
+impl std::fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::EmptyId => write!(f, "script id is empty"),
+            Self::InvalidDuration(d) => write!(f, "duration {d} must be positive"),
+            Self::InvalidTime(t) => write!(f, "time {t} must be a non-negative number"),
+            Self::UnsortedKeyframe { time, previous } => {
+                write!(
+                    f,
+                    "time {time} is earlier than previous keyframe ({previous})"
+                )
+            }
+            Self::KeyframeAfterDuration { time, duration } => {
+                write!(f, "time {time} is after script duration {duration}")
+            }
+            Self::UnknownAnimation(name) => write!(f, "unknown animation '{name}'"),
+            Self::UnknownExpression(name) => write!(f, "unknown expression '{name}'"),
+            Self::UnknownSound(name) => write!(f, "unknown sound '{name}'"),
+            Self::UnknownScript(id) => write!(f, "unknown script '{id}'"),
+            Self::NoChoices => write!(f, "action has no scripts to choose from"),
+            Self::EmptyText => write!(f, "speech text is empty"),
+            Self::OutOfRange { field, value } => write!(f, "{field} {value} is out of range"),
+            Self::DuplicateTrack(name) => write!(f, "track name '{name}' is used more than once"),
+        }
+    }
+}
+
+/// A single validation finding
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Name of the track the keyframe belongs to; `None` for the main track
+    pub track: Option<String>,
+    /// Index of the offending keyframe, if the finding is keyframe-specific
+    pub keyframe: Option<usize>,
+    pub kind: DiagnosticKind,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let level = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{level}: ")?;
+        if let Some(ref track) = self.track {
+            write!(f, "track '{track}': ")?;
+        }
+        if let Some(index) = self.keyframe {
+            write!(f, "keyframe {index}: ")?;
+        }
+        write!(f, "{}", self.kind)
+    }
+}
+
+/// Result of validating a script
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationReport {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ValidationReport {
+    /// Whether the script has no error-level findings
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        !self
+            .diagnostics
+            .iter()
+            .any(|d| d.severity == Severity::Error)
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Warning)
+    }
+
+    fn push(&mut self, severity: Severity, at: Option<KeyframeRef>, kind: DiagnosticKind) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            track: at.and_then(|at| at.track.map(str::to_string)),
+            keyframe: at.map(|at| at.index),
+            kind,
+        });
+    }
+}
+
+/// Position of a keyframe within a script, used while validating
+#[derive(Clone, Copy)]
+struct KeyframeRef<'a> {
+    track: Option<&'a str>,
+    index: usize,
+}
+
+impl std::fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, diagnostic) in self.diagnostics.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{diagnostic}")?;
+        }
+        Ok(())
+    }
+}
+
+impl BehaviorScript {
+    /// Check the script for problems that would make the theater skip
+    /// or misplay it.
+    #[must_use]
+    pub fn validate(&self, context: &ValidationContext) -> ValidationReport {
+        let mut report = ValidationReport::default();
+
+        if self.id.trim().is_empty() {
+            report.push(Severity::Error, None, DiagnosticKind::EmptyId);
+        }
+
+        if let Some(duration) = self.duration
+            && !(duration.is_finite() && duration > 0.0)
+        {
+            report.push(
+                Severity::Error,
+                None,
+                DiagnosticKind::InvalidDuration(duration),
+            );
+        }
+
+        if let Some(repeat) = self.repeat
+            && repeat.times == 0
+        {
+            report.push(
+                Severity::Error,
+                None,
+                DiagnosticKind::OutOfRange {
+                    field: "times",
+                    value: 0.0,
+                },
+            );
+        }
+
+        if let Some(ref next) = self.next {
+            self.check_script_ref(&next.script, None, context, &mut report);
+        }
+
+        let mut track_names = HashSet::new();
+        for track in &self.tracks {
+            if !track_names.insert(track.name.as_str()) {
+                report.push(
+                    Severity::Warning,
+                    None,
+                    DiagnosticKind::DuplicateTrack(track.name.clone()),
+                );
+            }
+        }
+
+        for track in 0..self.track_count() {
+            self.check_track(track, context, &mut report);
+        }
+
+        report
+    }
+
+    /// Fix problems that have an unambiguous repair, then re-validate.
+    ///
+    /// Negative keyframe times are clamped to zero, keyframes are sorted
+    /// by time within each track, and unknown entries are dropped from
+    /// `Random` actions as long as at least one known script remains.
+    pub fn repair(&mut self, context: &ValidationContext) -> ValidationReport {
+        let id = self.id.clone();
+        let is_known = |choice: &ScriptChoice| {
+            choice.script == id
+                || context
+                    .scripts
+                    .as_ref()
+                    .is_none_or(|known| known.contains(&choice.script))
+        };
+        // Drop unknown choices, unless that would leave none to pick from
+        let prune = |choices: &mut Vec<ScriptChoice>| {
+            if choices.iter().any(is_known) {
+                choices.retain(is_known);
+            }
+        };
+
+        for keyframes in self.track_keyframes_mut() {
+            for keyframe in keyframes.iter_mut() {
+                if let KeyframeTime::At(time) = &mut keyframe.time
+                    && time.is_finite()
+                    && *time < 0.0
+                {
+                    *time = 0.0;
+                }
+                match &mut keyframe.action {
+                    Action::Random { scripts } => prune(scripts),
+                    Action::Branch { cases, otherwise } => {
+                        for case in cases {
+                            prune(&mut case.scripts);
+                        }
+                        prune(otherwise);
+                    }
+                    _ => {}
+                }
+            }
+            // Relative times depend on keyframe order, so only sort tracks
+            // that are entirely absolute
+            if !keyframes.iter().any(|k| k.time.is_relative()) {
+                keyframes.sort_by(|a, b| a.time.seconds().total_cmp(&b.time.seconds()));
+            }
+        }
+
+        self.validate(context)
+    }
+
+    fn check_track(
+        &self,
+        track: usize,
+        context: &ValidationContext,
+        report: &mut ValidationReport,
+    ) {
+        let mut previous: Option<f32> = None;
+        let times = self.keyframe_times(track);
+        for (index, (keyframe, &time)) in self.track_keyframes(track).iter().zip(&times).enumerate()
+        {
+            let at = KeyframeRef {
+                track: self.track_name(track),
+                index,
+            };
+            let written = keyframe.time.seconds();
+            if !(written.is_finite() && written >= 0.0) {
+                report.push(
+                    Severity::Error,
+                    Some(at),
+                    DiagnosticKind::InvalidTime(written),
+                );
+            } else if !time.is_finite() {
+                // Relative to an invalid keyframe, which is reported already
+            } else {
+                if let Some(previous) = previous
+                    && time < previous
+                {
+                    report.push(
+                        Severity::Error,
+                        Some(at),
+                        DiagnosticKind::UnsortedKeyframe { time, previous },
+                    );
+                }
+                if let Some(duration) = self.duration
+                    && time > duration
+                {
+                    report.push(
+                        Severity::Warning,
+                        Some(at),
+                        DiagnosticKind::KeyframeAfterDuration { time, duration },
+                    );
+                }
+                previous = Some(previous.map_or(time, |p| p.max(time)));
+            }
+
+            self.check_action(&keyframe.action, at, context, report);
+        }
+    }
+
+    fn check_script_ref(
+        &self,
+        id: &str,
+        at: Option<KeyframeRef>,
+        context: &ValidationContext,
+        report: &mut ValidationReport,
+    ) {
+        // A script may always refer to itself, even before it is in the library
+        if let Some(ref known) = context.scripts
+            && id != self.id
+            && !known.contains(id)
+        {
+            report.push(
+                Severity::Error,
+                at,
+                DiagnosticKind::UnknownScript(id.to_string()),
+            );
+        }
+    }
+
+    fn check_choices(
+        &self,
+        choices: &[ScriptChoice],
+        at: KeyframeRef,
+        context: &ValidationContext,
+        report: &mut ValidationReport,
+    ) {
+        if choices.is_empty() {
+            report.push(Severity::Error, Some(at), DiagnosticKind::NoChoices);
+        } else if !choices.iter().any(|c| c.weight > 0.0) {
+            report.push(
+                Severity::Error,
+                Some(at),
+                DiagnosticKind::OutOfRange {
+                    field: "weight",
+                    value: 0.0,
+                },
+            );
+        }
+        for choice in choices {
+            self.check_choice(choice, at, context, report);
+        }
+    }
+
+    fn check_choice(
+        &self,
+        choice: &ScriptChoice,
+        at: KeyframeRef,
+        context: &ValidationContext,
+        report: &mut ValidationReport,
+    ) {
+        if !(choice.weight.is_finite() && choice.weight >= 0.0) {
+            report.push(
+                Severity::Error,
+                Some(at),
+                DiagnosticKind::OutOfRange {
+                    field: "weight",
+                    value: choice.weight,
+                },
+            );
+        }
+        self.check_script_ref(&choice.script, Some(at), context, report);
+    }
+
+    fn check_condition(condition: &Condition, at: KeyframeRef, report: &mut ValidationReport) {
+        let mut check_range = |field: &'static str, value: f32, valid: bool| {
+            if !(value.is_finite() && valid) {
+                report.push(
+                    Severity::Error,
+                    Some(at),
+                    DiagnosticKind::OutOfRange { field, value },
+                );
+            }
+        };
+
+        match condition {
+            Condition::TimeOfDay { from, to } => {
+                check_range("from", *from, (0.0..=24.0).contains(from));
+                check_range("to", *to, (0.0..=24.0).contains(to));
+            }
+            Condition::IdleFor { seconds } => check_range("seconds", *seconds, *seconds >= 0.0),
+            Condition::ClicksInLastMinute { .. } => {}
+            Condition::Mood { min, max } => {
+                if let Some(min) = min {
+                    check_range("min", *min, true);
+                }
+                if let Some(max) = max {
+                    check_range("max", *max, true);
+                }
+            }
+            Condition::All { conditions } | Condition::Any { conditions } => {
+                for condition in conditions {
+                    Self::check_condition(condition, at, report);
+                }
+            }
+            Condition::Not { condition } => Self::check_condition(condition, at, report),
+        }
+    }
+
+    fn check_action(
+        &self,
+        action: &Action,
+        at: KeyframeRef,
+        context: &ValidationContext,
+        report: &mut ValidationReport,
+    ) {
+        let mut check_range = |field: &'static str, value: f32, valid: bool| {
+            if !(value.is_finite() && valid) {
+                report.push(
+                    Severity::Error,
+                    Some(at),
+                    DiagnosticKind::OutOfRange { field, value },
+                );
             }
-            Self::Parse(e) => write!(f, "failed to parse RON script: {}", e),
-            Self::Serialize(e) => write!(f, "failed to serialize script to RON: {}", e),
+        };
+
+        if let Action::MoveTo {
+            duration: Some(duration),
+            ..
+        }
+        | Action::Scale {
+            duration: Some(duration),
+            ..
+        }
+        | Action::Spin {
+            duration: Some(duration),
+            ..
+        }
+        | Action::Bounce {
+            duration: Some(duration),
+            ..
+        }
+        | Action::Say {
+            duration: Some(duration),
+            ..
+        }
+        | Action::LookAt {
+            duration: Some(duration),
+            ..
+        } = action
+        {
+            check_range("duration", *duration, *duration > 0.0);
         }
-    }
-}
 
-impl std::error::Error for ScriptError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        match self {
-            Self::Io { source, .. } => Some(source),
-            Self::Parse(e) => Some(e),
-            Self::Serialize(e) => Some(e),
+        match action {
+            Action::PlayAnimation {
+                name,
+                crossfade,
+                speed,
+                ..
+            } => {
+                if let Some(crossfade) = crossfade {
+                    check_range("crossfade", *crossfade, *crossfade >= 0.0);
+                }
+                if let Some(speed) = speed {
+                    check_range("speed", *speed, *speed > 0.0);
+                }
+                if let Some(ref animations) = context.animations
+                    && !animations.contains(name)
+                {
+                    report.push(
+                        Severity::Error,
+                        Some(at),
+                        DiagnosticKind::UnknownAnimation(name.clone()),
+                    );
+                }
+            }
+            Action::MoveTo { x, y, .. } => {
+                check_range("x", *x, true);
+                check_range("y", *y, true);
+            }
+            Action::Scale { factor, .. } => check_range("factor", *factor, *factor > 0.0),
+            Action::Spin { turns, .. } => check_range("turns", *turns, true),
+            Action::Wait { duration } => check_range("duration", *duration, *duration >= 0.0),
+            Action::Bounce { height, .. } => check_range("height", *height, *height >= 0.0),
+            Action::FollowCursor { speed, distance } => {
+                if let Some(speed) = speed {
+                    check_range("speed", *speed, *speed > 0.0);
+                }
+                if let Some(distance) = distance {
+                    check_range("distance", *distance, *distance >= 0.0);
+                }
+            }
+            Action::LookAt { .. } | Action::TrackCursor { .. } => {}
+            Action::PlaySound { name, volume } => {
+                if let Some(volume) = volume {
+                    check_range("volume", *volume, (0.0..=1.0).contains(volume));
+                }
+                // A missing sound only leaves the pet silent
+                if let Some(ref sounds) = context.sounds
+                    && !sounds.contains(name)
+                {
+                    report.push(
+                        Severity::Warning,
+                        Some(at),
+                        DiagnosticKind::UnknownSound(name.clone()),
+                    );
+                }
+            }
+            Action::Say { text, .. } => {
+                if text.trim().is_empty() {
+                    report.push(Severity::Warning, Some(at), DiagnosticKind::EmptyText);
+                }
+            }
+            Action::SetExpression { expression, .. } => {
+                if let Some(ref expressions) = context.expressions
+                    && !expressions.contains(expression)
+                {
+                    report.push(
+                        Severity::Error,
+                        Some(at),
+                        DiagnosticKind::UnknownExpression(expression.clone()),
+                    );
+                }
+            }
+            Action::Call { script, .. } => self.check_script_ref(script, Some(at), context, report),
+            Action::Random { scripts } => self.check_choices(scripts, at, context, report),
+            Action::Branch { cases, otherwise } => {
+                if cases.is_empty() && otherwise.is_empty() {
+                    report.push(Severity::Error, Some(at), DiagnosticKind::NoChoices);
+                }
+                for case in cases {
+                    Self::check_condition(&case.when, at, report);
+                    self.check_choices(&case.scripts, at, context, report);
+                }
+                for choice in otherwise {
+                    self.check_choice(choice, at, context, report);
+                }
+            }
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Keyframe {
-    pub time: f32,
-    pub action: Action,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "type", rename_all = "snake_case")]
-pub enum Action {
-    PlayAnimation { name: String },
-    MoveTo { x: f32, y: f32 },
-    Scale { factor: f32 },
-    SetExpression { expression: String },
-    Spin { turns: f32 },
-    Wait { duration: f32 },
-    Random { scripts: Vec<String> },
-    Bounce { height: f32 },
-}
-
 impl BehaviorScript {
     #[must_use]
     pub fn idle() -> Self {
         Self {
+            schema_version: SCRIPT_SCHEMA_VERSION,
             id: "idle".to_string(),
             duration: Some(3.0),
             keyframes: vec![
                 Keyframe {
-                    time: 0.0,
-                    action: Action::PlayAnimation {
-                        name: "idle".to_string(),
-                    },
+                    time: KeyframeTime::At(0.0),
+                    action: Action::play_animation("idle"),
                 },
                 Keyframe {
-                    time: 0.0,
-                    action: Action::MoveTo { x: 0.0, y: 0.0 },
+                    time: KeyframeTime::At(0.0),
+                    action: Action::MoveTo {
+                        x: 0.0,
+                        y: 0.0,
+                        duration: None,
+                        easing: None,
+                    },
                 },
                 Keyframe {
-                    time: 3.0,
+                    time: KeyframeTime::At(3.0),
                     action: Action::Random {
                         scripts: vec![
-                            "idle".to_string(),
-                            "idle".to_string(),
-                            "walk".to_string(),
-                            "bounce".to_string(),
-                            "spin".to_string(),
+                            ScriptChoice::weighted("idle", 2.0),
+                            "walk".into(),
+                            "bounce".into(),
+                            "spin".into(),
                         ],
                     },
                 },
             ],
+            tracks: Vec::new(),
+            repeat: None,
             next: None,
             interruptible: true,
         }
@@ -123,25 +2204,36 @@ impl BehaviorScript {
     #[must_use]
     pub fn walk() -> Self {
         Self {
+            schema_version: SCRIPT_SCHEMA_VERSION,
             id: "walk".to_string(),
             duration: Some(4.0),
             keyframes: vec![
                 Keyframe {
-                    time: 0.0,
-                    action: Action::PlayAnimation {
-                        name: "walk".to_string(),
-                    },
+                    time: KeyframeTime::At(0.0),
+                    action: Action::play_animation("walk"),
                 },
                 Keyframe {
-                    time: 0.0,
-                    action: Action::MoveTo { x: 0.3, y: 0.0 },
+                    time: KeyframeTime::At(0.0),
+                    action: Action::MoveTo {
+                        x: 0.3,
+                        y: 0.0,
+                        duration: None,
+                        easing: None,
+                    },
                 },
                 Keyframe {
-                    time: 2.0,
-                    action: Action::MoveTo { x: -0.3, y: 0.0 },
+                    time: KeyframeTime::At(2.0),
+                    action: Action::MoveTo {
+                        x: -0.3,
+                        y: 0.0,
+                        duration: None,
+                        easing: None,
+                    },
                 },
             ],
-            next: Some("idle".to_string()),
+            tracks: Vec::new(),
+            repeat: None,
+            next: Some("idle".into()),
             interruptible: true,
         }
     }
@@ -149,31 +2241,38 @@ impl BehaviorScript {
     #[must_use]
     pub fn happy() -> Self {
         Self {
+            schema_version: SCRIPT_SCHEMA_VERSION,
             id: "happy".to_string(),
             duration: Some(2.0),
             keyframes: vec![
                 Keyframe {
-                    time: 0.0,
-                    action: Action::PlayAnimation {
-                        name: "jump".to_string(),
-                    },
+                    time: KeyframeTime::At(0.0),
+                    action: Action::play_animation("jump"),
                 },
                 Keyframe {
-                    time: 0.5,
-                    action: Action::Scale { factor: 1.2 },
+                    time: KeyframeTime::At(0.5),
+                    action: Action::Scale {
+                        factor: 1.2,
+                        duration: None,
+                        easing: None,
+                    },
                 },
                 Keyframe {
-                    time: 1.0,
-                    action: Action::Scale { factor: 1.0 },
+                    time: KeyframeTime::At(1.0),
+                    action: Action::Scale {
+                        factor: 1.0,
+                        duration: None,
+                        easing: None,
+                    },
                 },
                 Keyframe {
-                    time: 1.5,
-                    action: Action::PlayAnimation {
-                        name: "spin".to_string(),
-                    },
+                    time: KeyframeTime::At(1.5),
+                    action: Action::play_animation("spin"),
                 },
             ],
-            next: Some("idle".to_string()),
+            tracks: Vec::new(),
+            repeat: None,
+            next: Some("idle".into()),
             interruptible: false,
         }
     }
@@ -181,14 +2280,15 @@ impl BehaviorScript {
     #[must_use]
     pub fn sleep() -> Self {
         Self {
+            schema_version: SCRIPT_SCHEMA_VERSION,
             id: "sleep".to_string(),
             duration: None,
             keyframes: vec![Keyframe {
-                time: 0.0,
-                action: Action::PlayAnimation {
-                    name: "sleep".to_string(),
-                },
+                time: KeyframeTime::At(0.0),
+                action: Action::play_animation("sleep"),
             }],
+            tracks: Vec::new(),
+            repeat: None,
             next: None,
             interruptible: true,
         }
@@ -197,41 +2297,66 @@ impl BehaviorScript {
     #[must_use]
     pub fn bounce() -> Self {
         Self {
+            schema_version: SCRIPT_SCHEMA_VERSION,
             id: "bounce".to_string(),
             duration: Some(3.0),
             keyframes: vec![
                 Keyframe {
-                    time: 0.0,
-                    action: Action::PlayAnimation {
-                        name: "jump".to_string(),
-                    },
+                    time: KeyframeTime::At(0.0),
+                    action: Action::play_animation("jump"),
                 },
                 Keyframe {
-                    time: 0.0,
-                    action: Action::Bounce { height: 0.15 },
+                    time: KeyframeTime::At(0.0),
+                    action: Action::Bounce {
+                        height: 0.15,
+                        duration: None,
+                        easing: None,
+                    },
                 },
                 Keyframe {
-                    time: 0.5,
-                    action: Action::Bounce { height: 0.25 },
+                    time: KeyframeTime::At(0.5),
+                    action: Action::Bounce {
+                        height: 0.25,
+                        duration: None,
+                        easing: None,
+                    },
                 },
                 Keyframe {
-                    time: 1.2,
-                    action: Action::Bounce { height: 0.2 },
+                    time: KeyframeTime::At(1.2),
+                    action: Action::Bounce {
+                        height: 0.2,
+                        duration: None,
+                        easing: None,
+                    },
                 },
                 Keyframe {
-                    time: 1.9,
-                    action: Action::Scale { factor: 1.15 },
+                    time: KeyframeTime::At(1.9),
+                    action: Action::Scale {
+                        factor: 1.15,
+                        duration: None,
+                        easing: None,
+                    },
                 },
                 Keyframe {
-                    time: 2.2,
-                    action: Action::Scale { factor: 1.0 },
+                    time: KeyframeTime::At(2.2),
+                    action: Action::Scale {
+                        factor: 1.0,
+                        duration: None,
+                        easing: None,
+                    },
                 },
                 Keyframe {
-                    time: 2.5,
-                    action: Action::Bounce { height: 0.1 },
+                    time: KeyframeTime::At(2.5),
+                    action: Action::Bounce {
+                        height: 0.1,
+                        duration: None,
+                        easing: None,
+                    },
                 },
             ],
-            next: Some("idle".to_string()),
+            tracks: Vec::new(),
+            repeat: None,
+            next: Some("idle".into()),
             interruptible: true,
         }
     }
@@ -239,33 +2364,50 @@ impl BehaviorScript {
     #[must_use]
     pub fn spin() -> Self {
         Self {
+            schema_version: SCRIPT_SCHEMA_VERSION,
             id: "spin".to_string(),
             duration: Some(3.0),
             keyframes: vec![
                 Keyframe {
-                    time: 0.0,
-                    action: Action::PlayAnimation {
-                        name: "idle".to_string(),
-                    },
+                    time: KeyframeTime::At(0.0),
+                    action: Action::play_animation("idle"),
                 },
                 Keyframe {
-                    time: 0.0,
-                    action: Action::Scale { factor: 0.9 },
+                    time: KeyframeTime::At(0.0),
+                    action: Action::Scale {
+                        factor: 0.9,
+                        duration: None,
+                        easing: None,
+                    },
                 },
                 Keyframe {
-                    time: 0.3,
-                    action: Action::Spin { turns: 2.0 },
+                    time: KeyframeTime::At(0.3),
+                    action: Action::Spin {
+                        turns: 2.0,
+                        duration: None,
+                        easing: None,
+                    },
                 },
                 Keyframe {
-                    time: 0.3,
-                    action: Action::Scale { factor: 1.1 },
+                    time: KeyframeTime::At(0.3),
+                    action: Action::Scale {
+                        factor: 1.1,
+                        duration: None,
+                        easing: None,
+                    },
                 },
                 Keyframe {
-                    time: 2.0,
-                    action: Action::Scale { factor: 1.0 },
+                    time: KeyframeTime::At(2.0),
+                    action: Action::Scale {
+                        factor: 1.0,
+                        duration: None,
+                        easing: None,
+                    },
                 },
             ],
-            next: Some("idle".to_string()),
+            tracks: Vec::new(),
+            repeat: None,
+            next: Some("idle".into()),
             interruptible: true,
         }
     }
@@ -282,3 +2424,1292 @@ impl BehaviorScript {
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> ValidationContext {
+        ValidationContext::new()
+            .with_animations(["idle", "walk", "jump", "spin", "sleep"])
+            .with_expressions(["neutral", "smile"])
+            .with_sounds(["quack"])
+            .with_scripts(
+                BehaviorScript::builtin_scripts()
+                    .into_iter()
+                    .map(|s| s.id)
+                    .chain(
+                        ScriptTemplate::builtin_templates()
+                            .into_iter()
+                            .map(|t| t.id),
+                    ),
+            )
+    }
+
+    fn script(keyframes: Vec<Keyframe>) -> BehaviorScript {
+        BehaviorScript {
+            schema_version: SCRIPT_SCHEMA_VERSION,
+            id: "test".to_string(),
+            duration: Some(2.0),
+            keyframes,
+            tracks: Vec::new(),
+            repeat: None,
+            next: Some("idle".into()),
+            interruptible: true,
+        }
+    }
+
+    fn keyframe(time: f32, action: Action) -> Keyframe {
+        Keyframe {
+            time: KeyframeTime::At(time),
+            action,
+        }
+    }
+
+    #[test]
+    fn builtin_scripts_are_valid() {
+        let context = context();
+        for script in BehaviorScript::builtin_scripts() {
+            let report = script.validate(&context);
+            assert!(report.diagnostics.is_empty(), "{}: {report}", script.id);
+        }
+    }
+
+    #[test]
+    fn empty_context_skips_reference_checks() {
+        let script = script(vec![keyframe(0.0, Action::play_animation("dance"))]);
+        assert!(script.validate(&ValidationContext::new()).is_valid());
+    }
+
+    #[test]
+    fn unknown_animation_is_an_error() {
+        let script = script(vec![keyframe(0.0, Action::play_animation("dance"))]);
+        let report = script.validate(&context());
+        assert!(!report.is_valid());
+        assert_eq!(
+            report.diagnostics[0].kind,
+            DiagnosticKind::UnknownAnimation("dance".to_string())
+        );
+        assert_eq!(report.diagnostics[0].keyframe, Some(0));
+    }
+
+    #[test]
+    fn unknown_expression_is_an_error() {
+        let script = script(vec![
+            keyframe(
+                0.0,
+                Action::SetExpression {
+                    expression: "smile".to_string(),
+                    duration: None,
+                    easing: None,
+                },
+            ),
+            keyframe(
+                1.0,
+                Action::SetExpression {
+                    expression: "wink".to_string(),
+                    duration: Some(0.5),
+                    easing: None,
+                },
+            ),
+        ]);
+        let report = script.validate(&context());
+        assert_eq!(report.diagnostics.len(), 1);
+        assert_eq!(
+            report.diagnostics[0].kind,
+            DiagnosticKind::UnknownExpression("wink".to_string())
+        );
+        assert_eq!(report.diagnostics[0].keyframe, Some(1));
+        assert_eq!(
+            script.keyframes[0].action.tween_duration(),
+            Some(DEFAULT_EXPRESSION_DURATION)
+        );
+    }
+
+    #[test]
+    fn cursor_actions_are_checked() {
+        let look = Action::LookAt {
+            target: LookTarget::Cursor,
+            duration: None,
+        };
+        assert_eq!(look.timeline_duration(), DEFAULT_LOOK_DURATION);
+        let parsed: Action = serde_json::from_str(r#"{"type": "look_at"}"#).unwrap();
+        assert!(matches!(
+            parsed,
+            Action::LookAt {
+                target: LookTarget::Cursor,
+                duration: None
+            }
+        ));
+
+        let script = script(vec![
+            keyframe(0.0, look),
+            keyframe(0.5, Action::TrackCursor { enabled: true }),
+            keyframe(
+                1.0,
+                Action::FollowCursor {
+                    speed: Some(0.0),
+                    distance: Some(-10.0),
+                },
+            ),
+        ]);
+        let report = script.validate(&context());
+        let fields: Vec<_> = report
+            .diagnostics
+            .iter()
+            .map(|d| match d.kind {
+                DiagnosticKind::OutOfRange { field, .. } => field,
+                _ => panic!("unexpected diagnostic {d}"),
+            })
+            .collect();
+        assert_eq!(fields, ["speed", "distance"]);
+    }
+
+    #[test]
+    fn animation_playback_options_are_checked() {
+        let parsed: Action = serde_json::from_str(
+            r#"{"type": "play_animation", "name": "jump", "looping": false, "speed": 2.0}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            parsed,
+            Action::PlayAnimation {
+                crossfade: None,
+                looping: Some(false),
+                ..
+            }
+        ));
+        assert_eq!(
+            parsed.crossfade_duration(),
+            Some(DEFAULT_CROSSFADE_DURATION)
+        );
+
+        let script = script(vec![
+            keyframe(0.0, parsed),
+            keyframe(
+                1.0,
+                Action::PlayAnimation {
+                    name: "idle".to_string(),
+                    crossfade: Some(-0.5),
+                    looping: None,
+                    speed: Some(0.0),
+                },
+            ),
+        ]);
+        let report = script.validate(&context());
+        let fields: Vec<_> = report
+            .diagnostics
+            .iter()
+            .map(|d| match d.kind {
+                DiagnosticKind::OutOfRange { field, .. } => field,
+                _ => panic!("unexpected diagnostic {d}"),
+            })
+            .collect();
+        assert_eq!(fields, ["crossfade", "speed"]);
+    }
+
+    #[test]
+    fn unknown_sounds_are_warnings() {
+        let sound = |name: &str, volume| Action::PlaySound {
+            name: name.to_string(),
+            volume,
+        };
+        let script = script(vec![
+            keyframe(0.0, sound("quack", None)),
+            keyframe(0.5, sound("moo", Some(0.5))),
+            keyframe(1.0, sound("quack", Some(1.5))),
+        ]);
+        let report = script.validate(&context());
+        assert!(!report.is_valid());
+        assert_eq!(report.diagnostics.len(), 2);
+        assert_eq!(report.diagnostics[0].severity, Severity::Warning);
+        assert_eq!(
+            report.diagnostics[0].kind,
+            DiagnosticKind::UnknownSound("moo".to_string())
+        );
+        assert_eq!(
+            report.diagnostics[1].kind,
+            DiagnosticKind::OutOfRange {
+                field: "volume",
+                value: 1.5
+            }
+        );
+    }
+
+    #[test]
+    fn say_duration_defaults_to_reading_time() {
+        let say = |text: &str, duration| Action::Say {
+            text: text.to_string(),
+            duration,
+        };
+        assert_eq!(say("Hi!", None).speech_duration(), Some(MIN_SAY_DURATION));
+        let long = "a".repeat(100);
+        assert_eq!(
+            say(&long, None).speech_duration(),
+            Some(100.0 * SAY_SECONDS_PER_CHAR)
+        );
+        assert_eq!(say("Hi!", Some(0.5)).timeline_duration(), 0.5);
+
+        let script = script(vec![
+            keyframe(0.0, say(" ", None)),
+            keyframe(1.0, say("Hi!", Some(0.0))),
+        ]);
+        let report = script.validate(&context());
+        let kinds: Vec<_> = report.diagnostics.iter().map(|d| &d.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                &DiagnosticKind::EmptyText,
+                &DiagnosticKind::OutOfRange {
+                    field: "duration",
+                    value: 0.0
+                }
+            ]
+        );
+        assert_eq!(report.diagnostics[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn unknown_script_references_are_errors() {
+        let mut script = script(vec![keyframe(
+            1.0,
+            Action::Random {
+                scripts: vec!["missing".into()],
+            },
+        )]);
+        script.next = Some("gone".into());
+
+        let kinds: Vec<_> = script
+            .validate(&context())
+            .errors()
+            .map(|d| d.kind.clone())
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                DiagnosticKind::UnknownScript("gone".to_string()),
+                DiagnosticKind::UnknownScript("missing".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn self_reference_is_allowed() {
+        let mut script = script(vec![]);
+        script.next = Some("test".into());
+        assert!(script.validate(&context()).is_valid());
+    }
+
+    #[test]
+    fn time_and_range_checks() {
+        let script = script(vec![
+            keyframe(
+                -1.0,
+                Action::Scale {
+                    factor: 0.0,
+                    duration: None,
+                    easing: None,
+                },
+            ),
+            keyframe(
+                1.0,
+                Action::Bounce {
+                    height: -0.1,
+                    duration: None,
+                    easing: None,
+                },
+            ),
+            keyframe(0.5, Action::Wait { duration: 0.1 }),
+            keyframe(
+                3.0,
+                Action::Spin {
+                    turns: f32::NAN,
+                    duration: None,
+                    easing: None,
+                },
+            ),
+        ]);
+        let report = script.validate(&context());
+        let kinds: Vec<_> = report.diagnostics.iter().map(|d| &d.kind).collect();
+        assert!(kinds.contains(&&DiagnosticKind::InvalidTime(-1.0)));
+        assert!(kinds.contains(&&DiagnosticKind::OutOfRange {
+            field: "factor",
+            value: 0.0
+        }));
+        assert!(kinds.contains(&&DiagnosticKind::OutOfRange {
+            field: "height",
+            value: -0.1
+        }));
+        assert!(kinds.contains(&&DiagnosticKind::UnsortedKeyframe {
+            time: 0.5,
+            previous: 1.0
+        }));
+        assert_eq!(report.warnings().count(), 1);
+        assert!(kinds.contains(&&DiagnosticKind::KeyframeAfterDuration {
+            time: 3.0,
+            duration: 2.0
+        }));
+    }
+
+    #[test]
+    fn repair_sorts_and_clamps_keyframes() {
+        let mut script = script(vec![
+            keyframe(
+                1.0,
+                Action::Scale {
+                    factor: 1.0,
+                    duration: None,
+                    easing: None,
+                },
+            ),
+            keyframe(
+                -0.5,
+                Action::Scale {
+                    factor: 1.2,
+                    duration: None,
+                    easing: None,
+                },
+            ),
+            keyframe(
+                1.5,
+                Action::Random {
+                    scripts: vec!["walk".into(), "missing".into()],
+                },
+            ),
+        ]);
+
+        let report = script.repair(&context());
+        assert!(report.is_valid(), "{report}");
+        assert_eq!(script.keyframe_times(0), vec![0.0, 1.0, 1.5]);
+        assert!(matches!(
+            &script.keyframes[2].action,
+            Action::Random { scripts } if scripts == &["walk".into()]
+        ));
+    }
+
+    #[test]
+    fn repair_prunes_unknown_branch_choices() {
+        let mut script = script(vec![keyframe(
+            0.0,
+            Action::Branch {
+                cases: vec![BranchCase {
+                    when: Condition::TimeOfDay {
+                        from: 22.0,
+                        to: 6.0,
+                    },
+                    scripts: vec!["missing".into(), "sleep".into()],
+                }],
+                otherwise: vec!["walk".into(), "gone".into()],
+            },
+        )]);
+
+        let report = script.repair(&context());
+        assert!(report.is_valid(), "{report}");
+        let Action::Branch { cases, otherwise } = &script.keyframes[0].action else {
+            panic!("branch was replaced");
+        };
+        assert_eq!(cases[0].scripts, ["sleep".into()]);
+        assert_eq!(otherwise, &["walk".into()]);
+    }
+
+    #[test]
+    fn tween_fields_default_when_omitted() {
+        let keyframe: Keyframe = toml::from_str(
+            r#"
+time = 0.0
+[action]
+type = "move_to"
+x = 0.3
+y = 0.0
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            keyframe.action.tween_duration(),
+            Some(DEFAULT_MOVE_DURATION)
+        );
+        assert_eq!(keyframe.action.tween_easing(), Some(Easing::CubicOut));
+
+        // Omitted fields are not written back out
+        let serialized = toml::to_string(&keyframe).unwrap();
+        assert!(!serialized.contains("duration"));
+        assert!(!serialized.contains("easing"));
+    }
+
+    #[test]
+    fn tween_fields_override_defaults() {
+        let action: Action = serde_json::from_str(
+            r#"{"type": "spin", "turns": 2.0, "duration": 0.5, "easing": "elastic_out"}"#,
+        )
+        .unwrap();
+        assert_eq!(action.tween_duration(), Some(0.5));
+        assert_eq!(action.tween_easing(), Some(Easing::ElasticOut));
+
+        let default_spin = Action::Spin {
+            turns: -2.0,
+            duration: None,
+            easing: None,
+        };
+        assert_eq!(
+            default_spin.tween_duration(),
+            Some(2.0 * DEFAULT_SPIN_TURN_DURATION)
+        );
+    }
+
+    #[test]
+    fn easing_curves_hit_endpoints() {
+        let curves = [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+            Easing::CubicIn,
+            Easing::CubicOut,
+            Easing::CubicInOut,
+            Easing::ElasticIn,
+            Easing::ElasticOut,
+            Easing::BackIn,
+            Easing::BackOut,
+            Easing::BounceIn,
+            Easing::BounceOut,
+        ];
+        for easing in curves {
+            assert!(easing.apply(0.0).abs() < 1e-4, "{easing:?} at 0");
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-4, "{easing:?} at 1");
+        }
+        assert!((Easing::EaseInOut.apply(0.5) - 0.5).abs() < 1e-6);
+        assert!(Easing::BackOut.apply(0.8) > 1.0);
+    }
+
+    #[test]
+    fn explicit_tween_duration_must_be_positive() {
+        let script = script(vec![keyframe(
+            0.0,
+            Action::Scale {
+                factor: 1.2,
+                duration: Some(0.0),
+                easing: None,
+            },
+        )]);
+        assert!(!script.validate(&context()).is_valid());
+    }
+
+    #[test]
+    fn tracks_are_validated_independently() {
+        let mut script = script(vec![keyframe(1.0, Action::Wait { duration: 0.5 })]);
+        script.tracks = vec![
+            Track {
+                name: "effects".to_string(),
+                keyframes: vec![
+                    keyframe(0.5, Action::Wait { duration: 0.1 }),
+                    keyframe(0.0, Action::play_animation("dance")),
+                ],
+            },
+            Track {
+                name: "effects".to_string(),
+                keyframes: vec![],
+            },
+        ];
+
+        let report = script.validate(&context());
+        let errors: Vec<_> = report.errors().collect();
+        assert_eq!(errors.len(), 2);
+        assert!(
+            errors
+                .iter()
+                .all(|d| d.track.as_deref() == Some("effects") && d.keyframe == Some(1))
+        );
+        assert_eq!(
+            report.warnings().next().map(|d| &d.kind),
+            Some(&DiagnosticKind::DuplicateTrack("effects".to_string()))
+        );
+
+        script.repair(&context());
+        assert_eq!(script.tracks[0].keyframes[0].time, KeyframeTime::At(0.0));
+    }
+
+    #[test]
+    fn keyframe_times_parse_and_print() {
+        let cases = [
+            ("+0.5", KeyframeTime::AfterStart(0.5)),
+            ("+0.5s", KeyframeTime::AfterStart(0.5)),
+            ("after", KeyframeTime::AfterEnd(0.0)),
+            ("after+1", KeyframeTime::AfterEnd(1.0)),
+            ("after + 0.25s", KeyframeTime::AfterEnd(0.25)),
+        ];
+        for (text, time) in cases {
+            assert_eq!(text.parse::<KeyframeTime>(), Ok(time), "{text}");
+            assert_eq!(time.to_string().parse::<KeyframeTime>(), Ok(time));
+        }
+        assert!("later".parse::<KeyframeTime>().is_err());
+        assert!("after0.5".parse::<KeyframeTime>().is_err());
+    }
+
+    #[test]
+    fn relative_times_resolve_against_previous_keyframe() {
+        let mut script = script(vec![
+            keyframe(1.0, Action::play_animation("walk")),
+            Keyframe {
+                time: KeyframeTime::AfterStart(0.5),
+                action: Action::Scale {
+                    factor: 1.2,
+                    duration: Some(0.4),
+                    easing: None,
+                },
+            },
+            Keyframe {
+                time: KeyframeTime::AfterEnd(0.0),
+                action: Action::Wait { duration: 2.0 },
+            },
+            Keyframe {
+                time: KeyframeTime::AfterStart(0.5),
+                action: Action::play_animation("idle"),
+            },
+            keyframe(3.0, Action::play_animation("jump")),
+        ]);
+        script.duration = None;
+
+        // The wait starts when the scale finishes and holds back the
+        // keyframe after it; absolute times are unaffected
+        assert_eq!(script.keyframe_times(0), vec![1.0, 1.5, 1.9, 4.4, 3.0]);
+
+        let report = script.validate(&context());
+        assert_eq!(
+            report
+                .diagnostics
+                .iter()
+                .map(|d| &d.kind)
+                .collect::<Vec<_>>(),
+            vec![&DiagnosticKind::UnsortedKeyframe {
+                time: 3.0,
+                previous: 4.4
+            }]
+        );
+    }
+
+    #[test]
+    fn relative_times_round_trip_through_every_format() {
+        let original = script(vec![
+            keyframe(0.0, Action::Wait { duration: 1.0 }),
+            Keyframe {
+                time: KeyframeTime::AfterEnd(0.5),
+                action: Action::play_animation("jump"),
+            },
+            Keyframe {
+                time: KeyframeTime::AfterStart(0.25),
+                action: Action::play_animation("idle"),
+            },
+        ]);
+        for format in ScriptFormat::ALL {
+            let content = original.to_format(format).unwrap();
+            let parsed = BehaviorScript::parse(&content, format).unwrap();
+            let times: Vec<_> = parsed.keyframes.iter().map(|k| k.time).collect();
+            assert_eq!(
+                times,
+                vec![
+                    KeyframeTime::At(0.0),
+                    KeyframeTime::AfterEnd(0.5),
+                    KeyframeTime::AfterStart(0.25)
+                ],
+                "{format}"
+            );
+        }
+
+        let error = BehaviorScript::parse(
+            "id = \"x\"\ninterruptible = true\n[[keyframes]]\ntime = \"soon\"\naction = { type = \"wait\", duration = 1.0 }\n",
+            ScriptFormat::Toml,
+        )
+        .unwrap_err();
+        assert!(
+            error.to_string().contains("invalid keyframe time 'soon'"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn repair_keeps_relative_tracks_in_order() {
+        let mut script = script(vec![
+            keyframe(2.0, Action::play_animation("walk")),
+            Keyframe {
+                time: KeyframeTime::AfterStart(0.5),
+                action: Action::play_animation("idle"),
+            },
+            keyframe(1.0, Action::play_animation("jump")),
+        ]);
+        script.repair(&context());
+        assert_eq!(script.keyframe_times(0), vec![2.0, 2.5, 1.0]);
+    }
+
+    #[test]
+    fn track_accessors_include_main_keyframes() {
+        let mut script = script(vec![keyframe(0.0, Action::Wait { duration: 1.0 })]);
+        script.tracks.push(Track {
+            name: "speech".to_string(),
+            keyframes: vec![],
+        });
+
+        assert_eq!(script.track_count(), 2);
+        assert_eq!(script.track_keyframes(0).len(), 1);
+        assert!(script.track_keyframes(1).is_empty());
+        assert!(script.track_keyframes(2).is_empty());
+        assert_eq!(script.track_name(0), None);
+        assert_eq!(script.track_name(1), Some("speech"));
+    }
+
+    #[test]
+    fn tracks_round_trip_through_toml() {
+        let mut script = script(vec![]);
+        script.tracks.push(Track {
+            name: "transform".to_string(),
+            keyframes: vec![keyframe(
+                0.5,
+                Action::Bounce {
+                    height: 0.2,
+                    duration: None,
+                    easing: None,
+                },
+            )],
+        });
+
+        let serialized = toml::to_string_pretty(&script).unwrap();
+        let deserialized: BehaviorScript = toml::from_str(&serialized).unwrap();
+        assert!(deserialized.keyframes.is_empty());
+        assert_eq!(deserialized.tracks.len(), 1);
+        assert_eq!(deserialized.tracks[0].name, "transform");
+
+        // Scripts without tracks keep the old layout
+        let serialized = toml::to_string_pretty(&BehaviorScript::idle()).unwrap();
+        assert!(!serialized.contains("tracks"));
+    }
+
+    #[test]
+    fn script_choices_accept_bare_ids_and_weights() {
+        let action: Action = serde_json::from_str(
+            r#"{"type": "random", "scripts": ["walk", {"script": "idle", "weight": 3.0}, {"script": "spin"}]}"#,
+        )
+        .unwrap();
+        let Action::Random { ref scripts } = action else {
+            panic!("Expected Random action");
+        };
+        assert_eq!(
+            scripts,
+            &[
+                ScriptChoice::weighted("walk", 1.0),
+                ScriptChoice::weighted("idle", 3.0),
+                ScriptChoice::weighted("spin", 1.0),
+            ]
+        );
+
+        // Unweighted choices serialize back to bare IDs
+        let json = serde_json::to_string(&action).unwrap();
+        assert!(json.contains(r#""walk""#));
+        assert!(json.contains(r#"{"script":"idle","weight":3.0}"#));
+
+        let ron = BehaviorScript::idle().to_ron().unwrap();
+        let parsed = BehaviorScript::parse_ron(&ron).unwrap();
+        assert!(matches!(
+            &parsed.keyframes[2].action,
+            Action::Random { scripts } if scripts[0] == ScriptChoice::weighted("idle", 2.0)
+        ));
+    }
+
+    #[test]
+    fn conditions_evaluate_against_pet_state() {
+        let state = PetState {
+            hour: 23.5,
+            idle_seconds: 700.0,
+            clicks_last_minute: 2,
+            mood: 0.4,
+        };
+
+        assert!(
+            Condition::TimeOfDay {
+                from: 22.0,
+                to: 6.0
+            }
+            .evaluate(&state)
+        );
+        assert!(
+            !Condition::TimeOfDay {
+                from: 8.0,
+                to: 20.0
+            }
+            .evaluate(&state)
+        );
+        assert!(Condition::IdleFor { seconds: 600.0 }.evaluate(&state));
+        assert!(!Condition::ClicksInLastMinute { count: 3 }.evaluate(&state));
+        assert!(
+            Condition::Mood {
+                min: None,
+                max: Some(0.5)
+            }
+            .evaluate(&state)
+        );
+        assert!(
+            Condition::All {
+                conditions: vec![
+                    Condition::IdleFor { seconds: 600.0 },
+                    Condition::Not {
+                        condition: Box::new(Condition::ClicksInLastMinute { count: 3 }),
+                    },
+                ],
+            }
+            .evaluate(&state)
+        );
+        assert!(!Condition::Any { conditions: vec![] }.evaluate(&state));
+    }
+
+    #[test]
+    fn branch_picks_first_matching_case() {
+        // "Sleep after 10 minutes of no interaction"
+        let action: Action = toml::from_str(
+            r#"
+type = "branch"
+otherwise = ["walk", { script = "idle", weight = 2.0 }]
+
+[[cases]]
+when = { type = "idle_for", seconds = 600.0 }
+scripts = ["sleep"]
+
+[[cases]]
+when = { type = "clicks_in_last_minute", count = 5 }
+scripts = ["happy"]
+"#,
+        )
+        .unwrap();
+
+        let sleepy = PetState {
+            idle_seconds: 601.0,
+            ..Default::default()
+        };
+        let excited = PetState {
+            clicks_last_minute: 5,
+            ..Default::default()
+        };
+
+        assert_eq!(action.branch_choices(&sleepy), Some(&["sleep".into()][..]));
+        assert_eq!(action.branch_choices(&excited), Some(&["happy".into()][..]));
+        assert_eq!(
+            action.branch_choices(&PetState::default()).map(<[_]>::len),
+            Some(2)
+        );
+        assert!(
+            script(vec![keyframe(2.0, action)])
+                .validate(&context())
+                .is_valid()
+        );
+    }
+
+    #[test]
+    fn invalid_weights_and_conditions_are_errors() {
+        let script = script(vec![
+            keyframe(
+                0.0,
+                Action::Random {
+                    scripts: vec![ScriptChoice::weighted("walk", 0.0)],
+                },
+            ),
+            keyframe(
+                1.0,
+                Action::Branch {
+                    cases: vec![BranchCase {
+                        when: Condition::TimeOfDay {
+                            from: 25.0,
+                            to: 6.0,
+                        },
+                        scripts: vec!["sleep".into()],
+                    }],
+                    otherwise: vec![ScriptChoice::weighted("idle", -1.0)],
+                },
+            ),
+            keyframe(
+                1.5,
+                Action::Branch {
+                    cases: vec![],
+                    otherwise: vec![],
+                },
+            ),
+        ]);
+
+        let kinds: Vec<_> = script
+            .validate(&context())
+            .errors()
+            .map(|d| d.kind.clone())
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                DiagnosticKind::OutOfRange {
+                    field: "weight",
+                    value: 0.0
+                },
+                DiagnosticKind::OutOfRange {
+                    field: "from",
+                    value: 25.0
+                },
+                DiagnosticKind::OutOfRange {
+                    field: "weight",
+                    value: -1.0
+                },
+                DiagnosticKind::NoChoices,
+            ]
+        );
+    }
+
+    fn args(pairs: &[(&str, serde_json::Value)]) -> ScriptArgs {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn script_calls_accept_bare_ids_and_args() {
+        let script: BehaviorScript = serde_json::from_str(
+            r#"{
+                "id": "test",
+                "duration": 1.0,
+                "next": {"script": "walk_to", "args": {"x": -0.5}},
+                "interruptible": true
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            script.next,
+            Some(ScriptCall::with_args(
+                "walk_to",
+                args(&[("x", (-0.5).into())])
+            ))
+        );
+
+        let choice: ScriptChoice =
+            serde_json::from_str(r#"{"script": "walk_to", "args": {"x": 0.8}}"#).unwrap();
+        assert_eq!(choice.weight, 1.0);
+        assert_eq!(choice.call().args, args(&[("x", 0.8.into())]));
+
+        // Calls without arguments serialize back to bare IDs
+        let json = serde_json::to_string(&ScriptCall::from("idle")).unwrap();
+        assert_eq!(json, r#""idle""#);
+    }
+
+    #[test]
+    fn template_binds_arguments_and_defaults() {
+        let template = ScriptTemplate::walk_to();
+        let walk = template
+            .instantiate(&args(&[("x", (-0.6).into())]))
+            .unwrap();
+
+        assert_eq!(walk.id, "walk_to");
+        assert_eq!(walk.duration, Some(1.0));
+        assert_eq!(walk.next, Some("idle".into()));
+        assert!(matches!(
+            walk.keyframes[1].action,
+            Action::MoveTo {
+                x: -0.6,
+                y: 0.0,
+                duration: Some(1.0),
+                ..
+            }
+        ));
+        assert!(walk.validate(&context()).is_valid());
+    }
+
+    #[test]
+    fn template_interpolates_strings() {
+        let template = ScriptTemplate {
+            id: "play".to_string(),
+            params: vec![
+                TemplateParam::required("mood"),
+                TemplateParam::with_default("then", "idle"),
+            ],
+            script: serde_json::json!({
+                "duration": 1.0,
+                "keyframes": [
+                    { "time": 0.0, "action": { "type": "play_animation", "name": "${mood}_loop" } },
+                ],
+                "next": "$then",
+                "interruptible": true,
+            }),
+        };
+
+        let script = template
+            .instantiate(&args(&[("mood", "happy".into())]))
+            .unwrap();
+        assert!(matches!(
+            &script.keyframes[0].action,
+            Action::PlayAnimation { name, .. } if name == "happy_loop"
+        ));
+        assert_eq!(script.next, Some("idle".into()));
+    }
+
+    #[test]
+    fn template_reports_unbound_and_unexpected_parameters() {
+        let template = ScriptTemplate::walk_to();
+        assert_eq!(
+            template.instantiate(&ScriptArgs::new()).unwrap_err(),
+            TemplateError::Unbound {
+                template: "walk_to".to_string(),
+                params: vec!["x".to_string()],
+            }
+        );
+        assert_eq!(
+            template
+                .instantiate(&args(&[("x", 0.0.into()), ("speed", 2.0.into())]))
+                .unwrap_err(),
+            TemplateError::UnexpectedArguments {
+                template: "walk_to".to_string(),
+                args: vec!["speed".to_string()],
+            }
+        );
+
+        // References to undeclared parameters are unbound too
+        let mut template = ScriptTemplate::walk_to();
+        template.script["keyframes"][0]["action"]["name"] = "${gait}".into();
+        assert!(matches!(
+            template.instantiate(&args(&[("x", 0.0.into())])),
+            Err(TemplateError::Unbound { params, .. }) if params == ["gait"]
+        ));
+    }
+
+    #[test]
+    fn template_leaves_other_dollars_alone() {
+        let template = ScriptTemplate {
+            id: "greet".to_string(),
+            params: vec![TemplateParam::required("mood")],
+            script: serde_json::json!({
+                "duration": 1.0,
+                "keyframes": [
+                    { "time": 0.0, "action": { "type": "say", "text": "$5" } },
+                    { "time": 0.0, "action": { "type": "say", "text": "$$mood" } },
+                    { "time": 0.0, "action": { "type": "say", "text": "${mood}, $${mood} and $" } },
+                ],
+                "interruptible": true,
+            }),
+        };
+
+        let script = template
+            .instantiate(&args(&[("mood", "happy".into())]))
+            .unwrap();
+        let texts: Vec<_> = script
+            .keyframes
+            .iter()
+            .map(|keyframe| match &keyframe.action {
+                Action::Say { text, .. } => text.as_str(),
+                action => panic!("unexpected action {action:?}"),
+            })
+            .collect();
+        assert_eq!(texts, ["$5", "$mood", "happy, ${mood} and $"]);
+    }
+
+    #[test]
+    fn template_calls_with_different_arguments_get_different_ids() {
+        let call = |x: f64, y: f64| {
+            ScriptCall::with_args("walk_to", args(&[("y", y.into()), ("x", x.into())]))
+        };
+        assert_eq!(call(0.5, 0.0).instance_id(), r#"walk_to{"x":0.5,"y":0.0}"#);
+        assert_eq!(call(0.5, 0.0).instance_id(), call(0.5, 0.0).instance_id());
+        assert_ne!(call(0.5, 0.0).instance_id(), call(-0.5, 0.0).instance_id());
+        assert_eq!(ScriptCall::from("walk_to").instance_id(), "walk_to");
+    }
+
+    #[test]
+    fn template_rejects_ill_typed_arguments() {
+        let result = ScriptTemplate::walk_to().instantiate(&args(&[("x", "far".into())]));
+        assert!(matches!(result, Err(TemplateError::Invalid { .. })));
+    }
+
+    #[test]
+    fn template_parses_from_ron() {
+        let template: ScriptTemplate = ron::from_str(
+            r#"(
+                id: "scale_to",
+                params: [(name: "factor"), (name: "duration", default: Some(0.5))],
+                script: {
+                    "duration": 1.0,
+                    "keyframes": [
+                        (time: 0.0, action: (type: "scale", factor: "$factor", duration: "$duration")),
+                    ],
+                    "interruptible": true,
+                },
+            )"#,
+        )
+        .unwrap();
+
+        let script = template
+            .instantiate(&args(&[("factor", 1.5.into())]))
+            .unwrap();
+        assert!(matches!(
+            script.keyframes[0].action,
+            Action::Scale {
+                factor: 1.5,
+                duration: Some(0.5),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn repeat_and_call_parse_and_validate() {
+        let script: BehaviorScript = toml::from_str(
+            r#"
+id = "bounce_thrice"
+duration = 1.0
+interruptible = false
+next = "idle"
+repeat = { times = 3 }
+
+[[keyframes]]
+time = 0.0
+action = { type = "call", script = "bounce" }
+
+[[keyframes]]
+time = 0.5
+action = { type = "call", script = "walk_to", args = { x = 0.4 } }
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(script.play_count(), 3);
+        assert_eq!(
+            script.keyframes[1].action.sub_script(),
+            Some(ScriptCall::with_args("walk_to", args(&[("x", 0.4.into())])))
+        );
+        assert!(script.validate(&context()).is_valid());
+        assert_eq!(BehaviorScript::idle().play_count(), 1);
+    }
+
+    #[test]
+    fn call_and_repeat_are_checked() {
+        let mut script = script(vec![keyframe(
+            0.0,
+            Action::Call {
+                script: "missing".to_string(),
+                args: ScriptArgs::new(),
+            },
+        )]);
+        script.repeat = Some(Repeat { times: 0 });
+
+        let kinds: Vec<_> = script
+            .validate(&context())
+            .errors()
+            .map(|d| d.kind.clone())
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                DiagnosticKind::OutOfRange {
+                    field: "times",
+                    value: 0.0
+                },
+                DiagnosticKind::UnknownScript("missing".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn scripts_round_trip_between_formats() {
+        for script in BehaviorScript::builtin_scripts() {
+            let mut content = script.to_ron().unwrap();
+            let mut previous = ScriptFormat::Ron;
+            for format in [ScriptFormat::Toml, ScriptFormat::Json, ScriptFormat::Ron] {
+                let parsed = BehaviorScript::parse(&content, previous).unwrap();
+                content = parsed.to_format(format).unwrap();
+                assert_eq!(ScriptFormat::detect(&content), format, "{}", script.id);
+                previous = format;
+            }
+            let parsed = BehaviorScript::parse_ron(&content).unwrap();
+            assert_eq!(
+                parsed.to_format(ScriptFormat::Json).unwrap(),
+                script.to_format(ScriptFormat::Json).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn detects_format_from_path_and_content() {
+        assert_eq!(
+            ScriptFormat::from_path(Path::new("idle.JSON")),
+            Some(ScriptFormat::Json)
+        );
+        assert_eq!(ScriptFormat::from_path(Path::new("idle.txt")), None);
+
+        assert_eq!(
+            ScriptFormat::detect("# comment\nid = \"idle\""),
+            ScriptFormat::Toml
+        );
+        assert_eq!(
+            ScriptFormat::detect("[[keyframes]]\ntime = 0.0"),
+            ScriptFormat::Toml
+        );
+        assert_eq!(
+            ScriptFormat::detect("// comment\n(id: \"idle\")"),
+            ScriptFormat::Ron
+        );
+        assert_eq!(
+            ScriptFormat::detect("BehaviorScript(id: \"idle\")"),
+            ScriptFormat::Ron
+        );
+        assert_eq!(
+            ScriptFormat::detect("  {\"id\": \"idle\"}"),
+            ScriptFormat::Json
+        );
+    }
+
+    #[test]
+    fn parse_errors_carry_position() {
+        let toml_error =
+            BehaviorScript::parse("id = \"x\"\nduration = \"long\"\n", ScriptFormat::Toml)
+                .unwrap_err();
+        assert!(matches!(
+            toml_error,
+            ScriptError::Parse {
+                format: ScriptFormat::Toml,
+                line: Some(2),
+                column: Some(12),
+                ..
+            }
+        ));
+
+        let ron_error = BehaviorScript::parse_ron("(\n  id: 5,\n)").unwrap_err();
+        assert!(matches!(
+            ron_error,
+            ScriptError::Parse {
+                format: ScriptFormat::Ron,
+                line: Some(2),
+                ..
+            }
+        ));
+        assert!(ron_error.to_string().starts_with("2:"));
+    }
+
+    #[test]
+    fn json_parse_errors_report_position_once() {
+        use std::error::Error;
+
+        let error = BehaviorScript::parse("{\n  \"id\": 5\n}", ScriptFormat::Json).unwrap_err();
+        let message = error.to_string();
+        assert!(message.starts_with("2:"));
+        assert!(!message.contains("at line"), "{message}");
+        assert!(
+            error
+                .source()
+                .is_some_and(|source| source.is::<serde_json::Error>())
+        );
+    }
+
+    #[test]
+    fn current_scripts_are_not_migrated() {
+        for format in ScriptFormat::ALL {
+            let content = BehaviorScript::idle().to_format(format).unwrap();
+            let (_, version) = BehaviorScript::parse_versioned(&content, format).unwrap();
+            assert_eq!(version, SCRIPT_SCHEMA_VERSION);
+        }
+    }
+
+    #[test]
+    fn newer_schema_versions_are_rejected() {
+        let content = format!(
+            "schema_version = {}\nid = \"x\"\ninterruptible = true\n",
+            SCRIPT_SCHEMA_VERSION + 1
+        );
+        let error = BehaviorScript::parse(&content, ScriptFormat::Toml).unwrap_err();
+        assert!(matches!(
+            error,
+            ScriptError::UnsupportedVersion { version, .. } if version == SCRIPT_SCHEMA_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn unversioned_scripts_are_version_1() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("idle.json");
+        fs::write(
+            &path,
+            r#"{"id": "idle", "duration": 1.0, "next": null, "interruptible": true}"#,
+        )
+        .unwrap();
+
+        let (script, version) = BehaviorScript::load_versioned(&path).unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(script.schema_version, 1);
+        let typed: BehaviorScript = ScriptFormat::Json
+            .parse(&fs::read_to_string(&path).unwrap())
+            .unwrap();
+        assert_eq!(typed.schema_version, version);
+
+        assert_eq!(BehaviorScript::upgrade_file(&path).unwrap(), None);
+        assert!(!dir.path().join("idle.json.v1.bak").exists());
+    }
+
+    /// A version 1 to 2 step, as if durations had been in milliseconds
+    fn durations_to_seconds(document: &mut serde_json::Value) {
+        if let Some(duration) = document.get_mut("duration")
+            && let Some(ms) = duration.as_f64()
+        {
+            *duration = (ms / 1000.0).into();
+        }
+    }
+
+    /// A version 2 to 3 step
+    fn forbid_interrupts(document: &mut serde_json::Value) {
+        document["interruptible"] = false.into();
+    }
+
+    const TEST_MIGRATIONS: [Migration; 2] = [durations_to_seconds, forbid_interrupts];
+
+    #[test]
+    fn migrations_run_in_order_from_the_written_version() {
+        let v1 = r#"{"id": "x", "duration": 1500.0, "next": null, "interruptible": true}"#;
+        let (script, version) =
+            BehaviorScript::parse_migrated(v1, ScriptFormat::Json, &TEST_MIGRATIONS).unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(script.schema_version, 3);
+        assert_eq!(script.duration, Some(1.5));
+        assert!(!script.interruptible);
+
+        let v2 = r#"{"schema_version": 2, "id": "x", "duration": 1.5, "next": null, "interruptible": true}"#;
+        let (script, version) =
+            BehaviorScript::parse_migrated(v2, ScriptFormat::Json, &TEST_MIGRATIONS).unwrap();
+        assert_eq!(version, 2);
+        assert_eq!(script.schema_version, 3);
+        assert_eq!(script.duration, Some(1.5));
+        assert!(!script.interruptible);
+    }
+
+    #[test]
+    fn upgrading_a_file_keeps_a_backup_and_rewrites_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("x.json");
+        let original = r#"{"id": "x", "duration": 1500.0, "next": null, "interruptible": true}"#;
+        fs::write(&path, original).unwrap();
+
+        let upgraded = BehaviorScript::upgrade_file_migrated(&path, &TEST_MIGRATIONS).unwrap();
+        assert_eq!(upgraded, Some(1));
+        assert_eq!(
+            fs::read_to_string(dir.path().join("x.json.v1.bak")).unwrap(),
+            original
+        );
+        let (script, version) = BehaviorScript::load_migrated(&path, &TEST_MIGRATIONS).unwrap();
+        assert_eq!(version, 3);
+        assert_eq!(script.duration, Some(1.5));
+        assert!(!script.interruptible);
+
+        // Already current: left alone
+        assert_eq!(
+            BehaviorScript::upgrade_file_migrated(&path, &TEST_MIGRATIONS).unwrap(),
+            None
+        );
+        assert!(!dir.path().join("x.json.v3.bak").exists());
+    }
+
+    #[test]
+    fn repair_keeps_unrecoverable_errors() {
+        let mut script = script(vec![keyframe(
+            0.0,
+            Action::Random {
+                scripts: vec!["missing".into()],
+            },
+        )]);
+        assert!(!script.repair(&context()).is_valid());
+    }
+}
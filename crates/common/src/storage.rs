@@ -1,10 +1,59 @@
 use crate::error::{Error, Result};
 use crate::models::Pet;
 use crate::paths::AppPaths;
-use crate::script::BehaviorScript;
+use crate::script::{BehaviorScript, ScriptError, ScriptFormat};
+use std::collections::BTreeMap;
 use std::fs;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
+/// File extensions the theater can play sound effects from
+pub const SOUND_EXTENSIONS: [&str; 2] = ["ogg", "wav"];
+
+/// Sound files in `dir` by name (the file stem). A missing directory has
+/// no sounds.
+pub fn sound_files(dir: &Path) -> Result<BTreeMap<String, PathBuf>> {
+    let mut sounds = BTreeMap::new();
+    if !dir.exists() {
+        return Ok(sounds);
+    }
+
+    let mut paths = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    paths.sort();
+    for path in paths {
+        let is_sound = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| SOUND_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+        if !is_sound {
+            continue;
+        }
+        if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+            sounds.insert(name.to_string(), path);
+        }
+    }
+    Ok(sounds)
+}
+
+/// Scripts read from the scripts directory, along with the files that
+/// failed to load
+#[derive(Debug, Default)]
+pub struct LoadedScripts {
+    pub scripts: Vec<BehaviorScript>,
+    pub errors: Vec<ScriptError>,
+}
+
+/// Outcome of upgrading the scripts directory to the current schema
+/// version
+#[derive(Debug, Default)]
+pub struct ScriptUpgrades {
+    /// Rewritten files and the version each was upgraded from
+    pub upgraded: Vec<(PathBuf, u32)>,
+    pub errors: Vec<ScriptError>,
+}
+
 pub struct StorageService {
     paths: AppPaths,
 }
@@ -50,47 +99,81 @@ impl StorageService {
     pub fn save_script(&self, script: &BehaviorScript) -> Result<()> {
         self.paths.ensure_dirs()?;
         let path = self.paths.scripts_dir().join(format!("{}.toml", script.id));
-        let content = toml::to_string_pretty(script)?;
-        fs::write(path, content)?;
+        script.save(path)?;
         Ok(())
     }
 
+    /// Path of the script file with the given ID, in any supported format
+    #[must_use]
+    pub fn find_script(&self, script_id: &str) -> Option<PathBuf> {
+        ScriptFormat::ALL
+            .into_iter()
+            .map(|format| {
+                self.paths
+                    .scripts_dir()
+                    .join(format!("{script_id}.{}", format.extension()))
+            })
+            .find(|path| path.exists())
+    }
+
     pub fn load_script(&self, script_id: &str) -> Result<BehaviorScript> {
-        let path = self.paths.scripts_dir().join(format!("{script_id}.toml"));
-        if path.exists() {
-            let content = fs::read_to_string(&path)?;
-            let script: BehaviorScript = toml::from_str(&content)?;
-            Ok(script)
-        } else {
-            Err(Error::ScriptNotFound(script_id.to_string()))
-        }
+        let path = self
+            .find_script(script_id)
+            .ok_or_else(|| Error::ScriptNotFound(script_id.to_string()))?;
+        Ok(BehaviorScript::load(path)?)
     }
 
-    pub fn load_all_scripts(&self) -> Result<Vec<BehaviorScript>> {
+    /// RON, TOML and JSON files in the scripts directory, sorted by path
+    pub fn script_files(&self) -> Result<Vec<PathBuf>> {
         let scripts_dir = self.paths.scripts_dir();
-        let mut scripts = Vec::new();
+        let mut paths = Vec::new();
 
         if scripts_dir.exists() {
             for entry in fs::read_dir(&scripts_dir)? {
-                let entry = entry?;
-                let path = entry.path();
-                if path.extension().is_some_and(|ext| ext == "toml") {
-                    let content = fs::read_to_string(&path)?;
-                    if let Ok(script) = toml::from_str::<BehaviorScript>(&content) {
-                        scripts.push(script);
-                    }
+                let path = entry?.path();
+                if ScriptFormat::from_path(&path).is_some() {
+                    paths.push(path);
                 }
             }
+            paths.sort();
+        }
+
+        Ok(paths)
+    }
+
+    /// Load every RON, TOML and JSON script in the scripts directory.
+    ///
+    /// Files that fail to load are reported in `errors` rather than
+    /// failing the whole load.
+    pub fn load_all_scripts(&self) -> Result<LoadedScripts> {
+        let mut loaded = LoadedScripts::default();
+        for path in self.script_files()? {
+            match BehaviorScript::load(&path) {
+                Ok(script) => loaded.scripts.push(script),
+                Err(e) => loaded.errors.push(e),
+            }
         }
+        Ok(loaded)
+    }
 
-        Ok(scripts)
+    /// Rewrite every script older than the current schema version,
+    /// keeping each original as a `.bak` file next to it
+    pub fn upgrade_scripts(&self) -> Result<ScriptUpgrades> {
+        let mut upgrades = ScriptUpgrades::default();
+        for path in self.script_files()? {
+            match BehaviorScript::upgrade_file(&path) {
+                Ok(Some(version)) => upgrades.upgraded.push((path, version)),
+                Ok(None) => {}
+                Err(e) => upgrades.errors.push(e),
+            }
+        }
+        Ok(upgrades)
     }
 
     pub fn init_builtin_scripts(&self) -> Result<()> {
         self.paths.ensure_dirs()?;
         for script in BehaviorScript::builtin_scripts() {
-            let path = self.paths.scripts_dir().join(format!("{}.toml", script.id));
-            if !path.exists() {
+            if self.find_script(&script.id).is_none() {
                 self.save_script(&script)?;
             }
         }
@@ -103,6 +186,115 @@ impl StorageService {
         if thumbnail.exists() {
             fs::remove_file(thumbnail)?;
         }
+        let sounds = self.paths.sounds_dir(&pet.id);
+        if sounds.exists() {
+            fs::remove_dir_all(sounds)?;
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn test_storage() -> (TempDir, StorageService) {
+        let dir = TempDir::new().unwrap();
+        let paths = AppPaths::with_dirs(dir.path().join("config"), dir.path().join("data"));
+        paths.ensure_dirs().unwrap();
+        (dir, StorageService::new(paths))
+    }
+
+    #[test]
+    fn loads_scripts_in_every_format() {
+        let (_dir, storage) = test_storage();
+        let scripts_dir = storage.paths().scripts_dir();
+
+        storage.save_script(&BehaviorScript::idle()).unwrap();
+        BehaviorScript::walk()
+            .save(scripts_dir.join("walk.ron"))
+            .unwrap();
+        BehaviorScript::happy()
+            .save(scripts_dir.join("happy.json"))
+            .unwrap();
+        fs::write(scripts_dir.join("notes.txt"), "not a script").unwrap();
+
+        let loaded = storage.load_all_scripts().unwrap();
+        assert!(loaded.errors.is_empty(), "{:?}", loaded.errors);
+        let mut ids: Vec<_> = loaded.scripts.iter().map(|s| s.id.as_str()).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, ["happy", "idle", "walk"]);
+
+        assert_eq!(storage.load_script("walk").unwrap().id, "walk");
+        assert!(matches!(
+            storage.load_script("missing"),
+            Err(Error::ScriptNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn reports_per_file_parse_failures() {
+        let (_dir, storage) = test_storage();
+        let scripts_dir = storage.paths().scripts_dir();
+
+        storage.save_script(&BehaviorScript::idle()).unwrap();
+        let broken = scripts_dir.join("broken.json");
+        fs::write(&broken, "{\n  \"id\": \"broken\",\n  \"duration\": oops\n}").unwrap();
+
+        let loaded = storage.load_all_scripts().unwrap();
+        assert_eq!(loaded.scripts.len(), 1);
+        assert_eq!(loaded.errors.len(), 1);
+
+        let error = &loaded.errors[0];
+        assert_eq!(error.path(), Some(broken.as_path()));
+        assert!(matches!(
+            error,
+            ScriptError::Parse {
+                format: ScriptFormat::Json,
+                line: Some(3),
+                ..
+            }
+        ));
+        assert!(
+            error
+                .to_string()
+                .starts_with(&format!("{}:3:", broken.display()))
+        );
+    }
+
+    #[test]
+    fn builtin_scripts_are_not_overwritten() {
+        let (_dir, storage) = test_storage();
+        let custom = BehaviorScript {
+            duration: Some(10.0),
+            ..BehaviorScript::idle()
+        };
+        custom
+            .save(storage.paths().scripts_dir().join("idle.ron"))
+            .unwrap();
+
+        storage.init_builtin_scripts().unwrap();
+
+        assert_eq!(storage.load_script("idle").unwrap().duration, Some(10.0));
+        assert!(!storage.paths().scripts_dir().join("idle.toml").exists());
+    }
+
+    #[test]
+    fn current_scripts_are_not_upgraded() {
+        let (_dir, storage) = test_storage();
+        storage.init_builtin_scripts().unwrap();
+        let scripts_dir = storage.paths().scripts_dir();
+        fs::write(
+            scripts_dir.join("old.ron"),
+            r#"(id: "old", duration: None, next: None, interruptible: true)"#,
+        )
+        .unwrap();
+
+        let upgrades = storage.upgrade_scripts().unwrap();
+
+        assert!(upgrades.errors.is_empty());
+        assert!(upgrades.upgraded.is_empty());
+        assert!(!scripts_dir.join("old.ron.v1.bak").exists());
+    }
+}
@@ -0,0 +1,141 @@
+//! Interaction triggers: which scripts start when the user interacts with
+//! the pet
+//!
+//! Each pet has a trigger table mapping interactions to weighted script
+//! choices, like `Random`'s:
+//!
+//! ```toml
+//! [pets.triggers]
+//! idle_timeout = 120.0
+//!
+//! [pets.triggers.reactions]
+//! click = ["happy"]
+//! double_click = [{ script = "spin", weight = 2.0 }, "happy"]
+//! idle_timeout = ["sleep"]
+//! ```
+
+use crate::script::ScriptChoice;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Default seconds without interaction before `idle_timeout` triggers
+pub const DEFAULT_IDLE_TIMEOUT: f32 = 300.0;
+
+/// An interaction with the pet that can start a script
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+    Click,
+    /// A second click shortly after the first, in place of a second `Click`
+    DoubleClick,
+    DragStart,
+    /// The pet is let go after a drag
+    Drop,
+    /// The pet is pressed and held without moving
+    LongPress,
+    /// The cursor moves onto the pet's window
+    HoverEnter,
+    /// Nobody has interacted with the pet for the table's `idle_timeout`
+    IdleTimeout,
+}
+
+impl std::fmt::Display for Trigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Click => "click",
+            Self::DoubleClick => "double_click",
+            Self::DragStart => "drag_start",
+            Self::Drop => "drop",
+            Self::LongPress => "long_press",
+            Self::HoverEnter => "hover_enter",
+            Self::IdleTimeout => "idle_timeout",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A pet's scripts for each trigger. Triggers without an entry do nothing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct TriggerTable {
+    /// Scripts to choose from, by weight, for each trigger
+    pub reactions: BTreeMap<Trigger, Vec<ScriptChoice>>,
+    /// Seconds without interaction before `idle_timeout` triggers
+    pub idle_timeout: f32,
+}
+
+impl Default for TriggerTable {
+    /// Clicking cheers the pet up, as it always has
+    fn default() -> Self {
+        Self {
+            reactions: BTreeMap::from([(Trigger::Click, vec!["happy".into()])]),
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+        }
+    }
+}
+
+impl TriggerTable {
+    /// Scripts to choose from for `trigger`; empty if it has no reaction
+    #[must_use]
+    pub fn reaction(&self, trigger: Trigger) -> &[ScriptChoice] {
+        self.reactions.get(&trigger).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether `trigger` starts any script
+    #[must_use]
+    pub fn handles(&self, trigger: Trigger) -> bool {
+        !self.reaction(trigger).is_empty()
+    }
+
+    #[must_use]
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Every script choice in the table, with its trigger
+    pub fn choices(&self) -> impl Iterator<Item = (Trigger, &ScriptChoice)> {
+        self.reactions
+            .iter()
+            .flat_map(|(trigger, choices)| choices.iter().map(|choice| (*trigger, choice)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_parses_from_toml() {
+        let table: TriggerTable = toml::from_str(
+            r#"
+idle_timeout = 60.0
+
+[reactions]
+double_click = [{ script = "spin", weight = 2.0 }, "happy"]
+idle_timeout = ["sleep"]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(table.idle_timeout, 60.0);
+        assert!(!table.handles(Trigger::Click));
+        assert_eq!(
+            table.reaction(Trigger::DoubleClick),
+            [ScriptChoice::weighted("spin", 2.0), "happy".into()]
+        );
+        assert_eq!(table.reaction(Trigger::IdleTimeout), ["sleep".into()]);
+    }
+
+    #[test]
+    fn default_table_reacts_to_clicks() {
+        let table: TriggerTable = toml::from_str("").unwrap();
+        assert!(table.is_default());
+        assert_eq!(table.reaction(Trigger::Click), ["happy".into()]);
+
+        let written = toml::to_string(&table).unwrap();
+        assert_eq!(toml::from_str::<TriggerTable>(&written).unwrap(), table);
+    }
+}
@@ -11,6 +11,7 @@ pub struct SettingsApp {
     current_page: Page,
     general_state: views::general::State,
     appearance_state: views::appearance::State,
+    audio_state: views::audio::State,
     ai_state: views::ai::State,
     meshy_state: views::meshy::State,
     dirty: bool,
@@ -22,6 +23,7 @@ pub enum Message {
     Save,
     General(views::general::Message),
     Appearance(views::appearance::Message),
+    Audio(views::audio::Message),
     Ai(views::ai::Message),
     Meshy(views::meshy::Message),
 }
@@ -33,6 +35,7 @@ impl SettingsApp {
 
         let general_state = views::general::State::from_config(&config);
         let appearance_state = views::appearance::State::from_config(&config);
+        let audio_state = views::audio::State::from_config(&config);
         let ai_state = views::ai::State::from_config(&config);
         let meshy_state = views::meshy::State::from_config(&config);
 
@@ -43,6 +46,7 @@ impl SettingsApp {
                 current_page: Page::General,
                 general_state,
                 appearance_state,
+                audio_state,
                 ai_state,
                 meshy_state,
                 dirty: false,
@@ -79,6 +83,10 @@ impl SettingsApp {
                 self.appearance_state.update(msg);
                 self.dirty = true;
             }
+            Message::Audio(msg) => {
+                self.audio_state.update(msg);
+                self.dirty = true;
+            }
             Message::Ai(msg) => {
                 self.ai_state.update(msg);
                 self.dirty = true;
@@ -97,6 +105,7 @@ impl SettingsApp {
         let content: Element<'_, Message> = match self.current_page {
             Page::General => self.general_state.view().map(Message::General),
             Page::Appearance => self.appearance_state.view().map(Message::Appearance),
+            Page::Audio => self.audio_state.view().map(Message::Audio),
             Page::Ai => self.ai_state.view().map(Message::Ai),
             Page::Meshy => self.meshy_state.view().map(Message::Meshy),
             Page::About => views::about::view(),
@@ -128,6 +137,7 @@ impl SettingsApp {
         let pages = [
             (Page::General, "General"),
             (Page::Appearance, "Appearance"),
+            (Page::Audio, "Audio"),
             (Page::Ai, "AI"),
             (Page::Meshy, "Meshy AI"),
             (Page::About, "About"),
@@ -151,6 +161,7 @@ impl SettingsApp {
     fn apply_state_to_config(&mut self) {
         self.general_state.apply_to(&mut self.config);
         self.appearance_state.apply_to(&mut self.config);
+        self.audio_state.apply_to(&mut self.config);
         self.ai_state.apply_to(&mut self.config);
         self.meshy_state.apply_to(&mut self.config);
     }
@@ -10,6 +10,7 @@ pub struct State {
     pub position_y: String,
     pub always_on_top: bool,
     pub opacity: f32,
+    pub click_through: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -19,6 +20,7 @@ pub enum Message {
     PositionYChanged(String),
     ToggleAlwaysOnTop(bool),
     OpacityChanged(f32),
+    ToggleClickThrough(bool),
 }
 
 impl State {
@@ -29,6 +31,7 @@ impl State {
             position_y: config.appearance.pet_position.y.to_string(),
             always_on_top: config.appearance.always_on_top,
             opacity: config.appearance.opacity,
+            click_through: config.appearance.click_through,
         }
     }
 
@@ -39,6 +42,7 @@ impl State {
             Message::PositionYChanged(v) => self.position_y = v,
             Message::ToggleAlwaysOnTop(v) => self.always_on_top = v,
             Message::OpacityChanged(v) => self.opacity = v,
+            Message::ToggleClickThrough(v) => self.click_through = v,
         }
     }
 
@@ -70,6 +74,9 @@ impl State {
                 text(format!("{:.0}%", self.opacity * 100.0)),
             ]
             .spacing(10),
+            toggler(self.click_through)
+                .label("Let Clicks Through Empty Areas")
+                .on_toggle(Message::ToggleClickThrough),
         ]
         .spacing(12)
         .padding(20)
@@ -82,5 +89,6 @@ impl State {
         config.appearance.pet_position.y = self.position_y.parse().unwrap_or(0);
         config.appearance.always_on_top = self.always_on_top;
         config.appearance.opacity = self.opacity;
+        config.appearance.click_through = self.click_through;
     }
 }
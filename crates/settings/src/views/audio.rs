@@ -0,0 +1,55 @@
+use iced::Element;
+use iced::widget::{column, row, slider, text, toggler};
+
+use common::config::AppConfig;
+
+#[derive(Debug, Clone)]
+pub struct State {
+    pub volume: f32,
+    pub muted: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    VolumeChanged(f32),
+    ToggleMuted(bool),
+}
+
+impl State {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            volume: config.audio.volume,
+            muted: config.audio.muted,
+        }
+    }
+
+    pub fn update(&mut self, message: Message) {
+        match message {
+            Message::VolumeChanged(v) => self.volume = v,
+            Message::ToggleMuted(v) => self.muted = v,
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        column![
+            text("Audio Settings").size(24),
+            toggler(self.muted)
+                .label("Mute Sound Effects")
+                .on_toggle(Message::ToggleMuted),
+            row![
+                text("Volume"),
+                slider(0.0..=1.0, self.volume, Message::VolumeChanged).step(0.05_f32),
+                text(format!("{:.0}%", self.volume * 100.0)),
+            ]
+            .spacing(10),
+        ]
+        .spacing(12)
+        .padding(20)
+        .into()
+    }
+
+    pub fn apply_to(&self, config: &mut AppConfig) {
+        config.audio.volume = self.volume;
+        config.audio.muted = self.muted;
+    }
+}
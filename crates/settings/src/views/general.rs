@@ -1,5 +1,5 @@
 use iced::Element;
-use iced::widget::{column, pick_list, row, text, toggler};
+use iced::widget::{column, pick_list, row, text, text_input, toggler};
 
 use common::config::AppConfig;
 
@@ -9,12 +9,15 @@ const LANGUAGES: &[&str] = &["en", "zh"];
 pub struct State {
     pub auto_start: bool,
     pub language: String,
+    /// Minutes before a hidden pet is unloaded; empty for never
+    pub unload_hidden_after: String,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     ToggleAutoStart(bool),
     LanguageSelected(String),
+    UnloadHiddenAfterChanged(String),
 }
 
 impl State {
@@ -22,6 +25,11 @@ impl State {
         Self {
             auto_start: config.general.auto_start,
             language: config.general.language.clone(),
+            unload_hidden_after: config
+                .general
+                .unload_hidden_after
+                .map(|minutes| minutes.to_string())
+                .unwrap_or_default(),
         }
     }
 
@@ -29,6 +37,7 @@ impl State {
         match message {
             Message::ToggleAutoStart(v) => self.auto_start = v,
             Message::LanguageSelected(lang) => self.language = lang,
+            Message::UnloadHiddenAfterChanged(v) => self.unload_hidden_after = v,
         }
     }
 
@@ -46,6 +55,12 @@ impl State {
                 pick_list(languages, selected, Message::LanguageSelected),
             ]
             .spacing(10),
+            row![
+                text("Unload Hidden Pet After (minutes)"),
+                text_input("Never", &self.unload_hidden_after)
+                    .on_input(Message::UnloadHiddenAfterChanged),
+            ]
+            .spacing(10),
         ]
         .spacing(12)
         .padding(20)
@@ -55,5 +70,6 @@ impl State {
     pub fn apply_to(&self, config: &mut AppConfig) {
         config.general.auto_start = self.auto_start;
         config.general.language.clone_from(&self.language);
+        config.general.unload_hidden_after = self.unload_hidden_after.trim().parse().ok();
     }
 }
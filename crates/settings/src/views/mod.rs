@@ -1,6 +1,7 @@
 pub mod about;
 pub mod ai;
 pub mod appearance;
+pub mod audio;
 pub mod general;
 pub mod meshy;
 
@@ -8,6 +9,7 @@ pub mod meshy;
 pub enum Page {
     General,
     Appearance,
+    Audio,
     Ai,
     Meshy,
     About,
@@ -2,87 +2,121 @@
 
 #[cfg(unix)]
 use crate::plugins::IpcPlugin;
-use crate::plugins::{InteractionPlugin, PetPlugin, ReplayPlugin};
-use crate::resources::TheaterConfig;
+use crate::plugins::{
+    GazePlugin, InteractionPlugin, PetPlugin, ReplayPlugin, ScriptWatchPlugin, SoundPlugin,
+    SpeechPlugin, VisibilityPlugin,
+};
+use crate::resources::{AudioOutput, TheaterConfig};
 use bevy::asset::AssetPlugin;
 use bevy::prelude::*;
 use bevy::render::settings::WgpuSettings;
 use bevy::window::{PresentMode, WindowLevel, WindowResolution};
 use bevy::winit::{UpdateMode, WinitSettings};
+use common::models::CameraFraming;
+use common::triggers::TriggerTable;
 use common::{AppConfig, AppPaths};
+use std::collections::BTreeMap;
 
-/// Default window size for the pet theater
+/// Default window size for the pet theater; speech bubbles grow the
+/// window upwards while they show
 const DEFAULT_WINDOW_SIZE: u32 = 400;
 
+/// Environment variable selecting the audio output: `null` plays nothing
+/// and never opens an audio device, for machines without one
+const AUDIO_OUTPUT_ENV: &str = "PET_AUDIO_OUTPUT";
+
 /// Run the theater application
 pub fn run_theater() -> common::Result<()> {
     let paths = AppPaths::new()?;
     let config = AppConfig::load(&paths)?;
+    let assets_dir = AppPaths::find_assets_dir()?;
 
-    let theater_config = TheaterConfig {
-        model_path: config.get_active_pet().map(|pet| pet.model_path.clone()),
+    let mut theater_config = TheaterConfig {
+        model_path: None,
         pet_scale: config.appearance.pet_scale,
         opacity: config.appearance.opacity,
         always_on_top: config.appearance.always_on_top,
+        click_through: config.appearance.click_through,
         window_position: (
             config.appearance.pet_position.x,
             config.appearance.pet_position.y,
         ),
+        camera: CameraFraming::default(),
+        expressions: BTreeMap::new(),
+        builtin_sound_dir: assets_dir.join("sounds"),
+        pet_sound_dir: None,
+        audio: config.audio.clone(),
+        triggers: TriggerTable::default(),
         paths,
     };
+    if let Some(pet) = config.get_active_pet() {
+        theater_config.set_pet(pet);
+    }
+    let null_audio = std::env::var(AUDIO_OUTPUT_ENV).is_ok_and(|output| output == "null");
 
-    let assets_dir = AppPaths::find_assets_dir()?;
-
-    let mut app = App::new();
-    app.add_plugins(
-        DefaultPlugins
-            .set(AssetPlugin {
-                file_path: assets_dir.to_string_lossy().to_string(),
-                ..default()
-            })
-            .set(WindowPlugin {
-                primary_window: Some(Window {
-                    title: "Pet".into(),
-                    resolution: WindowResolution::new(DEFAULT_WINDOW_SIZE, DEFAULT_WINDOW_SIZE),
-                    transparent: true,
-                    decorations: false,
-                    window_level: if theater_config.always_on_top {
-                        WindowLevel::AlwaysOnTop
-                    } else {
-                        WindowLevel::Normal
-                    },
-                    present_mode: PresentMode::AutoVsync,
-                    resizable: false,
-                    #[cfg(target_os = "macos")]
-                    composite_alpha_mode: bevy::window::CompositeAlphaMode::PostMultiplied,
-                    position: WindowPosition::At(IVec2::new(
-                        theater_config.window_position.0,
-                        theater_config.window_position.1,
-                    )),
-                    ..default()
-                }),
-                ..default()
-            })
-            .set(bevy::render::RenderPlugin {
-                render_creation: bevy::render::settings::RenderCreation::Automatic(Box::new(
-                    WgpuSettings {
-                        backends: Some(bevy::render::settings::Backends::all()),
-                        ..default()
-                    },
+    let mut plugins = DefaultPlugins
+        .set(AssetPlugin {
+            file_path: assets_dir.to_string_lossy().to_string(),
+            ..default()
+        })
+        .set(WindowPlugin {
+            primary_window: Some(Window {
+                title: "Pet".into(),
+                resolution: WindowResolution::new(DEFAULT_WINDOW_SIZE, DEFAULT_WINDOW_SIZE),
+                transparent: true,
+                decorations: false,
+                window_level: if theater_config.always_on_top {
+                    WindowLevel::AlwaysOnTop
+                } else {
+                    WindowLevel::Normal
+                },
+                present_mode: PresentMode::AutoVsync,
+                resizable: false,
+                #[cfg(target_os = "macos")]
+                composite_alpha_mode: bevy::window::CompositeAlphaMode::PostMultiplied,
+                position: WindowPosition::At(IVec2::new(
+                    theater_config.window_position.0,
+                    theater_config.window_position.1,
                 )),
                 ..default()
             }),
-    )
-    .insert_resource(ClearColor(Color::NONE))
-    // Limit frame rate to reduce CPU usage - desktop pet doesn't need 60fps
-    .insert_resource(WinitSettings {
-        focused_mode: UpdateMode::reactive_low_power(std::time::Duration::from_millis(100)),
-        unfocused_mode: UpdateMode::reactive_low_power(std::time::Duration::from_millis(100)),
-    })
-    .insert_resource(theater_config)
-    .add_plugins(PetPlugin)
-    .add_plugins(InteractionPlugin)
-    .add_plugins(ReplayPlugin);
+            ..default()
+        })
+        .set(bevy::render::RenderPlugin {
+            render_creation: bevy::render::settings::RenderCreation::Automatic(Box::new(
+                WgpuSettings {
+                    backends: Some(bevy::render::settings::Backends::all()),
+                    ..default()
+                },
+            )),
+            ..default()
+        });
+    if null_audio {
+        plugins = plugins.disable::<bevy::audio::AudioPlugin>();
+    }
+
+    let mut app = App::new();
+    app.add_plugins(plugins)
+        .insert_resource(ClearColor(Color::NONE))
+        // Limit frame rate to reduce CPU usage - desktop pet doesn't need 60fps
+        .insert_resource(WinitSettings {
+            focused_mode: UpdateMode::reactive_low_power(std::time::Duration::from_millis(100)),
+            unfocused_mode: UpdateMode::reactive_low_power(std::time::Duration::from_millis(100)),
+        })
+        .insert_resource(theater_config)
+        .insert_resource(if null_audio {
+            AudioOutput::Null
+        } else {
+            AudioOutput::Device
+        })
+        .add_plugins(PetPlugin)
+        .add_plugins(InteractionPlugin)
+        .add_plugins(ReplayPlugin)
+        .add_plugins(ScriptWatchPlugin)
+        .add_plugins(SpeechPlugin)
+        .add_plugins(SoundPlugin)
+        .add_plugins(GazePlugin)
+        .add_plugins(VisibilityPlugin);
 
     #[cfg(unix)]
     app.add_plugins(IpcPlugin);
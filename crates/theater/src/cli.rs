@@ -0,0 +1,125 @@
+//! Command-line subcommands, run instead of opening the theater window
+
+use std::path::Path;
+use std::time::Duration;
+
+use crate::simulator::{DEFAULT_HOUR, DEFAULT_STEP, Simulator};
+
+const SIMULATE_USAGE: &str = "usage: pet-theater simulate <script-id|file> [--seconds <s>] \
+     [--step <s>] [--seed <n>] [--hour <h>] [--click <s>]...";
+
+/// Seconds simulated unless `--seconds` is given
+const DEFAULT_SECONDS: f32 = 10.0;
+
+/// Run the subcommand named by `args`, if any, returning its exit code.
+/// Returns `None` when no subcommand was given.
+pub fn run(args: &[String]) -> Option<i32> {
+    match args.first()?.as_str() {
+        "simulate" => Some(simulate_command(&args[1..])),
+        _ => None,
+    }
+}
+
+struct SimulateOptions {
+    script: String,
+    seconds: f32,
+    step: Duration,
+    seed: u64,
+    hour: f32,
+    clicks: Vec<f32>,
+}
+
+impl SimulateOptions {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Self {
+            script: String::new(),
+            seconds: DEFAULT_SECONDS,
+            step: DEFAULT_STEP,
+            seed: 0,
+            hour: DEFAULT_HOUR,
+            clicks: Vec::new(),
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .ok_or_else(|| format!("{name} needs a value"))
+                    .map(String::as_str)
+            };
+            match arg.as_str() {
+                "--seconds" => options.seconds = parse_number("--seconds", value("--seconds")?)?,
+                "--step" => {
+                    let step: f32 = parse_number("--step", value("--step")?)?;
+                    options.step = Duration::try_from_secs_f32(step)
+                        .ok()
+                        .filter(|step| !step.is_zero())
+                        .ok_or("--step must be positive")?;
+                }
+                "--seed" => options.seed = parse_number("--seed", value("--seed")?)?,
+                "--hour" => options.hour = parse_number("--hour", value("--hour")?)?,
+                "--click" => options
+                    .clicks
+                    .push(parse_number("--click", value("--click")?)?),
+                flag if flag.starts_with("--") => return Err(format!("unknown option '{flag}'")),
+                script if options.script.is_empty() => options.script = script.to_string(),
+                extra => return Err(format!("unexpected argument '{extra}'")),
+            }
+        }
+        if options.script.is_empty() {
+            return Err("missing script".to_string());
+        }
+        options.clicks.sort_by(f32::total_cmp);
+        Ok(options)
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value '{value}' for {name}"))
+}
+
+/// `pet-theater simulate <script>` plays a script headlessly and prints
+/// one line per executed action
+fn simulate_command(args: &[String]) -> i32 {
+    let options = match SimulateOptions::parse(args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}\n{SIMULATE_USAGE}");
+            return 2;
+        }
+    };
+
+    let mut simulator = Simulator::with_step(options.seed, options.step);
+    simulator.set_hour(options.hour);
+
+    // A path loads the file and starts the script it defines
+    let path = Path::new(&options.script);
+    let script = if path.is_file() {
+        match simulator.library_mut().load_file(path) {
+            // The file's own script comes last
+            Ok(mut ids) => ids.pop().unwrap_or_default(),
+            Err(e) => {
+                eprintln!("error: {e}");
+                return 1;
+            }
+        }
+    } else {
+        options.script.clone()
+    };
+    if let Err(e) = simulator.start(script.as_str()) {
+        eprintln!("error: {e}");
+        return 1;
+    }
+
+    for &at in options.clicks.iter().filter(|&&at| at < options.seconds) {
+        simulator.run_for(at - simulator.elapsed());
+        simulator.click();
+    }
+    simulator.run_for(options.seconds - simulator.elapsed());
+
+    for entry in simulator.trace() {
+        println!("{entry}");
+    }
+    0
+}
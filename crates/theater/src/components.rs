@@ -1,20 +1,94 @@
 //! ECS Components for the theater process
 
 use bevy::prelude::*;
+use common::script::{BehaviorScript, Easing, TimelineAnchor};
 
 /// Marker component for the pet entity
 #[derive(Component)]
 pub struct PetMarker;
 
+/// Marker for the main camera (to distinguish from GLTF-embedded cameras)
+#[derive(Component)]
+pub struct MainCamera;
+
 /// Component to store the pet's animation state
 #[derive(Component, Default)]
 pub struct PetAnimationState {
     /// The name of the currently playing animation
     pub current_animation: Option<String>,
+    /// Whether the current animation loops, rather than stopping on its
+    /// last frame
+    pub looping: bool,
+    /// The animation fading out while the current one fades in
+    pub fading_from: Option<String>,
+    /// How long the fade from `fading_from` takes, in seconds
+    pub crossfade: f32,
+    /// Time since the fade started, in seconds
+    pub crossfade_elapsed: f32,
     /// The entity containing the AnimationPlayer component
     pub player_entity: Option<Entity>,
 }
 
+impl PetAnimationState {
+    /// Start `animation`, fading out the one playing over `crossfade`
+    /// seconds. Restarting the same animation doesn't fade.
+    pub fn start(&mut self, animation: String, looping: bool, crossfade: f32) {
+        let previous = self.current_animation.replace(animation);
+        self.fading_from = previous.filter(|previous| {
+            crossfade > 0.0 && self.current_animation.as_ref() != Some(previous)
+        });
+        self.crossfade = crossfade;
+        self.crossfade_elapsed = 0.0;
+        self.looping = looping;
+    }
+
+    /// Move the fade on by `delta` seconds, ending it once it's done
+    pub fn advance_crossfade(&mut self, delta: f32) {
+        if self.fading_from.is_none() {
+            return;
+        }
+        self.crossfade_elapsed += delta;
+        if self.crossfade_elapsed >= self.crossfade {
+            self.fading_from = None;
+        }
+    }
+
+    /// How far the current animation has faded in, from 0 to 1
+    pub fn blend(&self) -> f32 {
+        if self.fading_from.is_none() {
+            return 1.0;
+        }
+        (self.crossfade_elapsed / self.crossfade).min(1.0)
+    }
+}
+
+/// Component turning the pet's head towards the cursor (LookAt and
+/// TrackCursor actions)
+#[derive(Component, Default)]
+pub struct Gaze {
+    /// Whether to keep looking at the cursor until tracking is turned off
+    pub tracking: bool,
+    /// Time left of the current `LookAt`, in seconds
+    pub look_remaining: f32,
+    /// How far the head is currently turned, in world space
+    pub offset: Quat,
+    /// The head's local rotation without the turn
+    pub base: Quat,
+    /// The rotation last written to the head, to notice when an animation
+    /// or tween has set a new one
+    pub written: Option<Quat>,
+}
+
+impl Gaze {
+    /// Whether the head should be turned towards the cursor
+    pub fn is_active(&self) -> bool {
+        self.tracking || self.look_remaining > 0.0
+    }
+}
+
+/// Maximum depth of nested `Call` actions, to stop runaway recursion
+pub const MAX_CALL_DEPTH: usize = 8;
+
 /// Component to track replay state for a pet entity
 #[derive(Component)]
 pub struct ReplayState {
@@ -22,10 +96,26 @@ pub struct ReplayState {
     pub script_id: String,
     /// Time elapsed since the script started (in seconds)
     pub elapsed_time: f32,
-    /// Index of the next keyframe to execute
-    pub next_keyframe_index: usize,
+    /// Index of the next keyframe to execute, per track (main track first)
+    pub track_cursors: Vec<usize>,
+    /// Where relative keyframe times are measured from, per track; a
+    /// `Wait` moves its track's anchor to the end of the wait
+    pub track_anchors: Vec<TimelineAnchor>,
+    /// Number of times the script has already played through, for `repeat`
+    pub iteration: u32,
     /// Whether the script has completed
     pub completed: bool,
+    /// Callers paused by `Call` actions, innermost last
+    pub call_stack: Vec<CallFrame>,
+}
+
+/// A caller paused while its sub-script plays
+pub struct CallFrame {
+    pub script_id: String,
+    pub elapsed_time: f32,
+    pub track_cursors: Vec<usize>,
+    pub track_anchors: Vec<TimelineAnchor>,
+    pub iteration: u32,
 }
 
 impl ReplayState {
@@ -34,18 +124,89 @@ impl ReplayState {
         Self {
             script_id,
             elapsed_time: 0.0,
-            next_keyframe_index: 0,
+            track_cursors: Vec::new(),
+            track_anchors: Vec::new(),
+            iteration: 0,
             completed: false,
+            call_stack: Vec::new(),
         }
     }
 
-    /// Switch to a new script, resetting all state
+    /// Switch to a new script, resetting all state and abandoning any callers
     pub fn switch_to(&mut self, script_id: String) {
+        self.call_stack.clear();
         self.script_id = script_id;
+        self.restart();
+        self.iteration = 0;
+    }
+
+    /// Play the current script again from the start, for `repeat`
+    pub fn restart(&mut self) {
         self.elapsed_time = 0.0;
-        self.next_keyframe_index = 0;
+        self.track_cursors.clear();
+        self.track_anchors.clear();
         self.completed = false;
     }
+
+    /// Pause the current script and run a sub-script.
+    ///
+    /// Returns `false`, leaving the state unchanged, if the call stack is
+    /// already at `MAX_CALL_DEPTH`.
+    pub fn push_call(&mut self, script_id: String) -> bool {
+        if self.call_stack.len() >= MAX_CALL_DEPTH {
+            return false;
+        }
+        self.call_stack.push(CallFrame {
+            script_id: std::mem::replace(&mut self.script_id, script_id),
+            elapsed_time: self.elapsed_time,
+            track_cursors: std::mem::take(&mut self.track_cursors),
+            track_anchors: std::mem::take(&mut self.track_anchors),
+            iteration: self.iteration,
+        });
+        self.restart();
+        self.iteration = 0;
+        true
+    }
+
+    /// Resume the innermost caller. Returns `false` if there is none.
+    pub fn return_from_call(&mut self) -> bool {
+        let Some(frame) = self.call_stack.pop() else {
+            return false;
+        };
+        self.script_id = frame.script_id;
+        self.elapsed_time = frame.elapsed_time;
+        self.track_cursors = frame.track_cursors;
+        self.track_anchors = frame.track_anchors;
+        self.iteration = frame.iteration;
+        self.completed = false;
+        true
+    }
+
+    /// IDs of the current script and its paused callers, innermost first;
+    /// the last one is the top-level script
+    pub fn script_stack(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.script_id.as_str())
+            .chain(self.call_stack.iter().rev().map(|f| f.script_id.as_str()))
+    }
+
+    /// Index of the next keyframe to execute on the given track
+    pub fn cursor(&self, track: usize) -> usize {
+        self.track_cursors.get(track).copied().unwrap_or(0)
+    }
+
+    /// Anchor for relative keyframe times on the given track
+    pub fn anchor(&self, track: usize) -> TimelineAnchor {
+        self.track_anchors.get(track).copied().unwrap_or_default()
+    }
+
+    /// Whether every track of the script has executed all of its keyframes
+    /// and sat out any trailing `Wait`
+    pub fn tracks_finished(&self, script: &BehaviorScript) -> bool {
+        (0..script.track_count()).all(|track| {
+            self.cursor(track) >= script.track_keyframes(track).len()
+                && self.anchor(track).start <= self.elapsed_time
+        })
+    }
 }
 
 /// Component for smooth position tweening (MoveTo action)
@@ -57,6 +218,8 @@ pub struct MovementTween {
     pub target_position: Vec3,
     /// Duration of the tween in seconds
     pub duration: f32,
+    /// Easing curve applied to the tween progress
+    pub easing: Easing,
     /// Time elapsed since tween started
     pub elapsed: f32,
 }
@@ -70,6 +233,8 @@ pub struct ScaleTween {
     pub target_scale: Vec3,
     /// Duration of the tween in seconds
     pub duration: f32,
+    /// Easing curve applied to the tween progress
+    pub easing: Easing,
     /// Time elapsed since tween started
     pub elapsed: f32,
 }
@@ -83,6 +248,8 @@ pub struct BounceTween {
     pub height: f32,
     /// Duration of the bounce in seconds
     pub duration: f32,
+    /// Easing curve applied to the bounce progress
+    pub easing: Easing,
     /// Time elapsed since bounce started
     pub elapsed: f32,
 }
@@ -96,6 +263,23 @@ pub struct RotationTween {
     pub total_angle: f32,
     /// Duration of the tween in seconds
     pub duration: f32,
+    /// Easing curve applied to the tween progress
+    pub easing: Easing,
     /// Time elapsed since tween started
     pub elapsed: f32,
 }
+
+/// Component for blending morph target weights (SetExpression action)
+#[derive(Component)]
+pub struct ExpressionTween {
+    /// Weights when the blend started, one per morph target
+    pub start_weights: Vec<f32>,
+    /// Weights of the target expression
+    pub target_weights: Vec<f32>,
+    /// Duration of the blend in seconds
+    pub duration: f32,
+    /// Easing curve applied to the blend progress
+    pub easing: Easing,
+    /// Time elapsed since the blend started
+    pub elapsed: f32,
+}
@@ -1,13 +1,14 @@
 //! Messages for the Replay system
 
 use bevy::prelude::*;
-use common::script::Action;
+use common::script::{Action, Easing, LookTarget, ScriptCall};
+use common::triggers::Trigger;
 
 /// Message to request switching to a different behavior script
 #[derive(Message)]
 pub struct SwitchScriptEvent {
-    /// The ID of the script to switch to
-    pub script_id: String,
+    /// The script or template to switch to
+    pub script: ScriptCall,
     /// If true, ignore the interruptible flag of the current script
     pub force: bool,
 }
@@ -17,6 +18,8 @@ pub struct SwitchScriptEvent {
 pub struct ExecuteActionEvent {
     /// The entity to execute the action on
     pub entity: Entity,
+    /// The script the keyframe belongs to
+    pub script_id: String,
     /// The action to execute
     pub action: Action,
 }
@@ -28,12 +31,138 @@ pub struct PlayAnimationEvent {
     pub entity: Entity,
     /// The name of the animation to play
     pub animation_name: String,
+    /// Time to crossfade from the previous animation, in seconds
+    pub crossfade: f32,
+    /// Whether to loop, rather than stop on the last frame
+    pub looping: bool,
+    /// Playback speed, relative to the clip's own
+    pub speed: f32,
+}
+
+/// Message to request blending to a facial expression
+#[derive(Message)]
+pub struct SetExpressionEvent {
+    /// The entity whose expression changes
+    pub entity: Entity,
+    /// The name of the expression
+    pub expression: String,
+    /// Blend time in seconds
+    pub duration: f32,
+    /// Easing curve applied to the blend
+    pub easing: Easing,
+}
+
+/// Message to request playing a sound effect
+#[derive(Message)]
+pub struct PlaySoundEvent {
+    /// The name of the sound
+    pub name: String,
+    /// Volume relative to the global volume, from 0 to 1
+    pub volume: f32,
+}
+
+/// Message to show text in the pet's speech bubble
+#[derive(Message)]
+pub struct SayEvent {
+    /// The text to show
+    pub text: String,
+    /// How long the bubble stays up, in seconds
+    pub duration: f32,
+}
+
+/// Message to turn the pet's head toward a target for a while
+#[derive(Message)]
+pub struct LookAtEvent {
+    /// The entity that looks
+    pub entity: Entity,
+    /// What to look at
+    pub target: LookTarget,
+    /// How long to keep looking, in seconds
+    pub duration: f32,
+}
+
+/// Message to start or stop continuously following the cursor with the head
+#[derive(Message)]
+pub struct TrackCursorEvent {
+    /// The entity that looks
+    pub entity: Entity,
+    pub enabled: bool,
+}
+
+/// Message to walk the window toward the cursor across the desktop
+#[derive(Message)]
+pub struct FollowCursorEvent {
+    /// Speed in logical pixels per second
+    pub speed: f32,
+    /// Stop once the pet is this close to the cursor, in logical pixels
+    pub distance: f32,
 }
 
 /// Message triggered when the pet is clicked (not dragged)
 #[derive(Message)]
 pub struct PetClickedEvent;
 
+/// Message triggered by a second click shortly after the first, in place
+/// of a second `PetClickedEvent`
+#[derive(Message)]
+pub struct PetDoubleClickedEvent;
+
+/// Message triggered when the pet is right-clicked
+#[derive(Message)]
+pub struct PetRightClickedEvent;
+
+/// Message triggered when the pet is pressed and held without dragging
+#[derive(Message)]
+pub struct PetLongPressedEvent;
+
+/// Message triggered when the cursor moves onto or off the pet's window
+#[derive(Message)]
+pub struct PetHoveredEvent {
+    pub hovering: bool,
+}
+
+/// Message triggered when the mouse wheel turns over the pet
+#[derive(Message)]
+pub struct PetScrolledEvent {
+    /// Lines scrolled; positive is up
+    pub delta: f32,
+}
+
+/// Message triggered when the user starts dragging the window
+#[derive(Message)]
+pub struct PetDragStartedEvent {
+    /// Pet position when the drag started: the window's top left corner
+    /// below any speech bubble, in screen coordinates
+    pub position: IVec2,
+}
+
+/// Message triggered when the user lets go of the window after a drag
+#[derive(Message)]
+pub struct PetDragEndedEvent {
+    /// Pet position when the drag started, as in `PetDragStartedEvent`
+    pub start: IVec2,
+    /// Pet position where it was let go
+    pub end: IVec2,
+    /// How long the drag lasted, in seconds
+    pub duration: f32,
+}
+
+/// Message triggered by an interaction that may start a script from the
+/// pet's trigger table
+#[derive(Message)]
+pub struct TriggerEvent(pub Trigger);
+
+/// Message to hide or show the pet
+#[derive(Message)]
+pub struct SetVisibilityEvent {
+    pub visible: bool,
+}
+
+/// Message triggered when the theater switches to another pet, once
+/// `TheaterConfig` describes the new one
+#[derive(Message)]
+pub struct PetSwappedEvent;
+
 /// Message triggered when a script has completed
 #[derive(Message)]
 pub struct ScriptCompletedEvent {
@@ -42,5 +171,5 @@ pub struct ScriptCompletedEvent {
     /// The ID of the completed script
     pub script_id: String,
     /// The next script to transition to (if specified)
-    pub next_script: Option<String>,
+    pub next_script: Option<ScriptCall>,
 }
@@ -4,16 +4,23 @@
 //! in a transparent, always-on-top window.
 
 mod app;
+mod cli;
 mod components;
 mod events;
 mod plugins;
 mod resources;
+mod simulator;
 mod systems;
 
 use app::run_theater;
 use tracing::info;
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = cli::run(&args) {
+        std::process::exit(code);
+    }
+
     tracing_subscriber::fmt()
         .with_env_filter(
             tracing_subscriber::EnvFilter::from_default_env()
@@ -0,0 +1,216 @@
+//! Gaze plugin - turns the pet's head towards the cursor
+
+use bevy::app::AnimationSystems;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::transform::TransformSystems;
+use bevy::window::PrimaryWindow;
+use common::script::LookTarget;
+use tracing::debug;
+
+use crate::components::{Gaze, MainCamera, PetMarker};
+use crate::events::{LookAtEvent, TrackCursorEvent};
+use crate::resources::CursorPosition;
+
+/// Furthest the head turns away from the camera, in radians
+const MAX_GAZE_ANGLE: f32 = 0.7;
+/// How quickly the head turns towards its target; higher is snappier
+const GAZE_RATE: f32 = 8.0;
+/// Distance in front of the head, towards the camera, that the cursor is
+/// projected to; nearer makes the head turn further for the same cursor
+/// movement
+const LOOK_DEPTH: f32 = 1.5;
+/// Turns smaller than this, in radians, count as looking straight ahead
+const GAZE_EPSILON: f32 = 1e-3;
+
+/// Plugin for looking at and tracking the cursor
+pub struct GazePlugin;
+
+impl Plugin for GazePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GazeJoint>()
+            .init_resource::<CursorPosition>()
+            .add_systems(Update, (find_gaze_joint, handle_look_events))
+            .add_systems(
+                PostUpdate,
+                apply_gaze
+                    .after(AnimationSystems)
+                    .before(TransformSystems::Propagate),
+            );
+    }
+}
+
+/// The pet's head bone, if the model has one; the whole model turns
+/// otherwise
+#[derive(Resource, Default)]
+struct GazeJoint(Option<Entity>);
+
+/// Whether a node name names a head bone: "Head", "mixamorig:Head",
+/// "J_Bip_C_Head" and the like, but not "HeadTop_End" or "Forehead"
+fn is_head_bone(name: &str) -> bool {
+    let name = name.to_lowercase();
+    let Some(prefix) = name.strip_suffix("head") else {
+        return false;
+    };
+    prefix.is_empty() || prefix.ends_with([':', '_', '.', '-', ' ', '|'])
+}
+
+/// Pick the head bone out of the model's nodes as they spawn, preferring
+/// the one nearest the model's root when several match
+fn find_gaze_joint(
+    mut joint: ResMut<GazeJoint>,
+    names: Query<(Entity, &Name), Added<Name>>,
+    parents: Query<&ChildOf>,
+    pets: Query<(), With<PetMarker>>,
+) {
+    // Depth below the pet, only counting ancestors that are still around
+    let depth = |entity| {
+        parents
+            .iter_ancestors(entity)
+            .position(|ancestor| pets.contains(ancestor))
+    };
+    for (entity, name) in names.iter() {
+        if !is_head_bone(name.as_str()) {
+            continue;
+        }
+        let Some(candidate) = depth(entity) else {
+            continue;
+        };
+        // A joint left over from a previous model no longer has a pet above it
+        if joint
+            .0
+            .and_then(depth)
+            .is_some_and(|current| current <= candidate)
+        {
+            continue;
+        }
+        debug!("Using '{name}' as the head bone");
+        joint.0 = Some(entity);
+    }
+}
+
+/// Start and stop looking at the cursor
+fn handle_look_events(
+    mut commands: Commands,
+    mut look_events: MessageReader<LookAtEvent>,
+    mut track_events: MessageReader<TrackCursorEvent>,
+) {
+    for event in look_events.read() {
+        let (target, duration) = (event.target, event.duration);
+        commands
+            .entity(event.entity)
+            .entry::<Gaze>()
+            .or_default()
+            .and_modify(move |mut gaze| match target {
+                LookTarget::Cursor => gaze.look_remaining = duration,
+                LookTarget::Forward => {
+                    gaze.tracking = false;
+                    gaze.look_remaining = 0.0;
+                }
+            });
+    }
+
+    for event in track_events.read() {
+        let enabled = event.enabled;
+        commands
+            .entity(event.entity)
+            .entry::<Gaze>()
+            .or_default()
+            .and_modify(move |mut gaze| gaze.tracking = enabled);
+    }
+}
+
+/// Where the cursor is, as seen through the pet camera
+#[derive(SystemParam)]
+struct CursorView<'w, 's> {
+    cursor: Res<'w, CursorPosition>,
+    windows: Query<'w, 's, &'static Window, With<PrimaryWindow>>,
+    cameras: Query<'w, 's, (&'static Camera, &'static GlobalTransform), With<MainCamera>>,
+}
+
+impl CursorView<'_, '_> {
+    /// The world-space rotation turning a head at `head`, facing the
+    /// camera, towards the cursor, limited to `MAX_GAZE_ANGLE`
+    fn turn_towards(&self, head: Vec3) -> Option<Quat> {
+        let cursor = self.cursor.in_window(self.windows.single().ok()?)?;
+        let (camera, camera_transform) = self.cameras.single().ok()?;
+        let ray = camera.viewport_to_world(camera_transform, cursor).ok()?;
+        let to_camera = Dir3::new(camera_transform.translation() - head).ok()?;
+        let distance = ray.intersect_plane(
+            head + to_camera * LOOK_DEPTH,
+            InfinitePlane3d::new(to_camera),
+        )?;
+        let to_cursor = (ray.get_point(distance) - head).try_normalize()?;
+
+        let (axis, angle) = Quat::from_rotation_arc(*to_camera, to_cursor).to_axis_angle();
+        Some(Quat::from_axis_angle(axis, angle.min(MAX_GAZE_ANGLE)))
+    }
+}
+
+/// Turn the head towards the cursor, on top of whatever pose animations
+/// left it in, and back once the pet stops looking
+fn apply_gaze(
+    mut commands: Commands,
+    time: Res<Time>,
+    joint: Res<GazeJoint>,
+    view: CursorView,
+    mut pets: Query<(Entity, &mut Gaze), With<PetMarker>>,
+    mut transforms: Query<(&mut Transform, &GlobalTransform, Option<&ChildOf>)>,
+    parents: Query<&GlobalTransform>,
+) {
+    let delta = time.delta_secs();
+
+    for (pet, mut gaze) in pets.iter_mut() {
+        gaze.look_remaining = (gaze.look_remaining - delta).max(0.0);
+        let target_entity = joint.0.filter(|&e| transforms.contains(e)).unwrap_or(pet);
+        let Ok((mut transform, global, parent)) = transforms.get_mut(target_entity) else {
+            continue;
+        };
+
+        // Hold the current turn while the cursor can't be placed
+        let desired = if gaze.is_active() {
+            view.turn_towards(global.translation())
+                .unwrap_or(gaze.offset)
+        } else {
+            Quat::IDENTITY
+        };
+        let smoothing = 1.0 - (-GAZE_RATE * delta).exp();
+        gaze.offset = gaze.offset.slerp(desired, smoothing);
+
+        // Animations and tweens set a new pose each frame they run; turn
+        // the head relative to that rather than to the last turn
+        if gaze.written != Some(transform.rotation) {
+            gaze.base = transform.rotation;
+        }
+
+        if !gaze.is_active() && gaze.offset.angle_between(Quat::IDENTITY) < GAZE_EPSILON {
+            transform.rotation = gaze.base;
+            commands.entity(pet).remove::<Gaze>();
+            continue;
+        }
+
+        // Apply the world-space turn between the parent's rotation and the
+        // head's own
+        let parent_rotation = parent
+            .and_then(|child_of| parents.get(child_of.parent()).ok())
+            .map_or(Quat::IDENTITY, |parent| parent.rotation());
+        transform.rotation =
+            (parent_rotation.inverse() * gaze.offset * parent_rotation * gaze.base).normalize();
+        gaze.written = Some(transform.rotation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn head_bones_need_a_separator() {
+        for name in ["Head", "mixamorig:Head", "J_Bip_C_Head", "Armature|head"] {
+            assert!(is_head_bone(name), "{name}");
+        }
+        for name in ["Forehead", "Bulkhead", "HeadTop_End", "Neck"] {
+            assert!(!is_head_bone(name), "{name}");
+        }
+    }
+}
@@ -1,13 +1,33 @@
-//! Interaction plugin - handles window dragging and click detection
+//! Interaction plugin - handles window dragging, gesture detection,
+//! click-through and following the cursor across the desktop
 
-use crate::events::PetClickedEvent;
-use crate::resources::DragState;
+use super::speech::SpeechBubble;
+use crate::components::{MainCamera, PetMarker};
+use crate::events::{
+    FollowCursorEvent, PetClickedEvent, PetDoubleClickedEvent, PetDragEndedEvent,
+    PetDragStartedEvent, PetHoveredEvent, PetLongPressedEvent, PetRightClickedEvent,
+    PetScrolledEvent,
+};
+use crate::resources::{
+    CursorPosition, DOUBLE_CLICK_INTERVAL, DragState, PetActivity, TheaterConfig,
+};
+use bevy::camera::primitives::Aabb;
+use bevy::ecs::system::SystemParam;
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
+use bevy::math::Vec3A;
 use bevy::prelude::*;
-use bevy::window::PrimaryWindow;
+use bevy::ui::{ComputedNode, UiGlobalTransform};
+use bevy::window::{CursorEntered, CursorLeft, CursorMoved, CursorOptions, Monitor, PrimaryWindow};
 use bevy::winit::{UpdateMode, WinitSettings};
+use common::models::{ScreenArea, WindowSize};
+use tracing::info;
 
 /// Minimum distance in pixels to consider a mouse movement as a drag
 const DRAG_THRESHOLD: f32 = 5.0;
+/// Seconds the pet must be held without dragging to count as a long press
+const LONG_PRESS_DURATION: f32 = 0.8;
+/// Pixels of touchpad scrolling that count as one line of mouse wheel
+const PIXELS_PER_LINE: f32 = 20.0;
 
 /// Plugin for handling user interactions
 pub struct InteractionPlugin;
@@ -15,7 +35,22 @@ pub struct InteractionPlugin;
 impl Plugin for InteractionPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<DragState>()
-            .add_systems(Update, handle_drag);
+            .init_resource::<CursorPosition>()
+            .init_resource::<CursorFollow>()
+            .add_systems(
+                Update,
+                (
+                    keep_on_screen,
+                    update_cursor_position,
+                    update_click_through,
+                    handle_drag,
+                    detect_right_click,
+                    detect_hover,
+                    detect_scroll,
+                    follow_cursor,
+                )
+                    .chain(),
+            );
     }
 }
 
@@ -24,6 +59,20 @@ const LOW_POWER_INTERVAL: std::time::Duration = std::time::Duration::from_millis
 /// High-frequency update interval for dragging
 const DRAG_UPDATE_INTERVAL: std::time::Duration = std::time::Duration::from_millis(8);
 
+/// An ongoing `FollowCursor`
+struct Follow {
+    /// Logical pixels per second
+    speed: f32,
+    /// Distance from the cursor to stop at, in logical pixels
+    distance: f32,
+    /// Movement not yet applied because the window moves in whole pixels
+    carry: Vec2,
+}
+
+/// Resource holding the ongoing `FollowCursor`, if any
+#[derive(Resource, Default)]
+struct CursorFollow(Option<Follow>);
+
 /// Convert window-local cursor position to screen coordinates
 fn cursor_to_screen(window: &Window) -> Option<IVec2> {
     let cursor_pos = window.cursor_position()?;
@@ -34,17 +83,218 @@ fn cursor_to_screen(window: &Window) -> Option<IVec2> {
     Some(window_pos + IVec2::new(cursor_pos.x as i32, cursor_pos.y as i32))
 }
 
-/// Handle window dragging and click detection
+/// Where the pet starts on screen for a window at `position`: below the
+/// headroom speech bubbles grow the window by, so it doesn't depend on
+/// whether a bubble is showing
+fn pet_position(window: &Window, position: IVec2) -> IVec2 {
+    let headroom = window
+        .physical_height()
+        .saturating_sub(window.physical_width());
+    position + IVec2::new(0, headroom as i32)
+}
+
+/// Move a window restored off-screen, say after its monitor was unplugged,
+/// onto the nearest monitor, once the monitors are known
+fn keep_on_screen(
+    mut checked: Local<bool>,
+    monitors: Query<&Monitor>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if *checked || monitors.is_empty() {
+        return;
+    }
+    let Ok(mut window) = windows.single_mut() else {
+        return;
+    };
+    *checked = true;
+    let WindowPosition::At(position) = window.position else {
+        return;
+    };
+
+    let screens: Vec<ScreenArea> = monitors
+        .iter()
+        .map(|monitor| ScreenArea {
+            x: monitor.physical_position.x,
+            y: monitor.physical_position.y,
+            width: monitor.physical_width,
+            height: monitor.physical_height,
+        })
+        .collect();
+    let size = WindowSize::new(window.physical_width(), window.physical_height());
+    let clamped = common::models::WindowPosition::new(position.x, position.y)
+        .clamp_to_screens(size, &screens);
+    let clamped = IVec2::new(clamped.x, clamped.y);
+    if clamped != position {
+        info!("Pet position {position} is off-screen, moving it to {clamped}");
+        window.position = WindowPosition::At(clamped);
+    }
+}
+
+/// The pet's meshes and speech bubble, for telling whether the cursor is
+/// over the pet
+#[derive(SystemParam)]
+struct PetBounds<'w, 's> {
+    cameras: Query<'w, 's, (&'static Camera, &'static GlobalTransform), With<MainCamera>>,
+    meshes: Query<'w, 's, (Entity, &'static Aabb, &'static GlobalTransform), With<Mesh3d>>,
+    parents: Query<'w, 's, &'static ChildOf>,
+    pets: Query<'w, 's, (), With<PetMarker>>,
+    bubbles: Query<
+        'w,
+        's,
+        (
+            &'static ComputedNode,
+            &'static UiGlobalTransform,
+            &'static InheritedVisibility,
+        ),
+        With<SpeechBubble>,
+    >,
+}
+
+impl PetBounds<'_, '_> {
+    /// Whether `cursor`, in window coordinates, is over the speech bubble
+    /// or any of the pet's meshes' bounding boxes. Without a camera ray to
+    /// tell, the whole window counts as the pet, so it can still be
+    /// clicked.
+    fn contains(&self, window: &Window, cursor: Vec2) -> bool {
+        if self.bubble_contains(cursor * window.scale_factor()) {
+            return true;
+        }
+        let Some(ray) = self
+            .cameras
+            .single()
+            .ok()
+            .and_then(|(camera, transform)| camera.viewport_to_world(transform, cursor).ok())
+        else {
+            return true;
+        };
+        self.hit_by(ray)
+    }
+
+    /// Whether the cursor, wherever it is on screen, is within the window
+    /// and over the pet; not when its position is unknown
+    fn contains_cursor(&self, window: &Window, cursor: &CursorPosition) -> bool {
+        let size = Vec2::new(window.width(), window.height());
+        cursor.in_window(window).is_some_and(|cursor| {
+            cursor.cmpge(Vec2::ZERO).all()
+                && cursor.cmplt(size).all()
+                && self.contains(window, cursor)
+        })
+    }
+
+    /// Whether a showing speech bubble covers `point`, in physical pixels
+    fn bubble_contains(&self, point: Vec2) -> bool {
+        self.bubbles.iter().any(|(node, transform, visibility)| {
+            visibility.get() && node.contains_point(*transform, point)
+        })
+    }
+
+    /// Whether `ray` passes through any of the pet's meshes' bounding boxes
+    fn hit_by(&self, ray: Ray3d) -> bool {
+        self.meshes
+            .iter()
+            .filter(|(entity, ..)| {
+                self.parents
+                    .iter_ancestors(*entity)
+                    .any(|ancestor| self.pets.contains(ancestor))
+            })
+            .any(|(_, aabb, transform)| ray_hits_box(ray, aabb, transform))
+    }
+}
+
+/// Whether `ray` passes through `aabb` as placed by `transform`, using
+/// the slab test in the box's own space
+fn ray_hits_box(ray: Ray3d, aabb: &Aabb, transform: &GlobalTransform) -> bool {
+    let to_local = transform.affine().inverse();
+    let origin = Vec3A::from(to_local.transform_point3(ray.origin));
+    let direction = Vec3A::from(to_local.transform_vector3(*ray.direction));
+    let inverse = direction.recip();
+    let entry = (aabb.center - aabb.half_extents - origin) * inverse;
+    let exit = (aabb.center + aabb.half_extents - origin) * inverse;
+    let near = entry.min(exit).max_element();
+    let far = entry.max(exit).min_element();
+    far >= near.max(0.0)
+}
+
+/// Cursor events that tell whether the cursor is over the pet
+#[derive(SystemParam)]
+struct CursorEvents<'w, 's> {
+    motion: MessageReader<'w, 's, MouseMotion>,
+    moved: MessageReader<'w, 's, CursorMoved>,
+    entered: MessageReader<'w, 's, CursorEntered>,
+    left: MessageReader<'w, 's, CursorLeft>,
+}
+
+/// Let clicks through the window where the pet isn't drawn, unless the
+/// setting is off or the pet is being pressed or dragged.
+///
+/// Whether the cursor is over the pet is only worked out again as the
+/// cursor moves, enters or leaves the window. While clicks are let
+/// through the window hears nothing of the cursor, so as the mouse moves
+/// its tracked screen position is checked against the pet instead.
+fn update_click_through(
+    mut over_pet: Local<Option<bool>>,
+    config: Res<TheaterConfig>,
+    drag_state: Res<DragState>,
+    cursor: Res<CursorPosition>,
+    mut events: CursorEvents,
+    mut windows: Query<(&Window, &mut CursorOptions), With<PrimaryWindow>>,
+    bounds: PetBounds,
+) {
+    let Ok((window, mut cursor_options)) = windows.single_mut() else {
+        return;
+    };
+
+    if events.motion.read().count() > 0 && !cursor_options.hit_test {
+        *over_pet = Some(bounds.contains_cursor(window, &cursor));
+    }
+    if events.entered.read().count() > 0 {
+        *over_pet = Some(
+            window
+                .cursor_position()
+                .is_some_and(|cursor| bounds.contains(window, cursor)),
+        );
+    }
+    if let Some(moved) = events.moved.read().last() {
+        *over_pet = Some(bounds.contains(window, moved.position));
+    }
+    if events.left.read().count() > 0 {
+        *over_pet = Some(false);
+    }
+
+    // Catch clicks until the cursor has been seen
+    let hit_test = !config.click_through
+        || drag_state.drag_start_screen_cursor.is_some()
+        || over_pet.unwrap_or(true);
+    if cursor_options.hit_test != hit_test {
+        cursor_options.hit_test = hit_test;
+    }
+}
+
+/// Messages left button gestures are reported as
+#[derive(SystemParam)]
+struct PressMessages<'w> {
+    clicked: MessageWriter<'w, PetClickedEvent>,
+    double_clicked: MessageWriter<'w, PetDoubleClickedEvent>,
+    long_pressed: MessageWriter<'w, PetLongPressedEvent>,
+    drag_started: MessageWriter<'w, PetDragStartedEvent>,
+    drag_ended: MessageWriter<'w, PetDragEndedEvent>,
+}
+
+/// Handle window dragging, and click, double click and long press
+/// detection
 fn handle_drag(
     mut drag_state: ResMut<DragState>,
+    mut activity: ResMut<PetActivity>,
+    time: Res<Time>,
     mouse_button: Res<ButtonInput<MouseButton>>,
     mut windows: Query<&mut Window, With<PrimaryWindow>>,
-    mut click_events: MessageWriter<PetClickedEvent>,
+    mut messages: PressMessages,
     mut winit_settings: ResMut<WinitSettings>,
 ) {
     let Ok(mut window) = windows.single_mut() else {
         return;
     };
+    let now = time.elapsed_secs();
 
     // Mouse button pressed - record start position in screen coordinates
     if mouse_button.just_pressed(MouseButton::Left)
@@ -56,6 +306,9 @@ fn handle_drag(
             _ => None,
         };
         drag_state.is_dragging = false;
+        drag_state.pressed_at = Some(now);
+        drag_state.is_long_press = false;
+        activity.record_interaction(now);
     }
 
     // Mouse held - check for drag threshold and handle dragging
@@ -69,8 +322,15 @@ fn handle_drag(
         let delta = current_screen_cursor - start_screen_cursor;
 
         // Check if movement exceeds drag threshold
-        if !drag_state.is_dragging && (delta.x.abs() + delta.y.abs()) > DRAG_THRESHOLD as i32 {
+        if !drag_state.is_dragging
+            && !drag_state.is_long_press
+            && (delta.x.abs() + delta.y.abs()) > DRAG_THRESHOLD as i32
+        {
             drag_state.is_dragging = true;
+            drag_state.drag_started_at = Some(now);
+            messages.drag_started.write(PetDragStartedEvent {
+                position: pet_position(&window, start_window),
+            });
             // Switch to high-frequency updates for smooth dragging
             winit_settings.focused_mode = UpdateMode::reactive_low_power(DRAG_UPDATE_INTERVAL);
             winit_settings.unfocused_mode = UpdateMode::reactive_low_power(DRAG_UPDATE_INTERVAL);
@@ -79,16 +339,54 @@ fn handle_drag(
         // If dragging, update window position directly
         if drag_state.is_dragging {
             window.position = WindowPosition::At(start_window + delta);
+            activity.record_interaction(now);
         }
     }
 
+    // Mouse held still - check for a long press
+    if mouse_button.pressed(MouseButton::Left)
+        && !drag_state.is_dragging
+        && !drag_state.is_long_press
+        && drag_state
+            .pressed_at
+            .is_some_and(|pressed_at| now - pressed_at >= LONG_PRESS_DURATION)
+    {
+        drag_state.is_long_press = true;
+        messages.long_pressed.write(PetLongPressedEvent);
+        activity.record_interaction(now);
+    }
+
     // Mouse released - check if it was a click or end of drag
     if mouse_button.just_released(MouseButton::Left) {
-        if !drag_state.is_dragging && drag_state.drag_start_screen_cursor.is_some() {
-            click_events.write(PetClickedEvent);
+        if !drag_state.is_dragging
+            && !drag_state.is_long_press
+            && drag_state.drag_start_screen_cursor.is_some()
+        {
+            if drag_state
+                .last_click
+                .is_some_and(|last| now - last <= DOUBLE_CLICK_INTERVAL)
+            {
+                drag_state.last_click = None;
+                messages.double_clicked.write(PetDoubleClickedEvent);
+            } else {
+                drag_state.last_click = Some(now);
+                messages.clicked.write(PetClickedEvent);
+            }
+            activity.record_click(now);
         }
 
         if drag_state.is_dragging {
+            if let (Some(start), WindowPosition::At(end)) =
+                (drag_state.drag_start_window, window.position)
+            {
+                messages.drag_ended.write(PetDragEndedEvent {
+                    start: pet_position(&window, start),
+                    end: pet_position(&window, end),
+                    duration: drag_state
+                        .drag_started_at
+                        .map_or(0.0, |started_at| now - started_at),
+                });
+            }
             winit_settings.focused_mode = UpdateMode::reactive_low_power(LOW_POWER_INTERVAL);
             winit_settings.unfocused_mode = UpdateMode::reactive_low_power(LOW_POWER_INTERVAL);
         }
@@ -96,5 +394,377 @@ fn handle_drag(
         drag_state.is_dragging = false;
         drag_state.drag_start_screen_cursor = None;
         drag_state.drag_start_window = None;
+        drag_state.pressed_at = None;
+        drag_state.is_long_press = false;
+        drag_state.drag_started_at = None;
+    }
+}
+
+/// Report right clicks on the pet
+fn detect_right_click(
+    mut activity: ResMut<PetActivity>,
+    time: Res<Time>,
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut right_click_events: MessageWriter<PetRightClickedEvent>,
+) {
+    if !mouse_button.just_released(MouseButton::Right) {
+        return;
+    }
+    if windows
+        .single()
+        .is_ok_and(|window| window.cursor_position().is_some())
+    {
+        activity.record_interaction(time.elapsed_secs());
+        right_click_events.write(PetRightClickedEvent);
+    }
+}
+
+/// Report the cursor moving onto and off the pet's window
+fn detect_hover(
+    mut entered_events: MessageReader<CursorEntered>,
+    mut left_events: MessageReader<CursorLeft>,
+    mut activity: ResMut<PetActivity>,
+    time: Res<Time>,
+    mut hover_events: MessageWriter<PetHoveredEvent>,
+) {
+    for _event in entered_events.read() {
+        activity.record_interaction(time.elapsed_secs());
+        hover_events.write(PetHoveredEvent { hovering: true });
+    }
+    for _event in left_events.read() {
+        hover_events.write(PetHoveredEvent { hovering: false });
+    }
+}
+
+/// Report the mouse wheel turning over the pet, in lines
+fn detect_scroll(
+    mut wheel_events: MessageReader<MouseWheel>,
+    mut activity: ResMut<PetActivity>,
+    time: Res<Time>,
+    mut scroll_events: MessageWriter<PetScrolledEvent>,
+) {
+    let delta: f32 = wheel_events
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_LINE,
+        })
+        .sum();
+    if delta != 0.0 {
+        activity.record_interaction(time.elapsed_secs());
+        scroll_events.write(PetScrolledEvent { delta });
+    }
+}
+
+/// Keep track of where the cursor is on screen, also once it has left
+/// the window
+fn update_cursor_position(
+    mut motion_events: MessageReader<MouseMotion>,
+    mut cursor: ResMut<CursorPosition>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+) {
+    let motion: Vec2 = motion_events.read().map(|event| event.delta).sum();
+    let seen = windows.single().ok().and_then(cursor_to_screen);
+    let screen = tracked_cursor(cursor.screen, seen, motion);
+    if cursor.screen != screen {
+        cursor.screen = screen;
+    }
+}
+
+/// Where the cursor is on screen: where the window sees it, or else where
+/// it was last tracked to, moved on by the mouse's `motion` since
+fn tracked_cursor(last: Option<Vec2>, seen: Option<IVec2>, motion: Vec2) -> Option<Vec2> {
+    seen.map(|seen| seen.as_vec2())
+        .or_else(|| last.map(|last| last + motion))
+}
+
+/// Move the window towards the cursor until the pet is close enough, the
+/// user grabs it, or the cursor's position is unknown
+fn follow_cursor(
+    mut events: MessageReader<FollowCursorEvent>,
+    mut follow: ResMut<CursorFollow>,
+    cursor: Res<CursorPosition>,
+    drag_state: Res<DragState>,
+    time: Res<Time>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut winit_settings: ResMut<WinitSettings>,
+) {
+    if let Some(event) = events.read().last()
+        && event.speed > 0.0
+    {
+        follow.0 = Some(Follow {
+            speed: event.speed,
+            distance: event.distance.max(0.0),
+            carry: Vec2::ZERO,
+        });
+        // Move smoothly, as while dragging
+        winit_settings.focused_mode = UpdateMode::reactive_low_power(DRAG_UPDATE_INTERVAL);
+        winit_settings.unfocused_mode = UpdateMode::reactive_low_power(DRAG_UPDATE_INTERVAL);
+    }
+    let Some(state) = follow.0.as_mut() else {
+        return;
+    };
+    let Ok(mut window) = windows.single_mut() else {
+        return;
+    };
+
+    // Grabbing the pet takes over from following
+    let grabbed = drag_state.drag_start_screen_cursor.is_some();
+    if !grabbed && step_towards_cursor(state, &cursor, &mut window, time.delta_secs()) {
+        return;
+    }
+
+    follow.0 = None;
+    // A drag restores the low-power mode itself once it ends
+    if !drag_state.is_dragging {
+        winit_settings.focused_mode = UpdateMode::reactive_low_power(LOW_POWER_INTERVAL);
+        winit_settings.unfocused_mode = UpdateMode::reactive_low_power(LOW_POWER_INTERVAL);
+    }
+}
+
+/// Move the window one frame's worth towards the cursor. Returns `false`
+/// once the pet is close enough, or the cursor's position is unknown.
+fn step_towards_cursor(
+    follow: &mut Follow,
+    cursor: &CursorPosition,
+    window: &mut Window,
+    delta_secs: f32,
+) -> bool {
+    let (WindowPosition::At(position), Some(target)) = (window.position, cursor.in_window(window))
+    else {
+        return false;
+    };
+    // The pet stands in the square at the bottom of the window
+    let pet_center = Vec2::new(window.width() / 2.0, window.height() - window.width() / 2.0);
+    let offset = target - pet_center;
+    let remaining = offset.length() - follow.distance;
+    if remaining <= 0.0 {
+        return false;
+    }
+
+    follow.carry += offset.normalize() * remaining.min(follow.speed * delta_secs);
+    let whole = follow.carry.trunc();
+    follow.carry -= whole;
+    window.position = WindowPosition::At(position + whole.as_ivec2());
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::SystemState;
+
+    fn ray(origin: Vec3, direction: Vec3) -> Ray3d {
+        Ray3d::new(origin, Dir3::new(direction).unwrap())
+    }
+
+    fn unit_box() -> Aabb {
+        Aabb::from_min_max(Vec3::splat(-0.5), Vec3::splat(0.5))
+    }
+
+    #[test]
+    fn rays_hit_boxes_where_they_are_placed() {
+        let placed = GlobalTransform::from(
+            Transform::from_xyz(2.0, 0.0, 0.0).with_scale(Vec3::new(2.0, 1.0, 1.0)),
+        );
+        let forward = Vec3::Z;
+
+        assert!(ray_hits_box(
+            ray(Vec3::new(0.0, 0.0, -5.0), forward),
+            &unit_box(),
+            &GlobalTransform::IDENTITY
+        ));
+        assert!(!ray_hits_box(
+            ray(Vec3::new(0.0, 0.0, -5.0), forward),
+            &unit_box(),
+            &placed
+        ));
+        // Scaled to run from x = 1 to x = 3
+        assert!(ray_hits_box(
+            ray(Vec3::new(2.9, 0.0, -5.0), forward),
+            &unit_box(),
+            &placed
+        ));
+        assert!(!ray_hits_box(
+            ray(Vec3::new(3.1, 0.0, -5.0), forward),
+            &unit_box(),
+            &placed
+        ));
+        // Boxes behind the ray don't count
+        assert!(!ray_hits_box(
+            ray(Vec3::new(0.0, 0.0, 5.0), forward),
+            &unit_box(),
+            &GlobalTransform::IDENTITY
+        ));
+    }
+
+    #[test]
+    fn pet_bounds_cover_the_pet_and_its_bubble() {
+        let mut world = World::new();
+        world.spawn((PetMarker, Transform::default())).with_child((
+            Mesh3d::default(),
+            unit_box(),
+            GlobalTransform::IDENTITY,
+        ));
+        // A mesh that isn't the pet's
+        world.spawn((
+            Mesh3d::default(),
+            unit_box(),
+            GlobalTransform::from_xyz(5.0, 0.0, 0.0),
+        ));
+        world.spawn((
+            SpeechBubble,
+            ComputedNode {
+                size: Vec2::new(100.0, 40.0),
+                ..default()
+            },
+            UiGlobalTransform::from_translation(Vec2::new(100.0, 50.0)),
+            InheritedVisibility::VISIBLE,
+        ));
+        let window = Window::default();
+        let mut state = SystemState::<PetBounds>::new(&mut world);
+        let bounds = state.get(&world);
+
+        assert!(bounds.hit_by(ray(Vec3::new(0.0, 0.0, -5.0), Vec3::Z)));
+        assert!(!bounds.hit_by(ray(Vec3::new(5.0, 0.0, -5.0), Vec3::Z)));
+        assert!(bounds.bubble_contains(Vec2::new(60.0, 40.0)));
+        assert!(!bounds.bubble_contains(Vec2::new(200.0, 40.0)));
+        // Without a camera to cast a ray from, the whole window counts
+        assert!(bounds.contains(&window, Vec2::new(300.0, 300.0)));
+    }
+
+    #[test]
+    fn hidden_bubbles_are_not_part_of_the_pet() {
+        let mut world = World::new();
+        world.spawn((
+            SpeechBubble,
+            ComputedNode {
+                size: Vec2::new(100.0, 40.0),
+                ..default()
+            },
+            UiGlobalTransform::from_translation(Vec2::new(100.0, 50.0)),
+            InheritedVisibility::HIDDEN,
+        ));
+        let mut state = SystemState::<PetBounds>::new(&mut world);
+        let bounds = state.get(&world);
+
+        assert!(!bounds.bubble_contains(Vec2::new(100.0, 50.0)));
+    }
+
+    /// A 200x300 window at (100, 100), its pet centered at (200, 300)
+    fn window() -> Window {
+        Window {
+            position: WindowPosition::At(IVec2::new(100, 100)),
+            resolution: (200, 300).into(),
+            ..default()
+        }
+    }
+
+    fn follow(speed: f32, distance: f32) -> Follow {
+        Follow {
+            speed,
+            distance,
+            carry: Vec2::ZERO,
+        }
+    }
+
+    fn cursor_at(x: f32, y: f32) -> CursorPosition {
+        CursorPosition {
+            screen: Some(Vec2::new(x, y)),
+        }
+    }
+
+    #[test]
+    fn only_cursors_within_the_window_can_be_over_the_pet() {
+        let mut world = World::new();
+        let mut state = SystemState::<PetBounds>::new(&mut world);
+        let bounds = state.get(&world);
+        let window = window();
+
+        // Without a camera the whole window counts as the pet
+        assert!(bounds.contains_cursor(&window, &cursor_at(150.0, 350.0)));
+        assert!(!bounds.contains_cursor(&window, &cursor_at(350.0, 350.0)));
+        assert!(!bounds.contains_cursor(&window, &cursor_at(150.0, 50.0)));
+        assert!(!bounds.contains_cursor(&window, &CursorPosition::default()));
+    }
+
+    #[test]
+    fn following_moves_towards_the_cursor_in_whole_pixels() {
+        let mut window = window();
+        let mut follow = follow(6.0, 0.0);
+        let cursor = cursor_at(500.0, 300.0);
+
+        assert!(step_towards_cursor(&mut follow, &cursor, &mut window, 0.25));
+        assert_eq!(window.position, WindowPosition::At(IVec2::new(101, 100)));
+        assert!((follow.carry.x - 0.5).abs() < 1e-4);
+
+        assert!(step_towards_cursor(&mut follow, &cursor, &mut window, 0.25));
+        assert_eq!(window.position, WindowPosition::At(IVec2::new(103, 100)));
+        assert!(follow.carry.x.abs() < 1e-4);
+    }
+
+    #[test]
+    fn following_stops_short_of_the_cursor() {
+        let mut window = window();
+        let mut follow = follow(1000.0, 50.0);
+        let cursor = cursor_at(300.0, 300.0);
+
+        // Only the 50 pixels beyond the stopping distance are covered
+        assert!(step_towards_cursor(&mut follow, &cursor, &mut window, 1.0));
+        assert_eq!(window.position, WindowPosition::At(IVec2::new(150, 100)));
+        assert!(!step_towards_cursor(&mut follow, &cursor, &mut window, 1.0));
+        assert_eq!(window.position, WindowPosition::At(IVec2::new(150, 100)));
+    }
+
+    #[test]
+    fn the_cursor_is_tracked_by_mouse_motion_away_from_the_window() {
+        let motion = Vec2::new(30.0, -10.0);
+
+        // Over the window, its position wins
+        assert_eq!(
+            tracked_cursor(Some(Vec2::ZERO), Some(IVec2::new(150, 120)), motion),
+            Some(Vec2::new(150.0, 120.0))
+        );
+        // Away from it, the last position moves with the mouse
+        assert_eq!(
+            tracked_cursor(Some(Vec2::new(150.0, 120.0)), None, motion),
+            Some(Vec2::new(180.0, 110.0))
+        );
+        // Never seen, nothing to move
+        assert_eq!(tracked_cursor(None, None, motion), None);
+    }
+
+    #[test]
+    fn following_reaches_cursors_outside_the_window() {
+        let mut window = window();
+        let mut follow = follow(1000.0, 10.0);
+        // Left the window at its right edge, then moved on across the
+        // desktop
+        let last_seen = Some(Vec2::new(299.0, 300.0));
+        let cursor = CursorPosition {
+            screen: tracked_cursor(last_seen, None, Vec2::new(601.0, 0.0)),
+        };
+
+        assert!(step_towards_cursor(&mut follow, &cursor, &mut window, 0.5));
+        assert_eq!(window.position, WindowPosition::At(IVec2::new(600, 100)));
+        // The pet, centered at (700, 300), stops 10 pixels short of (900, 300)
+        assert!(step_towards_cursor(&mut follow, &cursor, &mut window, 0.5));
+        assert_eq!(window.position, WindowPosition::At(IVec2::new(790, 100)));
+        assert!(!step_towards_cursor(&mut follow, &cursor, &mut window, 0.5));
+    }
+
+    #[test]
+    fn following_stops_without_a_cursor() {
+        let mut window = window();
+        let mut follow = follow(100.0, 0.0);
+
+        assert!(!step_towards_cursor(
+            &mut follow,
+            &CursorPosition::default(),
+            &mut window,
+            1.0
+        ));
+        assert_eq!(window.position, WindowPosition::At(IVec2::new(100, 100)));
     }
 }
@@ -1,7 +1,10 @@
 //! IPC plugin - bridges UDS client with Bevy ECS
 
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
-use common::ipc::{IpcEnvelope, IpcMessage, MAX_MESSAGE_SIZE, ProcessId};
+use common::config::AudioSettings;
+use common::ipc::{IpcEnvelope, IpcMessage, MAX_MESSAGE_SIZE, ProcessId, ScriptSource};
+use common::script::{ScriptCall, TemplateError, ValidationContext, reading_time};
 use std::sync::Mutex;
 use std::sync::mpsc as std_mpsc;
 use tracing::{error, info, warn};
@@ -9,8 +12,15 @@ use tracing::{error, info, warn};
 use bevy::window::{PrimaryWindow, WindowLevel};
 
 use crate::components::PetMarker;
-use crate::events::{PetClickedEvent, SwitchScriptEvent};
-use crate::resources::{ScriptLibrary, TheaterConfig};
+use crate::events::{
+    PetClickedEvent, PetDoubleClickedEvent, PetDragEndedEvent, PetDragStartedEvent,
+    PetHoveredEvent, PetLongPressedEvent, PetRightClickedEvent, PetScrolledEvent, PetSwappedEvent,
+    SayEvent, SetVisibilityEvent, SwitchScriptEvent,
+};
+use crate::resources::{
+    AnimationMap, ExpressionMap, ScriptLibrary, SoundLibrary, SoundSettings, TheaterConfig,
+    Triggers,
+};
 
 /// Resource holding the IPC bridge channels.
 /// Mutex wrappers are needed because mpsc channels are !Sync.
@@ -46,7 +56,15 @@ impl Plugin for IpcPlugin {
             outgoing_tx: Mutex::new(outgoing_tx),
             connected,
         })
-        .add_systems(Update, (receive_ipc_messages, forward_clicks_to_ipc));
+        .add_systems(
+            Update,
+            (
+                receive_ipc_messages,
+                forward_gestures_to_ipc,
+                announce_animations
+                    .run_if(resource_changed::<AnimationMap>.or(resource_changed::<ExpressionMap>)),
+            ),
+        );
     }
 }
 
@@ -168,32 +186,105 @@ fn spawn_ipc_thread(
         .unwrap_or(false)
 }
 
+/// Scripts, and what scripts from the Brain are checked against
+#[derive(SystemParam)]
+struct Scripts<'w> {
+    library: ResMut<'w, ScriptLibrary>,
+    animation_map: Res<'w, AnimationMap>,
+    expression_map: Res<'w, ExpressionMap>,
+    sound_library: Res<'w, SoundLibrary>,
+    triggers: ResMut<'w, Triggers>,
+}
+
+impl Scripts<'_> {
+    fn validation_context(&self) -> ValidationContext {
+        self.library.validation_context(
+            &self.animation_map,
+            &self.expression_map,
+            &self.sound_library,
+        )
+    }
+}
+
+/// Messages incoming IPC messages are dispatched as
+#[derive(SystemParam)]
+struct IncomingMessages<'w> {
+    switch_script: MessageWriter<'w, SwitchScriptEvent>,
+    say: MessageWriter<'w, SayEvent>,
+    pet_swapped: MessageWriter<'w, PetSwappedEvent>,
+    set_visibility: MessageWriter<'w, SetVisibilityEvent>,
+}
+
 /// Bevy system: poll incoming IPC messages and dispatch to ECS events
 fn receive_ipc_messages(
     bridge: Res<IpcBridge>,
-    mut switch_events: MessageWriter<SwitchScriptEvent>,
-    mut script_library: ResMut<ScriptLibrary>,
+    mut messages: IncomingMessages,
+    mut scripts: Scripts,
+    mut config: ResMut<TheaterConfig>,
+    mut sound_settings: ResMut<SoundSettings>,
     mut pet_query: Query<&mut Transform, With<PetMarker>>,
     mut window_query: Query<&mut Window, With<PrimaryWindow>>,
 ) {
     let rx = bridge.incoming_rx.lock().unwrap();
     while let Ok(envelope) = rx.try_recv() {
         match envelope.payload {
-            IpcMessage::ExecuteScript { script } => {
+            IpcMessage::ExecuteScript {
+                script: ScriptSource::Inline(mut script),
+                args,
+            } => {
                 info!("IPC: received script '{}'", script.id);
+                if !args.is_empty() {
+                    warn!(
+                        "IPC: rejected script '{}': {}",
+                        script.id,
+                        TemplateError::NotATemplate(script.id.clone())
+                    );
+                    continue;
+                }
+                let context = scripts.validation_context();
+                let report = script.repair(&context);
+                if !report.is_valid() {
+                    warn!("IPC: rejected script '{}': {report}", script.id);
+                    continue;
+                }
+                for warning in report.warnings() {
+                    warn!("IPC: script '{}': {warning}", script.id);
+                }
                 let script_id = script.id.clone();
-                script_library.add(script);
-                switch_events.write(SwitchScriptEvent {
-                    script_id,
+                scripts.library.add(script);
+                messages.switch_script.write(SwitchScriptEvent {
+                    script: script_id.into(),
+                    force: true,
+                });
+            }
+            IpcMessage::ExecuteScript {
+                script: ScriptSource::Library(id),
+                args,
+            } => {
+                info!("IPC: running script '{id}'");
+                messages.switch_script.write(SwitchScriptEvent {
+                    script: ScriptCall::with_args(id, args),
                     force: true,
                 });
             }
+            IpcMessage::Say { text, duration } => {
+                info!("IPC: saying '{text}'");
+                let duration = duration.unwrap_or_else(|| reading_time(&text));
+                messages.say.write(SayEvent { text, duration });
+            }
             IpcMessage::UpdateAppearance {
                 pet_scale,
+                opacity,
                 always_on_top,
-                ..
+                click_through,
             } => {
-                info!("IPC: updating appearance (scale={pet_scale}, on_top={always_on_top})");
+                info!(
+                    "IPC: updating appearance (scale={pet_scale}, opacity={opacity}, on_top={always_on_top}, click_through={click_through})"
+                );
+                config.opacity = opacity;
+                config.click_through = click_through;
+                // Kept for the model of the next pet swapped in
+                config.pet_scale = pet_scale;
                 for mut transform in pet_query.iter_mut() {
                     transform.scale = Vec3::splat(pet_scale);
                 }
@@ -204,7 +295,28 @@ fn receive_ipc_messages(
                         WindowLevel::Normal
                     };
                 }
-                // TODO: opacity requires modifying material alpha values
+            }
+            IpcMessage::UpdateAudio { volume, muted } => {
+                info!("IPC: updating audio (volume={volume}, muted={muted})");
+                sound_settings.0 = AudioSettings { volume, muted };
+            }
+            IpcMessage::UpdateTriggers { triggers } => {
+                info!("IPC: updating trigger table");
+                scripts.triggers.table = triggers;
+                scripts.triggers.warn_unknown_scripts(&scripts.library);
+            }
+            IpcMessage::SwapPet { pet } => {
+                info!("IPC: swapping to pet '{}'", pet.name);
+                config.set_pet(&pet);
+                scripts.triggers.table = pet.triggers;
+                scripts.triggers.warn_unknown_scripts(&scripts.library);
+                messages.pet_swapped.write(PetSwappedEvent);
+            }
+            IpcMessage::SetPetVisible { visible } => {
+                info!("IPC: setting pet visibility to {visible}");
+                messages
+                    .set_visibility
+                    .write(SetVisibilityEvent { visible });
             }
             IpcMessage::Shutdown => {
                 info!("IPC: received shutdown");
@@ -220,16 +332,103 @@ fn receive_ipc_messages(
     }
 }
 
-/// Bevy system: forward PetClicked events to IPC
-fn forward_clicks_to_ipc(bridge: Res<IpcBridge>, mut click_events: MessageReader<PetClickedEvent>) {
+/// Bevy system: report the model's animations and expressions once the
+/// graph is built or the morph targets are found
+fn announce_animations(
+    bridge: Res<IpcBridge>,
+    animation_map: Res<AnimationMap>,
+    expression_map: Res<ExpressionMap>,
+) {
+    if !bridge.connected || (animation_map.graph.is_none() && expression_map.expressions.is_empty())
+    {
+        return;
+    }
+
+    let envelope = IpcEnvelope::new(
+        ProcessId::Theater,
+        ProcessId::Brain,
+        IpcMessage::AnimationsAvailable {
+            animations: animation_map.names(),
+            expressions: expression_map.names(),
+        },
+    );
+    let _ = bridge.outgoing_tx.lock().unwrap().send(envelope);
+}
+
+/// Gestures reported to the Brain
+#[derive(SystemParam)]
+struct Gestures<'w, 's> {
+    clicked: MessageReader<'w, 's, PetClickedEvent>,
+    double_clicked: MessageReader<'w, 's, PetDoubleClickedEvent>,
+    right_clicked: MessageReader<'w, 's, PetRightClickedEvent>,
+    long_pressed: MessageReader<'w, 's, PetLongPressedEvent>,
+    hovered: MessageReader<'w, 's, PetHoveredEvent>,
+    scrolled: MessageReader<'w, 's, PetScrolledEvent>,
+    drag_started: MessageReader<'w, 's, PetDragStartedEvent>,
+    drag_ended: MessageReader<'w, 's, PetDragEndedEvent>,
+}
+
+impl Gestures<'_, '_> {
+    /// This frame's gestures as IPC messages
+    fn read(&mut self) -> Vec<IpcMessage> {
+        let mut messages = Vec::new();
+        messages.extend(self.clicked.read().map(|_| IpcMessage::PetClicked));
+        messages.extend(
+            self.double_clicked
+                .read()
+                .map(|_| IpcMessage::PetDoubleClicked),
+        );
+        messages.extend(
+            self.right_clicked
+                .read()
+                .map(|_| IpcMessage::PetRightClicked),
+        );
+        messages.extend(self.long_pressed.read().map(|_| IpcMessage::PetLongPressed));
+        messages.extend(self.hovered.read().map(|event| IpcMessage::PetHovered {
+            hovering: event.hovering,
+        }));
+        messages.extend(
+            self.scrolled
+                .read()
+                .map(|event| IpcMessage::PetScrolled { delta: event.delta }),
+        );
+        messages.extend(
+            self.drag_started
+                .read()
+                .map(|event| IpcMessage::PetDragStarted {
+                    x: event.position.x,
+                    y: event.position.y,
+                }),
+        );
+        messages.extend(
+            self.drag_ended
+                .read()
+                .map(|event| IpcMessage::PetDragCompleted {
+                    x: event.end.x,
+                    y: event.end.y,
+                    start_x: event.start.x,
+                    start_y: event.start.y,
+                    duration: event.duration,
+                }),
+        );
+        messages
+    }
+}
+
+/// Bevy system: forward gestures to the Brain over IPC, and finished drags
+/// to the app as well
+fn forward_gestures_to_ipc(bridge: Res<IpcBridge>, mut gestures: Gestures) {
     if !bridge.connected {
         return;
     }
 
     let tx = bridge.outgoing_tx.lock().unwrap();
-    for _event in click_events.read() {
-        let envelope =
-            IpcEnvelope::new(ProcessId::Theater, ProcessId::Brain, IpcMessage::PetClicked);
+    for message in gestures.read() {
+        if matches!(message, IpcMessage::PetDragCompleted { .. }) {
+            let envelope = IpcEnvelope::new(ProcessId::Theater, ProcessId::App, message.clone());
+            let _ = tx.send(envelope);
+        }
+        let envelope = IpcEnvelope::new(ProcessId::Theater, ProcessId::Brain, message);
         let _ = tx.send(envelope);
     }
 }
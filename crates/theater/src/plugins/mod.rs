@@ -1,13 +1,23 @@
 //! Bevy plugins for the theater process
 
+mod gaze;
 mod interaction;
 #[cfg(unix)]
 mod ipc;
 mod pet;
 mod replay;
+mod script_watch;
+mod sound;
+mod speech;
+mod visibility;
 
+pub use gaze::GazePlugin;
 pub use interaction::InteractionPlugin;
 #[cfg(unix)]
 pub use ipc::IpcPlugin;
 pub use pet::PetPlugin;
 pub use replay::ReplayPlugin;
+pub use script_watch::ScriptWatchPlugin;
+pub use sound::SoundPlugin;
+pub use speech::SpeechPlugin;
+pub use visibility::VisibilityPlugin;
@@ -1,14 +1,27 @@
-//! Pet plugin - handles model loading, camera, and lighting
+//! Pet plugin - handles model loading, framing, opacity, camera, and
+//! lighting
 
-use crate::components::PetMarker;
-use crate::resources::{PetModelState, TheaterConfig};
+use crate::components::{MainCamera, PetMarker};
+use crate::events::PetSwappedEvent;
+use crate::resources::{AnimationMap, ExpressionMap, PetModelState, TheaterConfig};
+use bevy::camera::primitives::Aabb;
+use bevy::camera::{ImageRenderTarget, RenderTarget};
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureFormat};
+use bevy::ui::IsDefaultUiCamera;
+use bevy::window::PrimaryWindow;
 use bevy::world_serialization::WorldAssetRoot;
+use common::models::CameraFraming;
 use std::path::PathBuf;
 
 /// Default model filename in assets/pets directory
 const DEFAULT_MODEL_NAME: &str = "duck.glb";
 
+/// Height every model is scaled to, in world units, so scripted moves and
+/// the pet scale setting look the same whatever size the model was made
+pub(super) const MODEL_HEIGHT: f32 = 2.0;
+
 /// Plugin for pet-related functionality
 pub struct PetPlugin;
 
@@ -16,20 +29,37 @@ impl Plugin for PetPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<PetModelState>()
             .add_systems(Startup, setup_scene)
-            .add_systems(Update, spawn_gltf_scene)
+            .add_systems(
+                Update,
+                (
+                    load_swapped_model,
+                    spawn_gltf_scene,
+                    frame_model,
+                    apply_opacity,
+                )
+                    .chain(),
+            )
             // Run camera removal in PostUpdate to catch newly spawned cameras from scenes
             .add_systems(PostUpdate, remove_gltf_cameras);
     }
 }
 
-/// Marker for the main camera (to distinguish from GLTF-embedded cameras)
-#[derive(Component)]
-struct MainCamera;
-
 /// Marker for placeholder mesh shown while model loads
 #[derive(Component)]
 struct PlaceholderPet;
 
+/// The loaded scene under the pet entity. It holds the scale and offset
+/// fitting the model to the frame, so tweens and the pet scale setting,
+/// which move the pet entity, work on top of them.
+#[derive(Component, Default)]
+struct PetModel {
+    framed: bool,
+}
+
+/// The UI node showing the pet at less than full opacity
+#[derive(Component)]
+struct FadedPet;
+
 /// Setup the scene with camera and lighting
 fn setup_scene(
     mut commands: Commands,
@@ -39,7 +69,7 @@ fn setup_scene(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
-    // Camera - side view, pet facing left
+    // Camera - side view, pet facing left; fit to the model once it loads
     commands.spawn((
         Camera3d::default(),
         Transform::from_xyz(0.0, 2.0, -5.0).looking_at(Vec3::new(0.0, 1.0, 0.0), Vec3::Y),
@@ -85,28 +115,64 @@ fn setup_scene(
         PlaceholderPet,
     ));
 
-    // Determine model path
-    let model_path = config
+    pet_state.gltf_handle = load_model(&config, &asset_server);
+}
+
+/// Load the configured model, or the default one if it's missing
+fn load_model(config: &TheaterConfig, asset_server: &AssetServer) -> Option<Handle<Gltf>> {
+    let path = config
         .model_path
         .as_ref()
         .filter(|p| p.exists())
         .cloned()
-        .or_else(default_model_path);
+        .or_else(default_model_path)?;
+    Some(asset_server.load(path.to_string_lossy().to_string()))
+}
 
-    // Load GLTF model
-    if let Some(path) = model_path {
-        let gltf_handle: Handle<Gltf> = asset_server.load(path.to_string_lossy().to_string());
-        pet_state.gltf_handle = Some(gltf_handle);
+/// Start loading the new pet's model; the old one stays until it's ready
+fn load_swapped_model(
+    mut events: MessageReader<PetSwappedEvent>,
+    mut pet_state: ResMut<PetModelState>,
+    config: Res<TheaterConfig>,
+    asset_server: Res<AssetServer>,
+) {
+    if events.read().count() == 0 {
+        return;
     }
+    pet_state.gltf_handle = load_model(&config, &asset_server);
+    pet_state.spawned = false;
+    tracing::info!("Loading swapped pet model {:?}", config.model_path);
 }
 
-/// Spawn the GLTF scene once loaded
+/// Remove the placeholder or previous pet, along with its replay state,
+/// and forget its animations and expressions so the new model's are set
+/// up. The maps should be cleared without flagging them as changed, so the
+/// Brain hears of the new model's animations once they're set up rather
+/// than of an empty model in between.
+fn forget_previous_pet(
+    commands: &mut Commands,
+    previous: impl IntoIterator<Item = Entity>,
+    animation_map: &mut AnimationMap,
+    expression_map: &mut ExpressionMap,
+) {
+    for entity in previous {
+        commands.entity(entity).despawn();
+        tracing::info!("Despawned previous pet entity {:?}", entity);
+    }
+    *animation_map = AnimationMap::default();
+    *expression_map = ExpressionMap::default();
+}
+
+/// Spawn the GLTF scene once loaded, replacing the placeholder or the
+/// previous pet
 fn spawn_gltf_scene(
     mut commands: Commands,
     mut pet_state: ResMut<PetModelState>,
     config: Res<TheaterConfig>,
     gltf_assets: Res<Assets<Gltf>>,
-    placeholder_query: Query<Entity, With<PlaceholderPet>>,
+    mut animation_map: ResMut<AnimationMap>,
+    mut expression_map: ResMut<ExpressionMap>,
+    previous_query: Query<Entity, Or<(With<PlaceholderPet>, With<PetMarker>)>>,
 ) {
     if pet_state.spawned {
         return;
@@ -120,11 +186,12 @@ fn spawn_gltf_scene(
         return;
     };
 
-    // Remove placeholder
-    for entity in placeholder_query.iter() {
-        commands.entity(entity).despawn();
-        tracing::info!("Despawned placeholder entity {:?}", entity);
-    }
+    forget_previous_pet(
+        &mut commands,
+        previous_query.iter(),
+        animation_map.bypass_change_detection(),
+        expression_map.bypass_change_detection(),
+    );
 
     // Spawn the scene
     if let Some(scene_handle) = gltf
@@ -134,16 +201,234 @@ fn spawn_gltf_scene(
     {
         let pet_entity = commands
             .spawn((
-                WorldAssetRoot(scene_handle),
                 Transform::from_scale(Vec3::splat(config.pet_scale)),
+                Visibility::default(),
                 PetMarker,
             ))
+            .with_child((WorldAssetRoot(scene_handle), PetModel::default()))
             .id();
         pet_state.spawned = true;
         tracing::info!("Spawned GLTF scene as pet entity {:?}", pet_entity);
     }
 }
 
+/// Once the model's meshes have bounds, scale it to `MODEL_HEIGHT`, turn
+/// it to face the configured way, stand it on the ground centred on the
+/// pet's origin, and fit the camera around it
+fn frame_model(
+    config: Res<TheaterConfig>,
+    mut models: Query<(Entity, &mut Transform, &GlobalTransform, &mut PetModel)>,
+    children: Query<&Children>,
+    meshes: Query<(&Aabb, &GlobalTransform), With<Mesh3d>>,
+    mut cameras: Query<(&mut Transform, &Projection), (With<MainCamera>, Without<PetModel>)>,
+) {
+    for (entity, mut transform, global, mut model) in models.iter_mut() {
+        if model.framed {
+            continue;
+        }
+        // Bounds in the model's own space, before any fitting
+        let to_model = global.affine().inverse();
+        let Some((min, max)) = children
+            .iter_descendants(entity)
+            .filter_map(|descendant| meshes.get(descendant).ok())
+            .flat_map(|(aabb, mesh_transform)| {
+                let to_model = to_model * mesh_transform.affine();
+                box_corners(aabb).map(move |corner| Vec3::from(to_model.transform_point3a(corner)))
+            })
+            .fold(None, |bounds: Option<(Vec3, Vec3)>, point| {
+                Some(bounds.map_or((point, point), |(min, max)| {
+                    (min.min(point), max.max(point))
+                }))
+            })
+        else {
+            // The scene hasn't spawned yet, or its bounds aren't computed
+            continue;
+        };
+        model.framed = true;
+
+        let camera = cameras.single_mut().ok();
+        let fov = match camera.as_ref().map(|(_, projection)| *projection) {
+            Some(Projection::Perspective(perspective)) => perspective.fov,
+            _ => PerspectiveProjection::default().fov,
+        };
+        let Some(fit) = fit_model(min, max, &config.camera, fov) else {
+            tracing::warn!("Pet model has no extent, leaving it unframed");
+            continue;
+        };
+        *transform = fit.model;
+        if let Some((mut camera_transform, _)) = camera {
+            *camera_transform = fit.camera;
+        }
+        tracing::info!(
+            "Framed pet model {:?}: scaled by {}, camera {} away",
+            entity,
+            fit.model.scale.x,
+            fit.camera.translation.distance(fit.camera_target),
+        );
+    }
+}
+
+/// Where a model and the camera go to frame the model
+struct Fit {
+    /// The model's transform under the pet entity
+    model: Transform,
+    camera: Transform,
+    /// The point the camera looks at, halfway up the fitted model
+    camera_target: Vec3,
+}
+
+/// Fit a model whose bounds in its own space run from `min` to `max`:
+/// scale it to `MODEL_HEIGHT`, turn it to face the configured way, stand
+/// it on the ground centred on the pet's origin, and place a camera with
+/// vertical field of view `fov` so the model's bounding sphere fits in
+/// view. `None` if the model has no extent.
+fn fit_model(min: Vec3, max: Vec3, framing: &CameraFraming, fov: f32) -> Option<Fit> {
+    let size = max - min;
+    let height = if size.y > f32::EPSILON {
+        size.y
+    } else {
+        size.max_element()
+    };
+    if height <= f32::EPSILON {
+        return None;
+    }
+    let scale = MODEL_HEIGHT / height;
+    let rotation = Quat::from_rotation_y(framing.facing.to_radians());
+    let ground_centre = Vec3::new((min.x + max.x) / 2.0, min.y, (min.z + max.z) / 2.0);
+    let model = Transform {
+        translation: -(rotation * ground_centre * scale),
+        rotation,
+        scale: Vec3::splat(scale),
+    };
+
+    let camera_target = Vec3::Y * size.y * scale / 2.0;
+    let radius = size.length() * scale / 2.0;
+    let distance = radius * (1.0 + framing.margin.max(0.0)) / (fov / 2.0).sin();
+    let angle = framing.angle.to_radians();
+    let direction = Vec3::new(0.0, angle.sin(), -angle.cos());
+    let camera = Transform::from_translation(camera_target + direction * distance)
+        .looking_at(camera_target, Vec3::Y);
+    Some(Fit {
+        model,
+        camera,
+        camera_target,
+    })
+}
+
+/// The eight corners of `aabb`
+fn box_corners(aabb: &Aabb) -> [Vec3A; 8] {
+    std::array::from_fn(|i| {
+        let sign = |bit: usize| if i & bit == 0 { -1.0 } else { 1.0 };
+        aabb.center + aabb.half_extents * Vec3A::new(sign(1), sign(2), sign(4))
+    })
+}
+
+/// Show the pet at the configured opacity
+fn apply_opacity(mut commands: Commands, config: Res<TheaterConfig>, mut picture: PetPicture) {
+    picture.show(&mut commands, config.opacity.clamp(0.0, 1.0));
+}
+
+/// Where the pet camera draws, and how that reaches the window
+#[derive(SystemParam)]
+struct PetPicture<'w, 's> {
+    images: ResMut<'w, Assets<Image>>,
+    windows: Query<'w, 's, &'static Window, With<PrimaryWindow>>,
+    pet_cameras: Query<'w, 's, &'static mut RenderTarget, With<MainCamera>>,
+    ui_cameras: Query<'w, 's, &'static mut Camera, (With<IsDefaultUiCamera>, Without<MainCamera>)>,
+    faded: Query<'w, 's, (Entity, &'static mut ImageNode), With<FadedPet>>,
+}
+
+impl PetPicture<'_, '_> {
+    /// Below full opacity, have the pet camera draw into an image the size
+    /// of the window, and show that through a UI node at `opacity`, with
+    /// the UI camera clearing the window instead. Fading the finished
+    /// picture rather than each material keeps meshes hiding what's
+    /// behind them, so inner meshes and far sides don't show through.
+    ///
+    /// While faded, the pet costs an extra window-sized image and a draw
+    /// of it, and any materials of the model's own that are translucent
+    /// are still depth sorted mesh by mesh, as at full opacity.
+    fn show(&mut self, commands: &mut Commands, opacity: f32) {
+        let (Ok(window), Ok(mut target), Ok(mut ui_camera)) = (
+            self.windows.single(),
+            self.pet_cameras.single_mut(),
+            self.ui_cameras.single_mut(),
+        ) else {
+            return;
+        };
+
+        if opacity >= 1.0 {
+            if let Ok((entity, _)) = self.faded.single() {
+                commands.entity(entity).despawn();
+                *target = RenderTarget::default();
+                ui_camera.clear_color = ClearColorConfig::None;
+            }
+            return;
+        }
+
+        let size = window.physical_size();
+        if size.x == 0 || size.y == 0 {
+            return;
+        }
+        let scale_factor = window.scale_factor();
+
+        let Ok((_, mut node)) = self.faded.single_mut() else {
+            let image = self.images.add(Image::new_target_texture(
+                size.x,
+                size.y,
+                TextureFormat::Rgba8UnormSrgb,
+                None,
+            ));
+            *target = RenderTarget::Image(ImageRenderTarget {
+                handle: image.clone(),
+                scale_factor,
+            });
+            ui_camera.clear_color = ClearColorConfig::Default;
+            commands.spawn((
+                FadedPet,
+                Node {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    ..default()
+                },
+                ImageNode {
+                    color: Color::WHITE.with_alpha(opacity),
+                    ..ImageNode::new(image)
+                },
+                // Below the speech bubble
+                GlobalZIndex(-1),
+            ));
+            return;
+        };
+
+        if node.color.alpha() != opacity {
+            node.color.set_alpha(opacity);
+        }
+        // Follow the window as speech bubbles grow it
+        if self
+            .images
+            .get(&node.image)
+            .is_some_and(|image| image.size() != size)
+            && let Some(image) = self.images.get_mut(&node.image)
+        {
+            image.resize(Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            });
+        }
+        if let RenderTarget::Image(image_target) = &*target
+            && image_target.scale_factor != scale_factor
+        {
+            *target = RenderTarget::Image(ImageRenderTarget {
+                handle: node.image.clone(),
+                scale_factor,
+            });
+        }
+    }
+}
+
 /// Remove cameras embedded in GLTF scenes to avoid conflicts
 fn remove_gltf_cameras(
     mut commands: Commands,
@@ -159,3 +444,245 @@ fn remove_gltf_cameras(
 fn default_model_path() -> Option<PathBuf> {
     Some(PathBuf::from(format!("pets/{}", DEFAULT_MODEL_NAME)))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::window::WindowRef;
+
+    const FOV: f32 = std::f32::consts::FRAC_PI_2;
+
+    #[test]
+    fn box_corners_span_the_box() {
+        let aabb = Aabb::from_min_max(Vec3::new(-1.0, 0.0, 2.0), Vec3::new(1.0, 4.0, 3.0));
+        let corners = box_corners(&aabb);
+
+        let min = corners.iter().copied().reduce(Vec3A::min).unwrap();
+        let max = corners.iter().copied().reduce(Vec3A::max).unwrap();
+        assert_eq!(Vec3::from(min), Vec3::new(-1.0, 0.0, 2.0));
+        assert_eq!(Vec3::from(max), Vec3::new(1.0, 4.0, 3.0));
+        for (i, corner) in corners.iter().enumerate() {
+            assert!(!corners[..i].contains(corner), "{corner} repeated");
+        }
+    }
+
+    #[test]
+    fn models_are_scaled_to_height_and_stood_on_the_ground() {
+        let framing = CameraFraming {
+            angle: 0.0,
+            facing: 0.0,
+            margin: 0.0,
+        };
+        // 4 tall, floating 1 above the ground and off to the side
+        let fit = fit_model(
+            Vec3::new(2.0, 1.0, -1.0),
+            Vec3::new(4.0, 5.0, 1.0),
+            &framing,
+            FOV,
+        )
+        .unwrap();
+
+        let scale = MODEL_HEIGHT / 4.0;
+        assert_eq!(fit.model.scale, Vec3::splat(scale));
+        assert!(
+            fit.model
+                .translation
+                .abs_diff_eq(Vec3::new(-3.0 * scale, -scale, 0.0), 1e-5)
+        );
+
+        // The bounding sphere, radius sqrt(24) / 2 before scaling, just
+        // fits a 90 degree view, looking straight along +z
+        let radius = 24.0_f32.sqrt() / 2.0 * scale;
+        let distance = radius / (FOV / 2.0).sin();
+        assert!(
+            fit.camera_target
+                .abs_diff_eq(Vec3::Y * MODEL_HEIGHT / 2.0, 1e-5)
+        );
+        assert!(
+            fit.camera
+                .translation
+                .abs_diff_eq(fit.camera_target - Vec3::Z * distance, 1e-4)
+        );
+    }
+
+    #[test]
+    fn framing_overrides_turn_the_model_and_move_the_camera() {
+        let framing = CameraFraming {
+            angle: 30.0,
+            facing: 90.0,
+            margin: 0.5,
+        };
+        let fit = fit_model(Vec3::ZERO, Vec3::new(2.0, 2.0, 2.0), &framing, FOV).unwrap();
+
+        assert!(
+            fit.model
+                .rotation
+                .abs_diff_eq(Quat::from_rotation_y(90_f32.to_radians()), 1e-5)
+        );
+        // Turned about the ground centre, which stays on the pet's origin
+        let ground_centre = fit.model.transform_point(Vec3::new(1.0, 0.0, 1.0));
+        assert!(ground_centre.abs_diff_eq(Vec3::ZERO, 1e-5));
+
+        let radius = 12.0_f32.sqrt() / 2.0;
+        let distance = radius * 1.5 / (FOV / 2.0).sin();
+        let offset = fit.camera.translation - fit.camera_target;
+        assert!((offset.length() - distance).abs() < 1e-4);
+        assert!((offset.y / distance - 0.5).abs() < 1e-5);
+        assert!(offset.x.abs() < 1e-5);
+    }
+
+    #[test]
+    fn flat_models_fit_by_their_largest_side_and_empty_ones_not_at_all() {
+        let framing = CameraFraming::default();
+        let fit = fit_model(Vec3::ZERO, Vec3::new(4.0, 0.0, 1.0), &framing, FOV).unwrap();
+        assert_eq!(fit.model.scale, Vec3::splat(MODEL_HEIGHT / 4.0));
+
+        assert!(fit_model(Vec3::ONE, Vec3::ONE, &framing, FOV).is_none());
+    }
+
+    /// A 200x300 window with the pet and UI cameras
+    fn picture_world() -> (World, Entity, Entity) {
+        let mut world = World::new();
+        world.init_resource::<Assets<Image>>();
+        world.spawn((
+            Window {
+                resolution: (200, 300).into(),
+                ..default()
+            },
+            PrimaryWindow,
+        ));
+        let pet_camera = world.spawn((Camera::default(), MainCamera)).id();
+        let ui_camera = world
+            .spawn((
+                Camera {
+                    order: 1,
+                    clear_color: ClearColorConfig::None,
+                    ..default()
+                },
+                IsDefaultUiCamera,
+            ))
+            .id();
+        (world, pet_camera, ui_camera)
+    }
+
+    fn show(world: &mut World, opacity: f32) {
+        world
+            .run_system_once(move |mut commands: Commands, mut picture: PetPicture| {
+                picture.show(&mut commands, opacity);
+            })
+            .unwrap();
+    }
+
+    fn faded_pet(world: &mut World) -> Option<ImageNode> {
+        world
+            .query_filtered::<&ImageNode, With<FadedPet>>()
+            .iter(world)
+            .next()
+            .cloned()
+    }
+
+    #[test]
+    fn fading_shows_the_pet_through_an_image() {
+        let (mut world, pet_camera, ui_camera) = picture_world();
+
+        show(&mut world, 0.5);
+
+        let node = faded_pet(&mut world).unwrap();
+        assert!((node.color.alpha() - 0.5).abs() < 1e-6);
+        let target = world.get::<RenderTarget>(pet_camera).unwrap();
+        assert_eq!(target.as_image(), Some(&node.image));
+        let image = world.resource::<Assets<Image>>().get(&node.image).unwrap();
+        assert_eq!(image.size(), UVec2::new(200, 300));
+        assert!(matches!(
+            world.get::<Camera>(ui_camera).unwrap().clear_color,
+            ClearColorConfig::Default
+        ));
+    }
+
+    #[test]
+    fn the_faded_image_follows_the_window_and_opacity() {
+        let (mut world, _, _) = picture_world();
+        show(&mut world, 0.5);
+
+        let mut windows = world.query::<&mut Window>();
+        windows
+            .single_mut(&mut world)
+            .unwrap()
+            .resolution
+            .set_physical_resolution(200, 400);
+        show(&mut world, 0.25);
+
+        let node = faded_pet(&mut world).unwrap();
+        assert!((node.color.alpha() - 0.25).abs() < 1e-6);
+        let image = world.resource::<Assets<Image>>().get(&node.image).unwrap();
+        assert_eq!(image.size(), UVec2::new(200, 400));
+        assert_eq!(
+            world
+                .query_filtered::<(), With<FadedPet>>()
+                .iter(&world)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn full_opacity_draws_straight_to_the_window() {
+        let (mut world, pet_camera, ui_camera) = picture_world();
+        show(&mut world, 0.5);
+
+        show(&mut world, 1.0);
+
+        assert!(faded_pet(&mut world).is_none());
+        assert!(matches!(
+            world.get::<RenderTarget>(pet_camera).unwrap(),
+            RenderTarget::Window(WindowRef::Primary)
+        ));
+        assert!(matches!(
+            world.get::<Camera>(ui_camera).unwrap().clear_color,
+            ClearColorConfig::None
+        ));
+    }
+
+    #[test]
+    fn swapping_pets_forgets_the_old_model_quietly() {
+        let mut world = World::new();
+        let mut animation_map = AnimationMap::default();
+        animation_map
+            .name_to_index
+            .insert("walk".to_string(), AnimationGraph::new().root);
+        let mut expression_map = ExpressionMap::default();
+        expression_map
+            .expressions
+            .insert("happy".to_string(), vec![1.0]);
+        world.insert_resource(animation_map);
+        world.insert_resource(expression_map);
+        let pet = world.spawn(PetMarker).id();
+        let animations_changed = world.resource_ref::<AnimationMap>().last_changed();
+        let expressions_changed = world.resource_ref::<ExpressionMap>().last_changed();
+
+        world
+            .run_system_once(
+                |mut commands: Commands,
+                 pets: Query<Entity, With<PetMarker>>,
+                 mut animation_map: ResMut<AnimationMap>,
+                 mut expression_map: ResMut<ExpressionMap>| {
+                    forget_previous_pet(
+                        &mut commands,
+                        pets.iter(),
+                        animation_map.bypass_change_detection(),
+                        expression_map.bypass_change_detection(),
+                    );
+                },
+            )
+            .unwrap();
+
+        assert!(world.get_entity(pet).is_err());
+        let animation_map = world.resource_ref::<AnimationMap>();
+        assert!(animation_map.names().is_empty());
+        assert_eq!(animation_map.last_changed(), animations_changed);
+        let expression_map = world.resource_ref::<ExpressionMap>();
+        assert!(expression_map.names().is_empty());
+        assert_eq!(expression_map.last_changed(), expressions_changed);
+    }
+}
@@ -1,19 +1,28 @@
 //! Replay plugin - manages behavior script playback
 
 use bevy::prelude::*;
-use tracing::info;
+use common::storage::StorageService;
+use tracing::{info, warn};
 
 use crate::components::{PetMarker, ReplayState};
 use crate::events::{
-    ExecuteActionEvent, PetClickedEvent, PlayAnimationEvent, ScriptCompletedEvent,
-    SwitchScriptEvent,
+    ExecuteActionEvent, FollowCursorEvent, LookAtEvent, PetClickedEvent, PetDoubleClickedEvent,
+    PetDragEndedEvent, PetDragStartedEvent, PetHoveredEvent, PetLongPressedEvent,
+    PetRightClickedEvent, PetScrolledEvent, PetSwappedEvent, PlayAnimationEvent, PlaySoundEvent,
+    SayEvent, ScriptCompletedEvent, SetExpressionEvent, SwitchScriptEvent, TrackCursorEvent,
+    TriggerEvent,
+};
+use crate::resources::{
+    AnimationMap, ExpressionMap, PetActivity, PetModelState, ScriptLibrary, ScriptRng,
+    TheaterConfig, Triggers,
 };
-use crate::resources::{AnimationMap, PetModelState, ScriptLibrary};
 use crate::systems::{
-    advance_replay_time, check_script_completion, dispatch_actions, execute_keyframes,
-    handle_pet_click, handle_script_transition, link_animation_player, play_animation,
-    setup_animation_graph, update_bounce_tween, update_movement_tween, update_rotation_tween,
-    update_scale_tween,
+    advance_crossfades, advance_replay_time, check_idle_timeout, check_script_completion,
+    detect_clicks, detect_gesture_triggers, dispatch_actions, execute_keyframes,
+    forget_despawned_morphs, forget_unused_instances, handle_script_transition, handle_triggers,
+    link_animation_player, play_animation, set_expression, setup_animation_graph,
+    setup_expressions, update_bounce_tween, update_expression_tween, update_movement_tween,
+    update_rotation_tween, update_scale_tween,
 };
 
 /// Plugin for behavior script replay system
@@ -21,18 +30,46 @@ pub struct ReplayPlugin;
 
 impl Plugin for ReplayPlugin {
     fn build(&self, app: &mut App) {
+        let triggers = app
+            .world()
+            .get_resource::<TheaterConfig>()
+            .map(|config| config.triggers.clone())
+            .unwrap_or_default();
+
         app
             // Resources
             .init_resource::<ScriptLibrary>()
             .init_resource::<AnimationMap>()
+            .init_resource::<ExpressionMap>()
+            .init_resource::<PetActivity>()
+            .insert_resource(Triggers::new(triggers))
+            .init_resource::<ScriptRng>()
             // Messages
             .add_message::<SwitchScriptEvent>()
             .add_message::<ExecuteActionEvent>()
             .add_message::<PlayAnimationEvent>()
+            .add_message::<SetExpressionEvent>()
+            .add_message::<PlaySoundEvent>()
+            .add_message::<SayEvent>()
+            .add_message::<LookAtEvent>()
+            .add_message::<TrackCursorEvent>()
+            .add_message::<FollowCursorEvent>()
             .add_message::<PetClickedEvent>()
+            .add_message::<PetDoubleClickedEvent>()
+            .add_message::<PetRightClickedEvent>()
+            .add_message::<PetLongPressedEvent>()
+            .add_message::<PetHoveredEvent>()
+            .add_message::<PetScrolledEvent>()
+            .add_message::<PetDragStartedEvent>()
+            .add_message::<PetDragEndedEvent>()
+            .add_message::<TriggerEvent>()
+            .add_message::<PetSwappedEvent>()
             .add_message::<ScriptCompletedEvent>()
             // Startup systems
-            .add_systems(Startup, load_script_library)
+            .add_systems(
+                Startup,
+                (load_script_library, check_trigger_scripts).chain(),
+            )
             // Update systems
             .add_systems(
                 Update,
@@ -40,6 +77,10 @@ impl Plugin for ReplayPlugin {
                     // Animation setup (runs once when model is loaded)
                     setup_animation_graph,
                     link_animation_player,
+                    // Expression setup (runs as the model's meshes spawn
+                    // and despawn)
+                    forget_despawned_morphs,
+                    setup_expressions,
                     // Initialize ReplayState for new pets
                     initialize_replay_state,
                 )
@@ -49,8 +90,11 @@ impl Plugin for ReplayPlugin {
             .add_systems(
                 Update,
                 (
-                    // Handle click events
-                    handle_pet_click,
+                    // Interaction triggers
+                    detect_clicks,
+                    detect_gesture_triggers,
+                    check_idle_timeout,
+                    handle_triggers,
                     // Replay time management
                     advance_replay_time,
                     // Keyframe execution
@@ -58,10 +102,13 @@ impl Plugin for ReplayPlugin {
                     dispatch_actions,
                     // Action handlers
                     play_animation,
+                    advance_crossfades,
+                    set_expression,
                     update_movement_tween,
                     update_rotation_tween,
                     update_scale_tween,
                     update_bounce_tween,
+                    update_expression_tween,
                     // Script completion check
                     check_script_completion,
                 )
@@ -70,13 +117,19 @@ impl Plugin for ReplayPlugin {
             )
             .add_systems(
                 PostUpdate,
-                handle_script_transition.run_if(any_with_component::<ReplayState>),
+                (handle_script_transition, forget_unused_instances)
+                    .chain()
+                    .run_if(any_with_component::<ReplayState>),
             );
     }
 }
 
-/// Load builtin scripts into the script library
-fn load_script_library(mut script_library: ResMut<ScriptLibrary>) {
+/// Load builtin scripts into the script library, then the user's scripts
+/// directory on top, overriding builtins with the same ID
+fn load_script_library(
+    mut script_library: ResMut<ScriptLibrary>,
+    config: Option<Res<TheaterConfig>>,
+) {
     script_library.load_builtin();
 
     info!(
@@ -84,6 +137,34 @@ fn load_script_library(mut script_library: ResMut<ScriptLibrary>) {
         script_library.script_ids().len(),
         script_library.script_ids()
     );
+    info!(
+        "Loaded {} builtin templates: {:?}",
+        script_library.template_ids().len(),
+        script_library.template_ids()
+    );
+
+    let Some(config) = config else {
+        return;
+    };
+    let storage = StorageService::new(config.paths.clone());
+    let files = match storage.script_files() {
+        Ok(files) => files,
+        Err(e) => {
+            warn!("Failed to list scripts directory: {e}");
+            return;
+        }
+    };
+    for path in files {
+        match script_library.load_file(&path) {
+            Ok(ids) => info!("Loaded script {:?} from {}", ids, path.display()),
+            Err(e) => warn!("Skipping script file: {e}"),
+        }
+    }
+}
+
+/// Warn about trigger reactions naming scripts that didn't load
+fn check_trigger_scripts(triggers: Res<Triggers>, script_library: Res<ScriptLibrary>) {
+    triggers.warn_unknown_scripts(&script_library);
 }
 
 /// Initialize ReplayState for pets that don't have one yet
@@ -0,0 +1,134 @@
+//! Script watch plugin - hot reloads scripts when files in the scripts
+//! directory change
+
+use bevy::prelude::*;
+use common::script::ScriptFormat;
+use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::mpsc as std_mpsc;
+use tracing::{info, warn};
+
+use crate::components::ReplayState;
+use crate::resources::{AnimationMap, ExpressionMap, ScriptLibrary, SoundLibrary, TheaterConfig};
+
+/// Resource holding the scripts directory watcher.
+/// Mutex wrappers are needed because the watcher and receiver are !Sync.
+#[derive(Resource)]
+struct ScriptWatcher {
+    _watcher: Mutex<RecommendedWatcher>,
+    changes_rx: Mutex<std_mpsc::Receiver<PathBuf>>,
+}
+
+/// Bevy plugin for reloading scripts when their files change
+pub struct ScriptWatchPlugin;
+
+impl Plugin for ScriptWatchPlugin {
+    fn build(&self, app: &mut App) {
+        let Some(scripts_dir) = app
+            .world()
+            .get_resource::<TheaterConfig>()
+            .map(|config| config.paths.scripts_dir())
+        else {
+            warn!("No TheaterConfig, script hot reload disabled");
+            return;
+        };
+
+        let (changes_tx, changes_rx) = std_mpsc::channel();
+        let watcher = RecommendedWatcher::new(
+            move |res: notify::Result<notify::Event>| match res {
+                Ok(event) => {
+                    for path in event.paths {
+                        if ScriptFormat::from_path(&path).is_some() {
+                            let _ = changes_tx.send(path);
+                        }
+                    }
+                }
+                Err(e) => warn!("Script watcher error: {e}"),
+            },
+            Config::default(),
+        )
+        .and_then(|mut watcher| {
+            watcher.watch(&scripts_dir, RecursiveMode::NonRecursive)?;
+            Ok(watcher)
+        });
+
+        match watcher {
+            Ok(watcher) => {
+                info!("Watching {} for script changes", scripts_dir.display());
+                app.insert_resource(ScriptWatcher {
+                    _watcher: Mutex::new(watcher),
+                    changes_rx: Mutex::new(changes_rx),
+                })
+                .add_systems(Update, reload_changed_scripts);
+            }
+            Err(e) => warn!("Failed to watch {}: {e}", scripts_dir.display()),
+        }
+    }
+}
+
+/// Bevy system: reload changed script files and restart affected replays
+fn reload_changed_scripts(
+    watcher: Res<ScriptWatcher>,
+    mut script_library: ResMut<ScriptLibrary>,
+    animation_map: Res<AnimationMap>,
+    expression_map: Res<ExpressionMap>,
+    sound_library: Res<SoundLibrary>,
+    mut query: Query<&mut ReplayState>,
+) {
+    // An editor save usually produces several events for the same file
+    let changed_files: BTreeSet<PathBuf> = watcher.changes_rx.lock().unwrap().try_iter().collect();
+    if changed_files.is_empty() {
+        return;
+    }
+
+    let mut changed_ids = Vec::new();
+    for path in changed_files {
+        if !path.exists() {
+            if let Some(id) = script_library.remove_file(&path) {
+                info!("Script file {} removed, unloaded '{id}'", path.display());
+                changed_ids.push(id);
+            }
+            continue;
+        }
+
+        match script_library.load_file(&path) {
+            Ok(ids) => {
+                info!("Reloaded script {:?} from {}", ids, path.display());
+                changed_ids.extend(ids);
+            }
+            // Keep the previous version; the file may be mid-write
+            Err(e) => warn!("Failed to reload script: {e}"),
+        }
+    }
+
+    let context =
+        script_library.validation_context(&animation_map, &expression_map, &sound_library);
+    for id in &changed_ids {
+        if let Some(script) = script_library.get(id) {
+            for diagnostic in &script.validate(&context).diagnostics {
+                warn!("Script '{id}': {diagnostic}");
+            }
+        }
+    }
+
+    // Restart replays that were playing (or calling) a changed script from
+    // their top-level script, falling back to idle if it is gone
+    for mut state in query.iter_mut() {
+        if !state
+            .script_stack()
+            .any(|id| changed_ids.iter().any(|changed| changed == id))
+        {
+            continue;
+        }
+        let root = state.script_stack().last().unwrap_or("idle").to_string();
+        let restart_id = if script_library.get(&root).is_some() {
+            root
+        } else {
+            "idle".to_string()
+        };
+        info!("Restarting script '{restart_id}' after reload");
+        state.switch_to(restart_id);
+    }
+}
@@ -0,0 +1,122 @@
+//! Sound plugin - plays sound effects requested by behavior scripts
+
+use bevy::audio::Volume;
+use bevy::prelude::*;
+use tracing::{debug, info, warn};
+
+use crate::events::{PetSwappedEvent, PlaySoundEvent};
+use crate::resources::{AudioOutput, PlayedSound, SoundLibrary, SoundSettings, TheaterConfig};
+
+/// Plugin for sound effects
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        let audio = app
+            .world()
+            .get_resource::<TheaterConfig>()
+            .map(|config| config.audio.clone())
+            .unwrap_or_default();
+
+        app.init_resource::<SoundLibrary>()
+            .init_resource::<AudioOutput>()
+            .insert_resource(SoundSettings(audio))
+            .add_systems(Startup, load_sound_library)
+            .add_systems(
+                Update,
+                (
+                    reload_sound_library,
+                    stop_muted_sounds.run_if(resource_changed::<SoundSettings>),
+                    play_sounds,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Marker for sound effects still playing
+#[derive(Component)]
+struct SoundEffect;
+
+/// Load the builtin sounds, then the pet's own sounds on top
+fn load_sound_library(mut sound_library: ResMut<SoundLibrary>, config: Option<Res<TheaterConfig>>) {
+    let Some(config) = config else {
+        return;
+    };
+    for dir in config.sound_dirs() {
+        match sound_library.load_dir(dir) {
+            Ok(0) => {}
+            Ok(count) => info!("Loaded {count} sounds from {}", dir.display()),
+            Err(e) => warn!("Failed to list sounds in {}: {e}", dir.display()),
+        }
+    }
+}
+
+/// Swap the previous pet's sounds for the new pet's
+fn reload_sound_library(
+    mut events: MessageReader<PetSwappedEvent>,
+    mut sound_library: ResMut<SoundLibrary>,
+    config: Option<Res<TheaterConfig>>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+    *sound_library = SoundLibrary::default();
+    load_sound_library(sound_library, config);
+}
+
+/// Send requested sounds to the audio output, scaled by the global volume
+fn play_sounds(
+    mut commands: Commands,
+    mut events: MessageReader<PlaySoundEvent>,
+    sound_library: Res<SoundLibrary>,
+    settings: Res<SoundSettings>,
+    mut output: ResMut<AudioOutput>,
+    asset_server: Option<Res<AssetServer>>,
+) {
+    for event in events.read() {
+        let Some(path) = sound_library.get(&event.name) else {
+            warn!("Sound '{}' not found", event.name);
+            continue;
+        };
+        let volume = event.volume.clamp(0.0, 1.0) * settings.0.effective_volume();
+        if volume <= 0.0 {
+            continue;
+        }
+
+        match &mut *output {
+            AudioOutput::Device => {
+                let Some(asset_server) = asset_server.as_ref() else {
+                    continue;
+                };
+                commands.spawn((
+                    SoundEffect,
+                    AudioPlayer::new(asset_server.load(path.to_path_buf())),
+                    PlaybackSettings::DESPAWN.with_volume(Volume::Linear(volume)),
+                ));
+            }
+            AudioOutput::Null => {
+                debug!("Sound '{}' discarded by the null audio output", event.name);
+            }
+            AudioOutput::Recording(played) => played.push(PlayedSound {
+                name: event.name.clone(),
+                path: path.to_path_buf(),
+                volume,
+            }),
+        }
+    }
+}
+
+/// Cut off sounds still playing when audio is muted
+fn stop_muted_sounds(
+    mut commands: Commands,
+    settings: Res<SoundSettings>,
+    sounds: Query<Entity, With<SoundEffect>>,
+) {
+    if !settings.0.muted {
+        return;
+    }
+    for entity in sounds.iter() {
+        commands.entity(entity).despawn();
+    }
+}
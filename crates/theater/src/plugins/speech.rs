@@ -0,0 +1,412 @@
+//! Speech plugin - shows what the pet says in a bubble above it
+
+use std::collections::VecDeque;
+
+use bevy::camera::{ClearColorConfig, Viewport};
+use bevy::prelude::*;
+use bevy::text::LineBreak;
+use bevy::ui::{ComputedNode, IsDefaultUiCamera};
+use bevy::window::PrimaryWindow;
+use tracing::{debug, warn};
+
+use super::pet::MODEL_HEIGHT;
+use crate::components::{MainCamera, PetMarker};
+use crate::events::SayEvent;
+use crate::resources::DragState;
+
+/// Height above the pet's origin the bubble sits at, in model units: the
+/// top of the model, once it's framed
+const BUBBLE_ANCHOR_HEIGHT: f32 = MODEL_HEIGHT;
+/// Widest a bubble gets before its text wraps, in logical pixels
+const BUBBLE_MAX_WIDTH: f32 = 280.0;
+/// Space kept between the bubble and the window edges, in logical pixels
+const BUBBLE_MARGIN: f32 = 8.0;
+const BUBBLE_FONT_SIZE: f32 = 16.0;
+const BUBBLE_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.95);
+const BUBBLE_TEXT_COLOR: Color = Color::srgb(0.1, 0.1, 0.1);
+/// Time a bubble takes to fade in, and again to fade out, in seconds
+const FADE_DURATION: f32 = 0.3;
+/// Most bubbles waiting to be shown; older ones are dropped beyond this
+const MAX_QUEUED_SPEECH: usize = 8;
+/// The window grows in steps of this many logical pixels, so it doesn't
+/// resize every frame while text reflows
+const HEADROOM_STEP: f32 = 40.0;
+/// Most the window grows above the pet, in logical pixels
+const MAX_HEADROOM: f32 = 400.0;
+
+/// Plugin for the pet's speech bubble
+pub struct SpeechPlugin;
+
+impl Plugin for SpeechPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpeechQueue>()
+            .init_resource::<Headroom>()
+            .add_systems(Startup, setup_bubble)
+            .add_systems(
+                Update,
+                (
+                    queue_speech,
+                    show_speech,
+                    place_bubble,
+                    update_pet_viewport.run_if(resource_changed::<Headroom>),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Marker for the bubble's root node
+#[derive(Component)]
+pub(super) struct SpeechBubble;
+
+/// Marker for the bubble's text
+#[derive(Component)]
+struct SpeechText;
+
+struct Speech {
+    text: String,
+    duration: f32,
+}
+
+/// Speech waiting to be shown, and the bubble showing now
+#[derive(Resource, Default)]
+struct SpeechQueue {
+    pending: VecDeque<Speech>,
+    current: Option<Speech>,
+    /// Time the current bubble has been up, in seconds
+    elapsed: f32,
+}
+
+impl SpeechQueue {
+    /// Queue speech behind whatever is waiting, dropping the oldest once
+    /// the queue is full
+    fn push(&mut self, speech: Speech) {
+        if self.pending.len() >= MAX_QUEUED_SPEECH
+            && let Some(dropped) = self.pending.pop_front()
+        {
+            warn!("Speech queue full, dropping '{}'", dropped.text);
+        }
+        self.pending.push_back(speech);
+    }
+}
+
+/// Logical pixels the window currently extends above the pet's square
+#[derive(Resource, Default)]
+struct Headroom(f32);
+
+/// Spawn the (hidden) bubble and the camera that draws it
+fn setup_bubble(mut commands: Commands) {
+    // UI gets its own camera: the pet camera's viewport shrinks back to
+    // the pet's square while the window is grown
+    commands.spawn((
+        Camera2d,
+        Camera {
+            order: 1,
+            clear_color: ClearColorConfig::None,
+            ..default()
+        },
+        IsDefaultUiCamera,
+    ));
+
+    commands
+        .spawn((
+            SpeechBubble,
+            Node {
+                position_type: PositionType::Absolute,
+                max_width: Val::Px(BUBBLE_MAX_WIDTH),
+                padding: UiRect::axes(Val::Px(12.0), Val::Px(8.0)),
+                border_radius: BorderRadius::all(Val::Px(12.0)),
+                ..default()
+            },
+            BackgroundColor(BUBBLE_COLOR),
+            Visibility::Hidden,
+        ))
+        .with_child((
+            SpeechText,
+            Text::default(),
+            TextFont {
+                font_size: BUBBLE_FONT_SIZE,
+                ..default()
+            },
+            TextColor(BUBBLE_TEXT_COLOR),
+            TextLayout::new_with_linebreak(LineBreak::WordBoundary),
+        ));
+}
+
+/// Queue requested speech behind whatever is already showing
+fn queue_speech(mut events: MessageReader<SayEvent>, mut queue: ResMut<SpeechQueue>) {
+    for event in events.read() {
+        if event.text.trim().is_empty() || event.duration <= 0.0 {
+            continue;
+        }
+        queue.push(Speech {
+            text: event.text.clone(),
+            duration: event.duration,
+        });
+    }
+}
+
+/// Show queued speech one bubble at a time, fading each in and out
+fn show_speech(
+    time: Res<Time>,
+    mut queue: ResMut<SpeechQueue>,
+    mut bubbles: Query<(&mut Visibility, &mut BackgroundColor), With<SpeechBubble>>,
+    mut texts: Query<(&mut Text, &mut TextColor), With<SpeechText>>,
+) {
+    let (Ok((mut visibility, mut background)), Ok((mut text, mut text_color))) =
+        (bubbles.single_mut(), texts.single_mut())
+    else {
+        return;
+    };
+
+    queue.elapsed += time.delta_secs();
+    if queue
+        .current
+        .as_ref()
+        .is_some_and(|speech| queue.elapsed >= speech.duration)
+    {
+        queue.current = None;
+    }
+    if queue.current.is_none() {
+        let Some(speech) = queue.pending.pop_front() else {
+            *visibility = Visibility::Hidden;
+            return;
+        };
+        debug!("Saying '{}'", speech.text);
+        text.0.clone_from(&speech.text);
+        queue.current = Some(speech);
+        queue.elapsed = 0.0;
+    }
+
+    let Some(speech) = &queue.current else {
+        return;
+    };
+    let alpha = bubble_alpha(queue.elapsed, speech.duration);
+    *visibility = Visibility::Inherited;
+    background.0 = BUBBLE_COLOR.with_alpha(BUBBLE_COLOR.alpha() * alpha);
+    text_color.0 = BUBBLE_TEXT_COLOR.with_alpha(alpha);
+}
+
+/// Opacity of a bubble shown for `duration` seconds, `elapsed` seconds in
+fn bubble_alpha(elapsed: f32, duration: f32) -> f32 {
+    let fade = FADE_DURATION.min(duration / 2.0);
+    (elapsed / fade)
+        .min((duration - elapsed) / fade)
+        .clamp(0.0, 1.0)
+}
+
+/// Keep the bubble centered above the pet, growing the window upwards
+/// when it doesn't fit and shrinking it back once the last bubble is gone
+fn place_bubble(
+    queue: Res<SpeechQueue>,
+    mut headroom: ResMut<Headroom>,
+    mut drag_state: Option<ResMut<DragState>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    pets: Query<&GlobalTransform, With<PetMarker>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut bubbles: Query<(&mut Node, &ComputedNode), With<SpeechBubble>>,
+) {
+    let Ok(mut window) = windows.single_mut() else {
+        return;
+    };
+
+    if queue.current.is_none() {
+        if headroom.0 > 0.0 {
+            let height = -headroom.0;
+            grow_headroom(
+                &mut window,
+                &mut headroom,
+                drag_state.as_deref_mut(),
+                height,
+            );
+        }
+        return;
+    }
+
+    let (Ok((camera, camera_transform)), Ok((mut node, computed))) =
+        (cameras.single(), bubbles.single_mut())
+    else {
+        return;
+    };
+    // Before the model loads, point at where it will appear
+    let anchor = pets
+        .iter()
+        .next()
+        .map_or(Vec3::Y * BUBBLE_ANCHOR_HEIGHT, |transform| {
+            let (scale, _, translation) = transform.to_scale_rotation_translation();
+            translation + Vec3::Y * BUBBLE_ANCHOR_HEIGHT * scale.y
+        });
+    let Ok(anchor) = camera.world_to_viewport(camera_transform, anchor) else {
+        return;
+    };
+
+    let size = computed.size() * computed.inverse_scale_factor();
+    let max_left = (window.width() - size.x - BUBBLE_MARGIN).max(BUBBLE_MARGIN);
+    let left = (anchor.x - size.x / 2.0).clamp(BUBBLE_MARGIN, max_left);
+    let top = anchor.y - size.y;
+    node.left = Val::Px(left);
+    node.top = Val::Px(top.max(BUBBLE_MARGIN));
+
+    let height = headroom_growth(BUBBLE_MARGIN - top, headroom.0);
+    if height > 0.0 {
+        grow_headroom(
+            &mut window,
+            &mut headroom,
+            drag_state.as_deref_mut(),
+            height,
+        );
+    }
+}
+
+/// Logical pixels to grow the window by so a bubble `shortfall` pixels
+/// short of room fits, in whole steps and within the most headroom allowed
+fn headroom_growth(shortfall: f32, headroom: f32) -> f32 {
+    if shortfall <= 0.0 {
+        return 0.0;
+    }
+    ((shortfall / HEADROOM_STEP).ceil() * HEADROOM_STEP).min((MAX_HEADROOM - headroom).max(0.0))
+}
+
+/// Grow the window by `height` logical pixels at the top (shrinking for a
+/// negative height), keeping its bottom edge, and so the pet, in place on
+/// screen.
+///
+/// Does nothing until the window manager has placed the window: without
+/// its position the window could only grow downwards.
+fn grow_headroom(
+    window: &mut Window,
+    headroom: &mut Headroom,
+    drag_state: Option<&mut DragState>,
+    height: f32,
+) {
+    let WindowPosition::At(position) = window.position else {
+        return;
+    };
+    let size = window.size();
+    window.resolution.set(size.x, size.y + height);
+    headroom.0 += height;
+
+    let offset = IVec2::new(0, -(height * window.scale_factor()).round() as i32);
+    window.position = WindowPosition::At(position + offset);
+    // Keep an ongoing drag from snapping the window back
+    if let Some(start) = drag_state.and_then(|drag_state| drag_state.drag_start_window.as_mut()) {
+        *start += offset;
+    }
+}
+
+/// Keep the pet camera on the square below the headroom, so growing the
+/// window neither moves nor rescales the pet
+fn update_pet_viewport(
+    headroom: Res<Headroom>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut cameras: Query<&mut Camera, With<MainCamera>>,
+) {
+    let (Ok(window), Ok(mut camera)) = (windows.single(), cameras.single_mut()) else {
+        return;
+    };
+
+    camera.viewport = (headroom.0 > 0.0).then(|| {
+        let top = (headroom.0 * window.scale_factor()).round() as u32;
+        let size = window.physical_size();
+        Viewport {
+            physical_position: UVec2::new(0, top),
+            physical_size: UVec2::new(size.x, size.y.saturating_sub(top)),
+            ..default()
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn speech(text: &str) -> Speech {
+        Speech {
+            text: text.to_string(),
+            duration: 1.0,
+        }
+    }
+
+    #[test]
+    fn full_queue_drops_the_oldest_speech() {
+        let mut queue = SpeechQueue::default();
+        for i in 0..=MAX_QUEUED_SPEECH {
+            queue.push(speech(&i.to_string()));
+        }
+
+        assert_eq!(queue.pending.len(), MAX_QUEUED_SPEECH);
+        assert_eq!(queue.pending.front().unwrap().text, "1");
+        assert_eq!(
+            queue.pending.back().unwrap().text,
+            MAX_QUEUED_SPEECH.to_string()
+        );
+    }
+
+    #[test]
+    fn bubbles_fade_in_and_out() {
+        assert_eq!(bubble_alpha(0.0, 2.0), 0.0);
+        assert!((bubble_alpha(FADE_DURATION / 2.0, 2.0) - 0.5).abs() < 1e-5);
+        assert_eq!(bubble_alpha(1.0, 2.0), 1.0);
+        assert!((bubble_alpha(2.0 - FADE_DURATION / 2.0, 2.0) - 0.5).abs() < 1e-5);
+        assert_eq!(bubble_alpha(2.0, 2.0), 0.0);
+        // Short bubbles spend half their time fading in and half out
+        assert_eq!(bubble_alpha(0.2, 0.4), 1.0);
+        assert!((bubble_alpha(0.1, 0.4) - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn headroom_grows_in_steps_up_to_the_limit() {
+        assert_eq!(headroom_growth(-5.0, 0.0), 0.0);
+        assert_eq!(headroom_growth(1.0, 0.0), HEADROOM_STEP);
+        assert_eq!(
+            headroom_growth(HEADROOM_STEP + 1.0, 0.0),
+            2.0 * HEADROOM_STEP
+        );
+        assert_eq!(
+            headroom_growth(MAX_HEADROOM * 2.0, HEADROOM_STEP),
+            MAX_HEADROOM - HEADROOM_STEP
+        );
+        assert_eq!(headroom_growth(10.0, MAX_HEADROOM), 0.0);
+    }
+
+    #[test]
+    fn growing_keeps_the_bottom_edge_in_place() {
+        let mut window = Window {
+            position: WindowPosition::At(IVec2::new(100, 500)),
+            resolution: (200, 200).into(),
+            ..default()
+        };
+        let mut headroom = Headroom::default();
+        let mut drag_state = DragState {
+            drag_start_window: Some(IVec2::new(100, 500)),
+            ..default()
+        };
+
+        grow_headroom(&mut window, &mut headroom, Some(&mut drag_state), 80.0);
+
+        assert_eq!(window.height(), 280.0);
+        assert_eq!(headroom.0, 80.0);
+        assert_eq!(window.position, WindowPosition::At(IVec2::new(100, 420)));
+        assert_eq!(drag_state.drag_start_window, Some(IVec2::new(100, 420)));
+
+        grow_headroom(&mut window, &mut headroom, None, -80.0);
+        assert_eq!(window.height(), 200.0);
+        assert_eq!(headroom.0, 0.0);
+        assert_eq!(window.position, WindowPosition::At(IVec2::new(100, 500)));
+    }
+
+    #[test]
+    fn unplaced_windows_do_not_grow() {
+        let mut window = Window {
+            position: WindowPosition::Automatic,
+            resolution: (200, 200).into(),
+            ..default()
+        };
+        let mut headroom = Headroom::default();
+
+        grow_headroom(&mut window, &mut headroom, None, 80.0);
+
+        assert_eq!(window.height(), 200.0);
+        assert_eq!(headroom.0, 0.0);
+    }
+}
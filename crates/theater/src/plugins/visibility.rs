@@ -0,0 +1,128 @@
+//! Visibility plugin - hides the pet without exiting, suspending rendering
+//! and replay while hidden
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy::winit::{UpdateMode, WinitSettings};
+use tracing::info;
+
+use crate::events::SetVisibilityEvent;
+
+/// Update interval while hidden; only IPC messages need handling, and
+/// they don't wake the event loop
+const HIDDEN_UPDATE_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Plugin for hiding and showing the pet
+pub struct VisibilityPlugin;
+
+impl Plugin for VisibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<SetVisibilityEvent>()
+            .add_systems(Update, apply_visibility);
+    }
+}
+
+/// Hide or show the window. Hiding pauses the virtual clock, which stops
+/// replay, tweens and idle timeouts, and turns off the cameras.
+fn apply_visibility(
+    mut events: MessageReader<SetVisibilityEvent>,
+    mut shown_modes: Local<Option<(UpdateMode, UpdateMode)>>,
+    mut time: ResMut<Time<Virtual>>,
+    mut winit_settings: ResMut<WinitSettings>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut cameras: Query<&mut Camera>,
+) {
+    let Some(visible) = events.read().last().map(|event| event.visible) else {
+        return;
+    };
+    let Ok(mut window) = windows.single_mut() else {
+        return;
+    };
+    if window.visible == visible {
+        return;
+    }
+    info!("{} the pet", if visible { "Showing" } else { "Hiding" });
+
+    window.visible = visible;
+    for mut camera in cameras.iter_mut() {
+        camera.is_active = visible;
+    }
+
+    if visible {
+        time.unpause();
+        if let Some((focused, unfocused)) = shown_modes.take() {
+            winit_settings.focused_mode = focused;
+            winit_settings.unfocused_mode = unfocused;
+        }
+    } else {
+        time.pause();
+        *shown_modes = Some((winit_settings.focused_mode, winit_settings.unfocused_mode));
+        winit_settings.focused_mode = UpdateMode::reactive_low_power(HIDDEN_UPDATE_INTERVAL);
+        winit_settings.unfocused_mode = UpdateMode::reactive_low_power(HIDDEN_UPDATE_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::ReplayState;
+    use crate::systems::advance_replay_time;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    fn set_visible(app: &mut App, visible: bool) {
+        app.world_mut()
+            .write_message(SetVisibilityEvent { visible });
+        app.update();
+    }
+
+    fn run_frames(app: &mut App, frames: usize) {
+        for _ in 0..frames {
+            app.update();
+        }
+    }
+
+    #[test]
+    fn hiding_pauses_replay_until_shown() {
+        let shown = WinitSettings::desktop_app();
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, VisibilityPlugin))
+            .insert_resource(shown.clone())
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                100,
+            )))
+            .add_systems(Update, advance_replay_time);
+        let world = app.world_mut();
+        let window = world.spawn((Window::default(), PrimaryWindow)).id();
+        let camera = world.spawn(Camera::default()).id();
+        let pet = world.spawn(ReplayState::new("idle".to_string())).id();
+        run_frames(&mut app, 5);
+
+        set_visible(&mut app, false);
+        let hidden_at = app.world().get::<ReplayState>(pet).unwrap().elapsed_time;
+        run_frames(&mut app, 5);
+        let world = app.world();
+        assert!(world.resource::<Time<Virtual>>().is_paused());
+        assert!(!world.get::<Window>(window).unwrap().visible);
+        assert!(!world.get::<Camera>(camera).unwrap().is_active);
+        assert_ne!(
+            world.resource::<WinitSettings>().focused_mode,
+            shown.focused_mode
+        );
+        assert_eq!(
+            world.get::<ReplayState>(pet).unwrap().elapsed_time,
+            hidden_at
+        );
+
+        set_visible(&mut app, true);
+        run_frames(&mut app, 5);
+        let world = app.world();
+        assert!(!world.resource::<Time<Virtual>>().is_paused());
+        assert!(world.get::<Window>(window).unwrap().visible);
+        assert!(world.get::<Camera>(camera).unwrap().is_active);
+        let winit_settings = world.resource::<WinitSettings>();
+        assert_eq!(winit_settings.focused_mode, shown.focused_mode);
+        assert_eq!(winit_settings.unfocused_mode, shown.unfocused_mode);
+        assert!(world.get::<ReplayState>(pet).unwrap().elapsed_time > hidden_at);
+    }
+}
@@ -3,9 +3,19 @@
 use bevy::animation::graph::AnimationNodeIndex;
 use bevy::prelude::*;
 use common::AppPaths;
-use common::script::BehaviorScript;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use common::config::AudioSettings;
+use common::models::{CameraFraming, MorphWeight, Pet};
+use common::script::{
+    BehaviorScript, PetState, ScriptCall, ScriptError, ScriptTemplate, TemplateError,
+    ValidationContext,
+};
+use common::storage;
+use common::triggers::TriggerTable;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use tracing::warn;
 
 /// Configuration resource for the theater
 #[derive(Resource)]
@@ -14,18 +24,50 @@ pub struct TheaterConfig {
     pub model_path: Option<PathBuf>,
     /// Scale factor for the pet
     pub pet_scale: f32,
-    /// Window opacity (0.0 - 1.0)
-    /// TODO: implement opacity via material alpha
-    #[allow(dead_code)]
+    /// Pet opacity (0.0 - 1.0), applied to the pet as a whole
     pub opacity: f32,
     /// Whether the window stays on top
     pub always_on_top: bool,
+    /// Whether clicks on empty parts of the window fall through to the
+    /// windows below
+    pub click_through: bool,
     /// Initial window position (x, y)
     pub window_position: (i32, i32),
+    /// How the camera frames the active pet's model
+    pub camera: CameraFraming,
+    /// The active pet's expression mapping, by expression name
+    pub expressions: BTreeMap<String, Vec<MorphWeight>>,
+    /// Directory of the builtin sound effects
+    pub builtin_sound_dir: PathBuf,
+    /// The active pet's own sound effects, overriding builtins with the
+    /// same name
+    pub pet_sound_dir: Option<PathBuf>,
+    /// Global volume and mute
+    pub audio: AudioSettings,
+    /// The active pet's scripts for clicks, drags and other interactions
+    pub triggers: TriggerTable,
     /// Application paths
     pub paths: AppPaths,
 }
 
+impl TheaterConfig {
+    /// Switch to `pet`'s model, framing, expressions, sounds and trigger
+    /// table
+    pub fn set_pet(&mut self, pet: &Pet) {
+        self.model_path = Some(pet.model_path.clone());
+        self.camera = pet.camera;
+        self.expressions = pet.expressions.clone();
+        self.pet_sound_dir = Some(self.paths.sounds_dir(&pet.id));
+        self.triggers = pet.triggers.clone();
+    }
+
+    /// Directories sound effects are loaded from; later directories
+    /// override sounds with the same name
+    pub fn sound_dirs(&self) -> impl Iterator<Item = &Path> {
+        std::iter::once(self.builtin_sound_dir.as_path()).chain(self.pet_sound_dir.as_deref())
+    }
+}
+
 /// Resource to track pet model loading state
 #[derive(Resource, Default)]
 pub struct PetModelState {
@@ -33,6 +75,9 @@ pub struct PetModelState {
     pub gltf_handle: Option<Handle<Gltf>>,
 }
 
+/// Longest gap between the clicks of a double click, in seconds
+pub const DOUBLE_CLICK_INTERVAL: f32 = 0.4;
+
 /// Resource to track window dragging state
 #[derive(Resource, Default)]
 pub struct DragState {
@@ -40,12 +85,140 @@ pub struct DragState {
     /// Cursor position in screen coordinates when drag started
     pub drag_start_screen_cursor: Option<IVec2>,
     pub drag_start_window: Option<IVec2>,
+    /// Time the mouse button went down on the pet, in seconds
+    pub pressed_at: Option<f32>,
+    /// Whether the current press has been held long enough to count as a
+    /// long press rather than a click
+    pub is_long_press: bool,
+    /// Time the current drag passed the drag threshold, in seconds
+    pub drag_started_at: Option<f32>,
+    /// Time of the last click, for spotting double clicks
+    pub last_click: Option<f32>,
 }
 
-/// Script library resource - stores all loaded behavior scripts
+/// Resource tracking the mouse cursor across the desktop
+///
+/// Bevy only reports the cursor's position while it is over the window.
+/// Away from the window, the position is carried on from where it was
+/// last seen by how far the mouse moves, and set right again whenever the
+/// cursor comes back over the window.
+#[derive(Resource, Default)]
+pub struct CursorPosition {
+    /// Cursor position in screen coordinates, once it has been seen
+    pub screen: Option<Vec2>,
+}
+
+impl CursorPosition {
+    /// The cursor position relative to the window's top left corner, the
+    /// inverse of how screen coordinates are computed from the window's
+    /// cursor position; outside the window's bounds once the window has
+    /// moved away from it
+    pub fn in_window(&self, window: &Window) -> Option<Vec2> {
+        let WindowPosition::At(position) = window.position else {
+            return None;
+        };
+        Some(self.screen? - position.as_vec2())
+    }
+}
+
+/// Mood the pet settles back to without interaction
+const NEUTRAL_MOOD: f32 = 0.5;
+/// Mood gained per click
+const CLICK_MOOD_BOOST: f32 = 0.1;
+/// Mood recovered towards neutral per second
+const MOOD_DECAY_PER_SECOND: f32 = 0.005;
+/// Window for counting recent clicks, in seconds
+const CLICK_WINDOW: f32 = 60.0;
+
+/// Resource tracking user interaction, used to evaluate branch conditions
+#[derive(Resource)]
+pub struct PetActivity {
+    /// Time of the last interaction, in seconds since startup
+    pub last_interaction: f32,
+    /// Times of recent clicks, oldest first
+    pub recent_clicks: VecDeque<f32>,
+    /// Mood from 0 (unhappy) to 1 (happy)
+    pub mood: f32,
+    /// Time the mood was last updated
+    mood_updated: f32,
+    /// Time of day to report instead of the clock, for reproducible runs
+    pub fixed_hour: Option<f32>,
+}
+
+impl Default for PetActivity {
+    fn default() -> Self {
+        Self {
+            last_interaction: 0.0,
+            recent_clicks: VecDeque::new(),
+            mood: NEUTRAL_MOOD,
+            mood_updated: 0.0,
+            fixed_hour: None,
+        }
+    }
+}
+
+impl PetActivity {
+    /// Record any interaction (press, drag) with the pet
+    pub fn record_interaction(&mut self, now: f32) {
+        self.last_interaction = now;
+    }
+
+    /// Record a click, which also cheers the pet up
+    pub fn record_click(&mut self, now: f32) {
+        self.record_interaction(now);
+        self.decay_mood(now);
+        self.mood = (self.mood + CLICK_MOOD_BOOST).min(1.0);
+        self.recent_clicks.push_back(now);
+        self.prune_clicks(now);
+    }
+
+    /// Build the state used to evaluate branch conditions
+    pub fn snapshot(&mut self, now: f32) -> PetState {
+        self.decay_mood(now);
+        self.prune_clicks(now);
+        PetState {
+            hour: self.fixed_hour.unwrap_or_else(PetState::local_hour),
+            idle_seconds: now - self.last_interaction,
+            clicks_last_minute: self.recent_clicks.len() as u32,
+            mood: self.mood,
+        }
+    }
+
+    fn decay_mood(&mut self, now: f32) {
+        let step = (now - self.mood_updated) * MOOD_DECAY_PER_SECOND;
+        self.mood = if self.mood > NEUTRAL_MOOD {
+            (self.mood - step).max(NEUTRAL_MOOD)
+        } else {
+            (self.mood + step).min(NEUTRAL_MOOD)
+        };
+        self.mood_updated = now;
+    }
+
+    fn prune_clicks(&mut self, now: f32) {
+        while self
+            .recent_clicks
+            .front()
+            .is_some_and(|&time| now - time > CLICK_WINDOW)
+        {
+            self.recent_clicks.pop_front();
+        }
+    }
+}
+
+/// Script library resource - stores all loaded behavior scripts and
+/// templates.
+///
+/// Templates are expanded when invoked; the expanded script is kept apart
+/// from the loaded scripts under the call's `ScriptCall::instance_id`,
+/// until no pet is playing it any more.
 #[derive(Resource, Default)]
 pub struct ScriptLibrary {
     scripts: HashMap<String, BehaviorScript>,
+    templates: HashMap<String, ScriptTemplate>,
+    /// Expanded templates, by instance ID
+    instances: HashMap<String, BehaviorScript>,
+    /// Script ID loaded from each file in the scripts directory
+    files: HashMap<PathBuf, String>,
 }
 
 impl ScriptLibrary {
@@ -54,22 +227,239 @@ impl ScriptLibrary {
         self.scripts.insert(script.id.clone(), script);
     }
 
-    /// Get a script by its ID
+    /// Load a script file, replacing any script with the same ID.
+    ///
+    /// Returns the IDs whose definition changed: the loaded script, plus
+    /// the script the file previously defined if its ID was edited.
+    pub fn load_file(&mut self, path: &Path) -> Result<Vec<String>, ScriptError> {
+        let script = BehaviorScript::load(path)?;
+        let id = script.id.clone();
+        let mut changed = self.remove_file(path).into_iter().collect::<Vec<_>>();
+        self.add(script);
+        self.files.insert(path.to_path_buf(), id.clone());
+        if !changed.contains(&id) {
+            changed.push(id);
+        }
+        Ok(changed)
+    }
+
+    /// Forget a script file, restoring the builtin it overrode if any.
+    /// Returns the ID the file defined.
+    pub fn remove_file(&mut self, path: &Path) -> Option<String> {
+        let id = self.files.remove(path)?;
+        // Another file may still define the same ID
+        if let Some(other) = self
+            .files
+            .iter()
+            .find_map(|(other, other_id)| (other_id == &id).then(|| other.clone()))
+        {
+            if let Ok(script) = BehaviorScript::load(&other) {
+                self.add(script);
+            }
+            return Some(id);
+        }
+        match BehaviorScript::builtin_scripts()
+            .into_iter()
+            .find(|builtin| builtin.id == id)
+        {
+            Some(builtin) => self.add(builtin),
+            None => {
+                self.scripts.remove(&id);
+            }
+        }
+        Some(id)
+    }
+
+    /// Add a template to the library
+    pub fn add_template(&mut self, template: ScriptTemplate) {
+        self.templates.insert(template.id.clone(), template);
+    }
+
+    /// Resolve a call to the ID of a playable script, expanding templates.
+    /// Each set of template arguments gets its own script, so a caller
+    /// still on the call stack keeps its keyframes when the template is
+    /// called again with other arguments.
+    pub fn resolve(&mut self, call: &ScriptCall) -> Result<String, TemplateError> {
+        if let Some(template) = self.templates.get(&call.script) {
+            let mut script = template.instantiate(&call.args)?;
+            script.id = call.instance_id();
+            let id = script.id.clone();
+            self.instances.insert(id.clone(), script);
+            return Ok(id);
+        }
+
+        if !self.scripts.contains_key(&call.script) {
+            return Err(TemplateError::UnknownScript(call.script.clone()));
+        }
+        if !call.args.is_empty() {
+            return Err(TemplateError::NotATemplate(call.script.clone()));
+        }
+        Ok(call.script.clone())
+    }
+
+    /// Get a script or expanded template by its ID
     pub fn get(&self, id: &str) -> Option<&BehaviorScript> {
-        self.scripts.get(id)
+        self.scripts.get(id).or_else(|| self.instances.get(id))
     }
 
-    /// Load all builtin scripts into the library
+    /// Forget the expanded templates `in_use` doesn't keep
+    pub fn retain_instances(&mut self, mut in_use: impl FnMut(&str) -> bool) {
+        self.instances.retain(|id, _| in_use(id));
+    }
+
+    /// Whether a script or template with this ID exists
+    pub fn contains(&self, id: &str) -> bool {
+        self.scripts.contains_key(id) || self.templates.contains_key(id)
+    }
+
+    /// Load all builtin scripts and templates into the library
     pub fn load_builtin(&mut self) {
         for script in BehaviorScript::builtin_scripts() {
             self.add(script);
         }
+        for template in ScriptTemplate::builtin_templates() {
+            self.add_template(template);
+        }
     }
 
     /// Get a list of all script IDs
     pub fn script_ids(&self) -> Vec<&String> {
         self.scripts.keys().collect()
     }
+
+    /// Get a list of all template IDs
+    pub fn template_ids(&self) -> Vec<&String> {
+        self.templates.keys().collect()
+    }
+
+    /// Build a validation context from the library, the loaded animations
+    /// and the available sounds
+    pub fn validation_context(
+        &self,
+        animation_map: &AnimationMap,
+        expression_map: &ExpressionMap,
+        sound_library: &SoundLibrary,
+    ) -> ValidationContext {
+        let mut context =
+            ValidationContext::new().with_scripts(self.scripts.keys().chain(self.templates.keys()));
+        // Models without animations or morph targets, and pets without
+        // sounds, skip those actions anyway
+        if !animation_map.name_to_index.is_empty() {
+            context = context.with_animations(animation_map.names());
+        }
+        if !expression_map.expressions.is_empty() {
+            context = context.with_expressions(expression_map.names());
+        }
+        if !sound_library.sounds.is_empty() {
+            context = context.with_sounds(sound_library.names());
+        }
+        context
+    }
+}
+
+/// Random source for `Random` and `Branch` choices; seeded for
+/// reproducible runs
+#[derive(Resource)]
+pub struct ScriptRng(pub StdRng);
+
+impl ScriptRng {
+    pub fn seeded(seed: u64) -> Self {
+        Self(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl Default for ScriptRng {
+    fn default() -> Self {
+        Self(rand::make_rng())
+    }
+}
+
+/// Sound effects available to the pet, by name
+#[derive(Resource, Default)]
+pub struct SoundLibrary {
+    sounds: BTreeMap<String, PathBuf>,
+}
+
+impl SoundLibrary {
+    /// Add the sounds in `dir`, replacing any with the same name.
+    /// Returns how many sounds the directory has.
+    pub fn load_dir(&mut self, dir: &Path) -> common::Result<usize> {
+        let sounds = storage::sound_files(dir)?;
+        let count = sounds.len();
+        self.sounds.extend(sounds);
+        Ok(count)
+    }
+
+    /// Add a sound, replacing any with the same name
+    #[cfg(test)]
+    pub fn add(&mut self, name: impl Into<String>, path: PathBuf) {
+        self.sounds.insert(name.into(), path);
+    }
+
+    /// Get a sound's file by name
+    pub fn get(&self, name: &str) -> Option<&Path> {
+        self.sounds.get(name).map(PathBuf::as_path)
+    }
+
+    /// Sorted list of sound names
+    pub fn names(&self) -> Vec<String> {
+        self.sounds.keys().cloned().collect()
+    }
+}
+
+/// Global volume and mute, kept in sync with the audio settings
+#[derive(Resource, Default)]
+pub struct SoundSettings(pub AudioSettings);
+
+/// Where sound effects are sent
+#[derive(Resource, Default)]
+pub enum AudioOutput {
+    /// The default audio device
+    #[default]
+    Device,
+    /// Nowhere, for machines without an audio device
+    Null,
+    /// A list of the sounds played, for tests
+    Recording(Vec<PlayedSound>),
+}
+
+/// A sound effect sent to the audio output
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayedSound {
+    pub name: String,
+    pub path: PathBuf,
+    /// Volume after applying the global volume, from 0 to 1
+    pub volume: f32,
+}
+
+/// The active pet's trigger table, and what has triggered so far
+#[derive(Resource, Default)]
+pub struct Triggers {
+    pub table: TriggerTable,
+    /// Time of a click held back to see whether a second click makes it
+    /// a double click
+    pub pending_click: Option<f32>,
+    /// The `last_interaction` the idle timeout last triggered for, so it
+    /// triggers once per idle spell
+    pub idle_triggered_for: Option<f32>,
+}
+
+impl Triggers {
+    pub fn new(table: TriggerTable) -> Self {
+        Self { table, ..default() }
+    }
+
+    /// Warn about reactions naming scripts the library doesn't have
+    pub fn warn_unknown_scripts(&self, library: &ScriptLibrary) {
+        for (trigger, choice) in self.table.choices() {
+            if !library.contains(&choice.script) {
+                warn!(
+                    "Trigger '{trigger}' refers to unknown script '{}'",
+                    choice.script
+                );
+            }
+        }
+    }
 }
 
 /// Animation mapping resource - maps animation names to graph node indices
@@ -80,3 +470,124 @@ pub struct AnimationMap {
     /// Handle to the AnimationGraph
     pub graph: Option<Handle<AnimationGraph>>,
 }
+
+impl AnimationMap {
+    /// Sorted list of animation names
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.name_to_index.keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+/// Name of the expression with every morph target at rest
+pub const NEUTRAL_EXPRESSION: &str = "neutral";
+
+/// Expression mapping resource - maps expression names to morph target
+/// weights on the loaded model
+#[derive(Resource, Default)]
+pub struct ExpressionMap {
+    /// Weights for each expression, one per morph target
+    pub expressions: HashMap<String, Vec<f32>>,
+    /// Entities under the pet whose `MorphWeights` are driven by expressions
+    pub morph_entities: Vec<Entity>,
+}
+
+impl ExpressionMap {
+    /// Sorted list of expression names
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.expressions.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Build expressions for a model with the given morph targets, from
+    /// the pet's mapping or, without one, one expression per named target
+    pub fn build(
+        &mut self,
+        mapping: &BTreeMap<String, Vec<MorphWeight>>,
+        target_names: &[String],
+        target_count: usize,
+    ) {
+        self.expressions.clear();
+        self.expressions
+            .insert(NEUTRAL_EXPRESSION.to_string(), vec![0.0; target_count]);
+
+        if mapping.is_empty() {
+            for (target, name) in target_names.iter().enumerate().take(target_count) {
+                let mut weights = vec![0.0; target_count];
+                weights[target] = 1.0;
+                self.expressions.insert(name.clone(), weights);
+            }
+            return;
+        }
+
+        for (name, morphs) in mapping {
+            let mut weights = vec![0.0; target_count];
+            for morph in morphs {
+                match weights.get_mut(morph.target) {
+                    Some(weight) => *weight = morph.weight,
+                    None => warn!(
+                        "Expression '{name}' uses morph target {} but the model has {target_count}",
+                        morph.target
+                    ),
+                }
+            }
+            self.expressions.insert(name.clone(), weights);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::script::{ScriptArgs, TemplateParam};
+
+    fn library_with_template() -> ScriptLibrary {
+        let mut library = ScriptLibrary::default();
+        library.load_builtin();
+        library.add_template(ScriptTemplate {
+            id: "hop".to_string(),
+            params: vec![TemplateParam::required("height")],
+            script: serde_json::json!({
+                "duration": 1.0,
+                "keyframes": [
+                    { "time": 0.0, "action": { "type": "bounce", "height": "$height" } },
+                ],
+            }),
+        });
+        library
+    }
+
+    fn hop(height: f64) -> ScriptCall {
+        ScriptCall::with_args(
+            "hop",
+            ScriptArgs::from([("height".to_string(), serde_json::json!(height))]),
+        )
+    }
+
+    #[test]
+    fn expanded_templates_are_not_listed_as_scripts() {
+        let mut library = library_with_template();
+        let scripts = library.script_ids().len();
+
+        let id = library.resolve(&hop(0.5)).unwrap();
+
+        assert!(library.get(&id).is_some());
+        assert_eq!(library.script_ids().len(), scripts);
+        assert!(!library.script_ids().contains(&&id));
+    }
+
+    #[test]
+    fn unused_instances_are_forgotten() {
+        let mut library = library_with_template();
+        let kept = library.resolve(&hop(0.5)).unwrap();
+        let dropped = library.resolve(&hop(1.0)).unwrap();
+
+        library.retain_instances(|id| id == kept);
+
+        assert!(library.get(&kept).is_some());
+        assert!(library.get(&dropped).is_none());
+        assert!(library.get("idle").is_some());
+    }
+}
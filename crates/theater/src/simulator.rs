@@ -0,0 +1,567 @@
+//! Headless replay simulation, for testing behavior scripts without a
+//! window or GPU.
+//!
+//! The simulator runs the theater's [`ReplayPlugin`] on Bevy's minimal
+//! plugins, with a clock that advances a fixed step per frame and a seeded
+//! random source, and records every action a script executes and every
+//! sound it plays.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use common::script::{Action, ScriptCall, TemplateError};
+
+use crate::components::{PetMarker, ReplayState};
+#[cfg(test)]
+use crate::events::PetDoubleClickedEvent;
+use crate::events::{ExecuteActionEvent, PetClickedEvent};
+use crate::plugins::{ReplayPlugin, SoundPlugin};
+use crate::resources::{AudioOutput, PetActivity, ScriptLibrary, ScriptRng};
+#[cfg(test)]
+use crate::resources::{PlayedSound, SoundLibrary, SoundSettings, Triggers};
+
+/// Default simulation step, one frame at 60 fps
+pub const DEFAULT_STEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+/// Time of day branch conditions see unless told otherwise
+pub const DEFAULT_HOUR: f32 = 12.0;
+
+/// An action executed during a simulation
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEntry {
+    /// Simulated time the action executed at, in seconds
+    pub time: f32,
+    /// Script the action's keyframe belongs to
+    pub script_id: String,
+    pub action: Action,
+    /// The pet's transform at the end of the frame the action executed in
+    pub transform: Transform,
+}
+
+impl std::fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let action = serde_json::to_string(&self.action).map_err(|_| std::fmt::Error)?;
+        let Transform {
+            translation,
+            rotation,
+            scale,
+        } = self.transform;
+        write!(
+            f,
+            "{:.3}\t{}\t{action}\ttranslation={} rotation={} scale={}",
+            self.time,
+            self.script_id,
+            format_components(&translation.to_array()),
+            format_components(&rotation.to_array()),
+            format_components(&scale.to_array()),
+        )
+    }
+}
+
+/// Components rounded to 3 decimals, without negative zeros, so traces
+/// diff cleanly
+fn format_components(values: &[f32]) -> String {
+    let values: Vec<String> = values
+        .iter()
+        .map(|&v| {
+            let rounded = (v * 1000.0).round() / 1000.0;
+            format!("{:.3}", rounded + 0.0)
+        })
+        .collect();
+    format!("({})", values.join(", "))
+}
+
+/// Actions recorded so far
+#[derive(Resource, Default)]
+struct SimulationTrace(Vec<TraceEntry>);
+
+/// A headless theater with a single pet
+pub struct Simulator {
+    app: App,
+    pet: Entity,
+}
+
+impl Simulator {
+    /// Create a simulator whose clock advances by `step` each frame
+    pub fn with_step(seed: u64, step: Duration) -> Self {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(ReplayPlugin)
+            .add_plugins(SoundPlugin)
+            .insert_resource(AudioOutput::Recording(Vec::new()))
+            .init_resource::<SimulationTrace>()
+            .insert_resource(ScriptRng::seeded(seed))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO))
+            .add_systems(Last, record_trace);
+        app.finish();
+        app.cleanup();
+
+        // Run startup systems, loading the script library, without
+        // advancing the clock
+        app.update();
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(step));
+        // The virtual clock normally clamps long frames
+        let mut virtual_time = app.world_mut().resource_mut::<Time<Virtual>>();
+        let max_delta = virtual_time.max_delta().max(step);
+        virtual_time.set_max_delta(max_delta);
+        app.world_mut().resource_mut::<PetActivity>().fixed_hour = Some(DEFAULT_HOUR);
+
+        let pet = app
+            .world_mut()
+            .spawn((
+                PetMarker,
+                Transform::default(),
+                ReplayState::new("idle".to_string()),
+            ))
+            .id();
+
+        Self { app, pet }
+    }
+
+    /// The script library, e.g. to add scripts under test
+    pub fn library_mut(&mut self) -> Mut<'_, ScriptLibrary> {
+        self.app.world_mut().resource_mut::<ScriptLibrary>()
+    }
+
+    /// Set the time of day branch conditions see
+    pub fn set_hour(&mut self, hour: f32) {
+        self.app
+            .world_mut()
+            .resource_mut::<PetActivity>()
+            .fixed_hour = Some(hour);
+    }
+
+    /// Switch the pet to a script or template immediately
+    pub fn start(&mut self, call: impl Into<ScriptCall>) -> Result<(), TemplateError> {
+        let call = call.into();
+        let script_id = self.library_mut().resolve(&call)?;
+        self.replay_state_mut().switch_to(script_id);
+        Ok(())
+    }
+
+    /// Click the pet, as a click in the theater window would
+    pub fn click(&mut self) {
+        let now = self.elapsed();
+        let world = self.app.world_mut();
+        world.resource_mut::<PetActivity>().record_click(now);
+        world.write_message(PetClickedEvent);
+    }
+
+    /// Advance the simulation by one frame
+    pub fn step(&mut self) {
+        self.app.update();
+    }
+
+    /// Advance the simulation until at least `seconds` more have passed
+    pub fn run_for(&mut self, seconds: f32) {
+        let until = self.elapsed() + seconds;
+        while self.elapsed() < until {
+            self.step();
+        }
+    }
+
+    /// Simulated time since the simulator started, in seconds
+    pub fn elapsed(&self) -> f32 {
+        self.app.world().resource::<Time>().elapsed_secs()
+    }
+
+    fn replay_state_mut(&mut self) -> Mut<'_, ReplayState> {
+        self.app
+            .world_mut()
+            .get_mut::<ReplayState>(self.pet)
+            .unwrap()
+    }
+
+    /// Actions executed so far, oldest first
+    pub fn trace(&self) -> &[TraceEntry] {
+        &self.app.world().resource::<SimulationTrace>().0
+    }
+}
+
+/// Setup and inspection only the unit tests use
+#[cfg(test)]
+impl Simulator {
+    /// Create a simulator with the builtin scripts loaded, playing `idle`
+    pub fn new(seed: u64) -> Self {
+        Self::with_step(seed, DEFAULT_STEP)
+    }
+
+    /// The sounds scripts can play
+    pub fn sound_library_mut(&mut self) -> Mut<'_, SoundLibrary> {
+        self.app.world_mut().resource_mut::<SoundLibrary>()
+    }
+
+    /// Global volume and mute
+    pub fn sound_settings_mut(&mut self) -> Mut<'_, SoundSettings> {
+        self.app.world_mut().resource_mut::<SoundSettings>()
+    }
+
+    /// The pet's trigger table and trigger state
+    pub fn triggers_mut(&mut self) -> Mut<'_, Triggers> {
+        self.app.world_mut().resource_mut::<Triggers>()
+    }
+
+    /// Click the pet a second time, shortly after `click`
+    pub fn double_click(&mut self) {
+        let now = self.elapsed();
+        let world = self.app.world_mut();
+        world.resource_mut::<PetActivity>().record_click(now);
+        world.write_message(PetDoubleClickedEvent);
+    }
+
+    pub fn transform(&self) -> Transform {
+        *self.app.world().get::<Transform>(self.pet).unwrap()
+    }
+
+    pub fn replay_state(&self) -> &ReplayState {
+        self.app.world().get::<ReplayState>(self.pet).unwrap()
+    }
+
+    /// Sounds played so far, oldest first
+    pub fn played_sounds(&self) -> &[PlayedSound] {
+        match self.app.world().resource::<AudioOutput>() {
+            AudioOutput::Recording(played) => played,
+            _ => &[],
+        }
+    }
+}
+
+/// Record executed actions once the frame's tweens have been applied
+fn record_trace(
+    time: Res<Time>,
+    mut action_events: MessageReader<ExecuteActionEvent>,
+    transforms: Query<&Transform>,
+    mut trace: ResMut<SimulationTrace>,
+) {
+    for event in action_events.read() {
+        let Ok(transform) = transforms.get(event.entity) else {
+            continue;
+        };
+        trace.0.push(TraceEntry {
+            time: time.elapsed_secs(),
+            script_id: event.script_id.clone(),
+            action: event.action.clone(),
+            transform: *transform,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::BehaviorScript;
+    use common::script::{Keyframe, KeyframeTime, ScriptArgs, ScriptTemplate, TemplateParam};
+    use common::triggers::Trigger;
+
+    fn spin_keyframe() -> Keyframe {
+        Keyframe {
+            time: KeyframeTime::At(0.0),
+            action: Action::Spin {
+                turns: 1.0,
+                duration: None,
+                easing: None,
+            },
+        }
+    }
+
+    fn script(id: &str, keyframes: Vec<Keyframe>) -> BehaviorScript {
+        BehaviorScript {
+            id: id.to_string(),
+            duration: None,
+            keyframes,
+            next: None,
+            interruptible: true,
+            ..BehaviorScript::idle()
+        }
+    }
+
+    #[test]
+    fn nested_template_calls_keep_their_own_arguments() {
+        let mut simulator = Simulator::new(0);
+        let mut library = simulator.library_mut();
+        library.add_template(ScriptTemplate {
+            id: "lap".to_string(),
+            params: vec![
+                TemplateParam::required("x"),
+                TemplateParam::required("call"),
+            ],
+            script: serde_json::json!({
+                "duration": 1.0,
+                "keyframes": [
+                    { "time": 0.0, "action": { "type": "move_to", "x": "$x", "y": 0.0, "duration": 0.1 } },
+                    { "time": 0.2, "action": { "type": "call", "script": "$call" } },
+                    { "time": 0.4, "action": { "type": "move_to", "x": "$x", "y": 0.0, "duration": 0.1 } },
+                ],
+                "interruptible": true,
+            }),
+        });
+        // The same template, called with other arguments while the first
+        // expansion is paused on the call stack
+        library.add(script(
+            "middle",
+            vec![Keyframe {
+                time: KeyframeTime::At(0.0),
+                action: Action::Call {
+                    script: "lap".to_string(),
+                    args: ScriptArgs::from([
+                        ("x".to_string(), (-0.5).into()),
+                        ("call".to_string(), "rest".into()),
+                    ]),
+                },
+            }],
+        ));
+        library.add(script("rest", vec![]));
+
+        simulator
+            .start(ScriptCall::with_args(
+                "lap",
+                ScriptArgs::from([
+                    ("x".to_string(), 0.5.into()),
+                    ("call".to_string(), "middle".into()),
+                ]),
+            ))
+            .unwrap();
+        simulator.run_for(3.0);
+
+        let moves: Vec<f32> = simulator
+            .trace()
+            .iter()
+            .filter_map(|entry| match entry.action {
+                Action::MoveTo { x, .. } => Some(x),
+                _ => None,
+            })
+            .take(4)
+            .collect();
+        assert_eq!(moves, [0.5, -0.5, -0.5, 0.5]);
+    }
+
+    #[test]
+    fn script_stack_lists_callers_innermost_first() {
+        let call = |script: &str| Keyframe {
+            time: KeyframeTime::At(0.0),
+            action: Action::Call {
+                script: script.to_string(),
+                args: ScriptArgs::new(),
+            },
+        };
+        let mut simulator = Simulator::new(0);
+        let mut library = simulator.library_mut();
+        library.add(script("outer", vec![call("middle")]));
+        library.add(script("middle", vec![call("inner")]));
+        library.add(BehaviorScript {
+            duration: Some(5.0),
+            ..script("inner", vec![spin_keyframe()])
+        });
+
+        simulator.start("outer").unwrap();
+        simulator.run_for(1.0);
+        let stack: Vec<_> = simulator.replay_state().script_stack().collect();
+        assert_eq!(stack, ["inner", "middle", "outer"]);
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_trace() {
+        let run = |seed| {
+            let mut simulator = Simulator::new(seed);
+            simulator.run_for(60.0);
+            simulator
+                .trace()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        };
+
+        let trace = run(7);
+        assert!(trace.len() > 10);
+        assert_eq!(trace, run(7));
+    }
+
+    #[test]
+    fn happy_scales_the_pet_then_returns_to_idle() {
+        let mut simulator = Simulator::new(0);
+        simulator.start("happy").unwrap();
+        simulator.run_for(0.9);
+
+        let scale = simulator.transform().scale.x;
+        assert!((scale - 1.2).abs() < 1e-3, "scale {scale}");
+
+        simulator.run_for(1.5);
+        assert_eq!(simulator.replay_state().script_id, "idle");
+        let scripts: Vec<_> = simulator
+            .trace()
+            .iter()
+            .map(|entry| entry.script_id.as_str())
+            .collect();
+        assert_eq!(scripts.first(), Some(&"happy"));
+        assert_eq!(scripts.last(), Some(&"idle"));
+    }
+
+    #[test]
+    fn wait_holds_back_relative_keyframes() {
+        let mut simulator = Simulator::new(0);
+        simulator.library_mut().add(script(
+            "pause",
+            vec![
+                Keyframe {
+                    time: KeyframeTime::At(0.0),
+                    action: Action::Wait { duration: 1.0 },
+                },
+                Keyframe {
+                    time: KeyframeTime::AfterStart(0.5),
+                    action: Action::play_animation("jump"),
+                },
+            ],
+        ));
+        simulator.start("pause").unwrap();
+        simulator.run_for(2.0);
+
+        let jump = simulator
+            .trace()
+            .iter()
+            .find(|entry| matches!(&entry.action, Action::PlayAnimation { name, .. } if name == "jump"))
+            .unwrap();
+        assert!((jump.time - 1.5).abs() <= DEFAULT_STEP.as_secs_f32() * 1.5);
+    }
+
+    #[test]
+    fn clicks_switch_to_the_click_reaction() {
+        let mut simulator = Simulator::new(0);
+        simulator.step();
+        simulator.click();
+        simulator.step();
+        simulator.step();
+
+        assert_eq!(simulator.replay_state().script_id, "happy");
+    }
+
+    #[test]
+    fn double_clicks_use_their_own_reaction() {
+        let mut simulator = Simulator::new(0);
+        simulator
+            .library_mut()
+            .add(script("twirl", vec![spin_keyframe()]));
+        simulator
+            .triggers_mut()
+            .table
+            .reactions
+            .insert(Trigger::DoubleClick, vec!["twirl".into()]);
+        simulator.step();
+        simulator.click();
+        simulator.step();
+        simulator.click();
+        simulator.step();
+        simulator.step();
+
+        assert_eq!(simulator.replay_state().script_id, "twirl");
+    }
+
+    #[test]
+    fn double_click_gestures_use_their_own_reaction() {
+        let mut simulator = Simulator::new(0);
+        simulator
+            .library_mut()
+            .add(script("twirl", vec![spin_keyframe()]));
+        simulator
+            .triggers_mut()
+            .table
+            .reactions
+            .insert(Trigger::DoubleClick, vec!["twirl".into()]);
+        simulator.step();
+        simulator.click();
+        simulator.step();
+        simulator.double_click();
+        simulator.step();
+        simulator.step();
+        assert_eq!(simulator.replay_state().script_id, "twirl");
+
+        // A lone click still gets the click reaction, once it's clear no
+        // second click follows
+        simulator.click();
+        simulator.step();
+        simulator.step();
+        assert_eq!(simulator.replay_state().script_id, "twirl");
+        simulator.run_for(0.5);
+        assert_eq!(simulator.replay_state().script_id, "happy");
+    }
+
+    #[test]
+    fn idle_timeout_triggers_once_per_idle_spell() {
+        let mut simulator = Simulator::new(0);
+        simulator
+            .library_mut()
+            .add(script("doze", vec![spin_keyframe()]));
+        {
+            let mut triggers = simulator.triggers_mut();
+            triggers.table.idle_timeout = 4.0;
+            triggers
+                .table
+                .reactions
+                .insert(Trigger::IdleTimeout, vec!["doze".into()]);
+        }
+        simulator.run_for(10.0);
+
+        let dozes = |simulator: &Simulator| {
+            simulator
+                .trace()
+                .iter()
+                .filter(|entry| entry.script_id == "doze")
+                .count()
+        };
+        assert_eq!(dozes(&simulator), 1);
+
+        // The click reaction plays out before the pet dozes off again
+        simulator.click();
+        simulator.run_for(5.0);
+        assert_eq!(dozes(&simulator), 2);
+    }
+
+    #[test]
+    fn sounds_play_at_the_global_volume() {
+        let mut simulator = Simulator::new(0);
+        simulator
+            .sound_library_mut()
+            .add("quack", "sounds/quack.ogg".into());
+        simulator.sound_settings_mut().0.volume = 0.5;
+        let quack = |time: f32| Keyframe {
+            time: KeyframeTime::At(time),
+            action: Action::PlaySound {
+                name: "quack".to_string(),
+                volume: Some(0.5),
+            },
+        };
+        simulator
+            .library_mut()
+            .add(script("quack", vec![quack(0.0), quack(1.0)]));
+        simulator.start("quack").unwrap();
+        simulator.run_for(0.5);
+
+        let played = simulator.played_sounds();
+        assert_eq!(played.len(), 1);
+        assert_eq!(played[0].name, "quack");
+        assert_eq!(played[0].volume, 0.25);
+
+        simulator.sound_settings_mut().0.muted = true;
+        simulator.run_for(1.0);
+        assert_eq!(simulator.played_sounds().len(), 1);
+    }
+
+    #[test]
+    fn trace_lines_are_stable() {
+        let entry = TraceEntry {
+            time: 0.5,
+            script_id: "happy".to_string(),
+            action: Action::Scale {
+                factor: 1.2,
+                duration: None,
+                easing: None,
+            },
+            transform: Transform::from_xyz(-0.0001, 0.0, 1.0),
+        };
+        assert_eq!(
+            entry.to_string(),
+            "0.500\thappy\t{\"type\":\"scale\",\"factor\":1.2}\t\
+             translation=(0.000, 0.000, 1.000) rotation=(0.000, 0.000, 0.000, 1.000) \
+             scale=(1.000, 1.000, 1.000)"
+        );
+    }
+}
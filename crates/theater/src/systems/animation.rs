@@ -1,9 +1,11 @@
 //! Animation playback systems
 
-use bevy::animation::AnimationPlayer;
-use bevy::animation::graph::{AnimationGraph, AnimationGraphHandle};
+use bevy::animation::graph::{AnimationGraph, AnimationGraphHandle, AnimationNodeIndex};
+use bevy::animation::transition::AnimationTransitions;
+use bevy::animation::{AnimationPlayer, RepeatAnimation};
 use bevy::gltf::Gltf;
 use bevy::prelude::*;
+use std::time::Duration;
 use tracing::{debug, info, warn};
 
 use crate::components::{PetAnimationState, PetMarker, ReplayState};
@@ -67,10 +69,12 @@ pub fn setup_animation_graph(
     let graph_handle = graphs.add(graph);
     animation_map.graph = Some(graph_handle.clone());
 
-    // Attach graph to the player
-    commands
-        .entity(player_entity)
-        .insert(AnimationGraphHandle(graph_handle));
+    // Attach graph to the player, along with the transitions that fade
+    // out the previous clip when another starts
+    commands.entity(player_entity).insert((
+        AnimationGraphHandle(graph_handle),
+        AnimationTransitions::new(),
+    ));
 
     info!(
         "Animation graph setup complete with {} animations",
@@ -98,8 +102,8 @@ pub fn link_animation_player(
             find_animation_player_recursive(pet_entity, &children_query, &animation_players)
         {
             commands.entity(pet_entity).insert(PetAnimationState {
-                current_animation: None,
                 player_entity: Some(player_entity),
+                ..default()
             });
             info!(
                 "Linked AnimationPlayer {:?} to pet {:?}",
@@ -134,15 +138,16 @@ fn find_animation_player_recursive(
     None
 }
 
-/// Handle animation playback requests
+/// Handle animation playback requests, crossfading from the animation
+/// playing before
 pub fn play_animation(
     mut events: MessageReader<PlayAnimationEvent>,
     animation_map: Res<AnimationMap>,
     mut pet_query: Query<&mut PetAnimationState>,
-    mut animation_players: Query<&mut AnimationPlayer>,
+    mut animation_players: Query<(&mut AnimationPlayer, &mut AnimationTransitions)>,
 ) {
     for event in events.read() {
-        let Some(node_index) = animation_map.name_to_index.get(&event.animation_name) else {
+        let Some(&node_index) = animation_map.name_to_index.get(&event.animation_name) else {
             if animation_map.name_to_index.is_empty() {
                 debug!(
                     "Animation '{}' skipped: model has no animations",
@@ -162,23 +167,143 @@ pub fn play_animation(
             continue;
         };
 
-        // Update current animation name
-        pet_state.current_animation = Some(event.animation_name.clone());
-
         // Get the animation player
         let Some(player_entity) = pet_state.player_entity else {
             warn!("No AnimationPlayer linked to pet entity {:?}", event.entity);
             continue;
         };
 
-        let Ok(mut player) = animation_players.get_mut(player_entity) else {
+        let Ok((mut player, mut transitions)) = animation_players.get_mut(player_entity) else {
             warn!("AnimationPlayer not found on entity {:?}", player_entity);
             continue;
         };
 
-        // Play the animation
-        player.play(*node_index).repeat();
+        // A loop that's already playing carries on rather than restarting;
+        // anything else starts over, fading out what was playing
+        let continuing = continues_loop(
+            &player,
+            &transitions,
+            node_index,
+            pet_state.looping,
+            event.looping,
+        );
+        let crossfade = event.crossfade.max(0.0);
+        let animation = if continuing {
+            player.play(node_index)
+        } else {
+            transitions.play(&mut player, node_index, Duration::from_secs_f32(crossfade))
+        };
+        animation
+            .set_speed(event.speed)
+            .set_repeat(if event.looping {
+                RepeatAnimation::Forever
+            } else {
+                RepeatAnimation::Never
+            });
+
+        if !continuing {
+            pet_state.start(event.animation_name.clone(), event.looping, crossfade);
+        }
+
+        info!(
+            "Playing animation '{}' ({}, speed {}, crossfade {crossfade}s)",
+            event.animation_name,
+            if event.looping { "looping" } else { "once" },
+            event.speed
+        );
+    }
+}
+
+/// Keep the pets' blend state in step with their crossfades
+pub fn advance_crossfades(time: Res<Time>, mut pet_query: Query<&mut PetAnimationState>) {
+    for mut pet_state in &mut pet_query {
+        if pet_state.fading_from.is_some() {
+            pet_state.advance_crossfade(time.delta_secs());
+        }
+    }
+}
+
+/// Whether a request to play `node` can leave it running: only a loop
+/// that's still the main animation and was asked to loop last time too
+fn continues_loop(
+    player: &AnimationPlayer,
+    transitions: &AnimationTransitions,
+    node: AnimationNodeIndex,
+    was_looping: bool,
+    looping: bool,
+) -> bool {
+    looping
+        && was_looping
+        && transitions.get_main_animation() == Some(node)
+        && player
+            .animation(node)
+            .is_some_and(|animation| !animation.is_finished())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playing(node: AnimationNodeIndex) -> (AnimationPlayer, AnimationTransitions) {
+        let mut player = AnimationPlayer::default();
+        let mut transitions = AnimationTransitions::new();
+        transitions.play(&mut player, node, Duration::ZERO);
+        (player, transitions)
+    }
+
+    #[test]
+    fn one_shot_then_same_clip_looping_restarts() {
+        let node = AnimationNodeIndex::new(1);
+        let (player, transitions) = playing(node);
+
+        assert!(!continues_loop(&player, &transitions, node, false, true));
+    }
+
+    #[test]
+    fn crossfades_track_the_outgoing_clip_until_done() {
+        let mut state = PetAnimationState::default();
+        state.start("idle".to_string(), true, 0.5);
+        // Nothing was playing to fade from
+        assert_eq!(state.fading_from, None);
+        assert_eq!(state.blend(), 1.0);
+
+        state.start("walk".to_string(), true, 0.5);
+        assert_eq!(state.current_animation.as_deref(), Some("walk"));
+        assert_eq!(state.fading_from.as_deref(), Some("idle"));
+        assert_eq!(state.blend(), 0.0);
+
+        state.advance_crossfade(0.25);
+        assert!((state.blend() - 0.5).abs() < 1e-6);
+        state.advance_crossfade(0.25);
+        assert_eq!(state.fading_from, None);
+        assert_eq!(state.blend(), 1.0);
+    }
+
+    #[test]
+    fn cuts_and_restarts_do_not_fade() {
+        let mut state = PetAnimationState::default();
+        state.start("idle".to_string(), true, 0.5);
+        state.start("jump".to_string(), false, 0.0);
+        assert_eq!(state.fading_from, None);
+
+        state.start("jump".to_string(), false, 0.5);
+        assert_eq!(state.fading_from, None);
+        assert!(!state.looping);
+    }
+
+    #[test]
+    fn looping_clip_asked_to_loop_again_carries_on() {
+        let node = AnimationNodeIndex::new(1);
+        let (player, transitions) = playing(node);
 
-        info!("Playing animation '{}'", event.animation_name);
+        assert!(continues_loop(&player, &transitions, node, true, true));
+        assert!(!continues_loop(&player, &transitions, node, true, false));
+        assert!(!continues_loop(
+            &player,
+            &transitions,
+            AnimationNodeIndex::new(2),
+            true,
+            true
+        ));
     }
 }
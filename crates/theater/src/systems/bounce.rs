@@ -1,6 +1,7 @@
 //! Bounce tween system
 
 use bevy::prelude::*;
+use common::script::Easing;
 
 use crate::components::BounceTween;
 
@@ -9,6 +10,14 @@ fn bounce_arc(t: f32) -> f32 {
     4.0 * t * (1.0 - t)
 }
 
+/// Height of the bounce at linear `progress`, from 0 (ground) to 1
+/// (apex). The easing only changes when the pet passes each point of the
+/// arc; overshooting curves are held at the ends, so the pet doesn't sink
+/// below the ground before taking off or after landing.
+fn bounce_height(progress: f32, easing: Easing) -> f32 {
+    bounce_arc(easing.apply(progress).clamp(0.0, 1.0))
+}
+
 /// Update bounce tweens
 pub fn update_bounce_tween(
     mut commands: Commands,
@@ -19,7 +28,7 @@ pub fn update_bounce_tween(
         tween.elapsed += time.delta_secs();
 
         let progress = (tween.elapsed / tween.duration).clamp(0.0, 1.0);
-        let arc = bounce_arc(progress);
+        let arc = bounce_height(progress, tween.easing);
 
         transform.translation.y = tween.base_y + tween.height * arc;
 
@@ -30,3 +39,29 @@ pub fn update_bounce_tween(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overshooting_easings_stay_between_ground_and_apex() {
+        for easing in [Easing::BackIn, Easing::BackOut, Easing::ElasticOut] {
+            for step in 0..=100 {
+                let height = bounce_height(step as f32 / 100.0, easing);
+                assert!(
+                    (0.0..=1.0).contains(&height),
+                    "{easing:?} reached {height} at step {step}"
+                );
+            }
+            assert_eq!(bounce_height(0.0, easing), 0.0);
+            assert!(bounce_height(1.0, easing).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn linear_bounce_peaks_halfway() {
+        assert_eq!(bounce_height(0.5, Easing::Linear), 1.0);
+        assert_eq!(bounce_height(0.25, Easing::Linear), 0.75);
+    }
+}
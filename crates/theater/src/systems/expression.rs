@@ -0,0 +1,153 @@
+//! Facial expression systems, driven by the model's morph targets
+
+use bevy::prelude::*;
+use tracing::{debug, info, warn};
+
+use crate::components::{ExpressionTween, PetMarker};
+use crate::events::SetExpressionEvent;
+use crate::resources::{ExpressionMap, TheaterConfig};
+
+/// Collect the pet's morph targets as its meshes are spawned and build
+/// the expression map from them
+pub fn setup_expressions(
+    config: Option<Res<TheaterConfig>>,
+    meshes: Res<Assets<Mesh>>,
+    mut expression_map: ResMut<ExpressionMap>,
+    added: Query<Entity, Added<MorphWeights>>,
+    morph_weights: Query<&MorphWeights>,
+    parents: Query<&ChildOf>,
+    pets: Query<(), With<PetMarker>>,
+) {
+    let mut found = false;
+    for entity in added.iter() {
+        if parents
+            .iter_ancestors(entity)
+            .any(|ancestor| pets.contains(ancestor))
+        {
+            expression_map.morph_entities.push(entity);
+            found = true;
+        }
+    }
+    if !found {
+        return;
+    }
+
+    // Morph targets are indexed per mesh; expressions address the mesh
+    // with the most targets, and shorter meshes ignore the extra weights
+    let Some(weights) = expression_map
+        .morph_entities
+        .iter()
+        .filter_map(|&entity| morph_weights.get(entity).ok())
+        .max_by_key(|weights| weights.weights().len())
+    else {
+        return;
+    };
+    let target_count = weights.weights().len();
+    let target_names = weights
+        .first_mesh()
+        .and_then(|handle| meshes.get(handle))
+        .and_then(Mesh::morph_target_names)
+        .map(<[String]>::to_vec)
+        .unwrap_or_default();
+
+    let mapping = config.map(|c| c.expressions.clone()).unwrap_or_default();
+    expression_map.build(&mapping, &target_names, target_count);
+
+    info!(
+        "Expression setup complete with {} morph targets: {:?}",
+        target_count,
+        expression_map.names()
+    );
+}
+
+/// Forget morph entities whose scene has been despawned, so expressions
+/// are not blended from or into meshes that no longer exist
+pub fn forget_despawned_morphs(
+    mut removed: RemovedComponents<MorphWeights>,
+    mut expression_map: ResMut<ExpressionMap>,
+) {
+    let removed: Vec<Entity> = removed.read().collect();
+    if removed.is_empty() {
+        return;
+    }
+    // The entity list is not announced, so leave change detection to the
+    // expressions themselves
+    expression_map
+        .bypass_change_detection()
+        .morph_entities
+        .retain(|entity| !removed.contains(entity));
+}
+
+/// Start blending towards requested expressions
+pub fn set_expression(
+    mut commands: Commands,
+    mut events: MessageReader<SetExpressionEvent>,
+    expression_map: Res<ExpressionMap>,
+    morph_weights: Query<&MorphWeights>,
+) {
+    for event in events.read() {
+        let Some(target_weights) = expression_map.expressions.get(&event.expression) else {
+            if expression_map.expressions.is_empty() {
+                debug!(
+                    "Expression '{}' skipped: model has no morph targets",
+                    event.expression
+                );
+            } else {
+                warn!(
+                    "Expression '{}' not found in expression map",
+                    event.expression
+                );
+            }
+            continue;
+        };
+
+        // Blend from wherever the face is now, including mid-blend
+        let mut start_weights = expression_map
+            .morph_entities
+            .iter()
+            .find_map(|&entity| morph_weights.get(entity).ok())
+            .map(|weights| weights.weights().to_vec())
+            .unwrap_or_default();
+        start_weights.resize(target_weights.len(), 0.0);
+
+        commands.entity(event.entity).insert(ExpressionTween {
+            start_weights,
+            target_weights: target_weights.clone(),
+            duration: event.duration,
+            easing: event.easing,
+            elapsed: 0.0,
+        });
+        info!("Setting expression '{}'", event.expression);
+    }
+}
+
+/// Update expression blends
+pub fn update_expression_tween(
+    mut commands: Commands,
+    time: Res<Time>,
+    expression_map: Res<ExpressionMap>,
+    mut query: Query<(Entity, &mut ExpressionTween)>,
+    mut morph_weights: Query<&mut MorphWeights>,
+) {
+    for (entity, mut tween) in query.iter_mut() {
+        tween.elapsed += time.delta_secs();
+
+        let progress = (tween.elapsed / tween.duration).clamp(0.0, 1.0);
+        let eased_progress = tween.easing.apply(progress);
+
+        for &morph_entity in &expression_map.morph_entities {
+            let Ok(mut weights) = morph_weights.get_mut(morph_entity) else {
+                continue;
+            };
+            let blended = tween.start_weights.iter().zip(&tween.target_weights);
+            for (weight, (start, target)) in weights.weights_mut().iter_mut().zip(blended) {
+                *weight = start + (target - start) * eased_progress;
+            }
+        }
+
+        // Remove component when tween is complete
+        if progress >= 1.0 {
+            commands.entity(entity).remove::<ExpressionTween>();
+        }
+    }
+}
@@ -1,12 +1,19 @@
 //! Keyframe execution systems
 
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
-use common::script::Action;
+use common::script::{Action, DEFAULT_FOLLOW_DISTANCE, DEFAULT_FOLLOW_SPEED, TimelineAnchor};
 use rand::prelude::IndexedRandom;
+use tracing::{info, warn};
 
-use crate::components::{BounceTween, MovementTween, ReplayState, RotationTween, ScaleTween};
-use crate::events::{ExecuteActionEvent, PlayAnimationEvent, SwitchScriptEvent};
-use crate::resources::ScriptLibrary;
+use crate::components::{
+    BounceTween, MAX_CALL_DEPTH, MovementTween, ReplayState, RotationTween, ScaleTween,
+};
+use crate::events::{
+    ExecuteActionEvent, FollowCursorEvent, LookAtEvent, PlayAnimationEvent, PlaySoundEvent,
+    SayEvent, SetExpressionEvent, SwitchScriptEvent, TrackCursorEvent,
+};
+use crate::resources::{PetActivity, ScriptLibrary, ScriptRng};
 
 /// Advance the replay time for all entities with ReplayState
 pub fn advance_replay_time(time: Res<Time>, mut query: Query<&mut ReplayState>) {
@@ -19,10 +26,13 @@ pub fn advance_replay_time(time: Res<Time>, mut query: Query<&mut ReplayState>)
     }
 }
 
-/// Check and execute keyframes that are due
+/// Check and execute keyframes that are due.
+///
+/// `Call` actions are handled here rather than in `dispatch_actions`: the
+/// caller must stop executing keyframes as soon as its sub-script starts.
 pub fn execute_keyframes(
     mut query: Query<(Entity, &mut ReplayState)>,
-    script_library: Res<ScriptLibrary>,
+    mut script_library: ResMut<ScriptLibrary>,
     mut action_events: MessageWriter<ExecuteActionEvent>,
 ) {
     for (entity, mut state) in query.iter_mut() {
@@ -30,41 +40,100 @@ pub fn execute_keyframes(
             continue;
         };
 
-        // Execute all keyframes that are due
-        while state.next_keyframe_index < script.keyframes.len() {
-            let keyframe = &script.keyframes[state.next_keyframe_index];
+        // Each track advances independently; execute whatever is due on each
+        let mut sub_script = None;
+        state.track_cursors.resize(script.track_count(), 0);
+        state
+            .track_anchors
+            .resize(script.track_count(), TimelineAnchor::default());
+        'tracks: for track in 0..script.track_count() {
+            let keyframes = script.track_keyframes(track);
 
-            if keyframe.time <= state.elapsed_time {
+            while let Some(keyframe) = keyframes.get(state.track_cursors[track]) {
+                if state.track_anchors[track].time_of(keyframe) > state.elapsed_time {
+                    break;
+                }
+                state.track_cursors[track] += 1;
+                state.track_anchors[track].advance(keyframe);
+                if let Some(call) = keyframe.action.sub_script() {
+                    sub_script = Some(call);
+                    break 'tracks;
+                }
                 action_events.write(ExecuteActionEvent {
                     entity,
+                    script_id: state.script_id.clone(),
                     action: keyframe.action.clone(),
                 });
-                state.next_keyframe_index += 1;
-            } else {
-                break;
             }
         }
+
+        let Some(call) = sub_script else {
+            continue;
+        };
+        match script_library.resolve(&call) {
+            Ok(script_id) => {
+                let caller = state.script_id.clone();
+                if state.push_call(script_id.clone()) {
+                    info!("Script '{caller}' calling '{script_id}'");
+                } else {
+                    warn!(
+                        "Script '{caller}' cannot call '{script_id}': call depth limit ({MAX_CALL_DEPTH}) reached"
+                    );
+                }
+            }
+            Err(e) => warn!(
+                "Script '{}' cannot call '{}': {e}",
+                state.script_id, call.script
+            ),
+        }
     }
 }
 
+/// Messages actions are dispatched as
+#[derive(SystemParam)]
+pub struct ActionMessages<'w> {
+    play_animation: MessageWriter<'w, PlayAnimationEvent>,
+    set_expression: MessageWriter<'w, SetExpressionEvent>,
+    play_sound: MessageWriter<'w, PlaySoundEvent>,
+    say: MessageWriter<'w, SayEvent>,
+    look_at: MessageWriter<'w, LookAtEvent>,
+    track_cursor: MessageWriter<'w, TrackCursorEvent>,
+    follow_cursor: MessageWriter<'w, FollowCursorEvent>,
+    switch_script: MessageWriter<'w, SwitchScriptEvent>,
+}
+
 /// Dispatch actions to their respective handlers
 pub fn dispatch_actions(
     mut action_events: MessageReader<ExecuteActionEvent>,
-    mut play_anim_events: MessageWriter<PlayAnimationEvent>,
-    mut switch_script_events: MessageWriter<SwitchScriptEvent>,
+    mut messages: ActionMessages,
     mut commands: Commands,
+    mut activity: ResMut<PetActivity>,
+    mut rng: ResMut<ScriptRng>,
+    time: Res<Time>,
     query: Query<&Transform>,
 ) {
     for event in action_events.read() {
+        // Per-keyframe overrides, or the action's defaults
+        let tween_duration = event.action.tween_duration().unwrap_or_default();
+        let easing = event.action.tween_easing().unwrap_or_default();
+
         match &event.action {
-            Action::PlayAnimation { name } => {
-                play_anim_events.write(PlayAnimationEvent {
+            Action::PlayAnimation {
+                name,
+                looping,
+                speed,
+                ..
+            } => {
+                messages.play_animation.write(PlayAnimationEvent {
                     entity: event.entity,
                     animation_name: name.clone(),
+                    crossfade: event.action.crossfade_duration().unwrap_or_default(),
+                    looping: looping.unwrap_or(true),
+                    speed: speed.unwrap_or(1.0),
                 });
             }
 
-            Action::MoveTo { x, y } => {
+            Action::MoveTo { x, y, .. } => {
                 if let Ok(transform) = query.get(event.entity) {
                     // Map script coordinates to 3D space:
                     // x -> x (left/right)
@@ -72,61 +141,123 @@ pub fn dispatch_actions(
                     commands.entity(event.entity).insert(MovementTween {
                         start_position: transform.translation,
                         target_position: Vec3::new(*x, transform.translation.y, *y),
-                        duration: 1.0, // Default 1 second for movement
+                        duration: tween_duration,
+                        easing,
                         elapsed: 0.0,
                     });
                 }
             }
 
-            Action::Scale { factor } => {
+            Action::Scale { factor, .. } => {
                 if let Ok(transform) = query.get(event.entity) {
                     commands.entity(event.entity).insert(ScaleTween {
                         start_scale: transform.scale,
                         target_scale: Vec3::splat(*factor),
-                        duration: 0.3, // Quick scale transition
+                        duration: tween_duration,
+                        easing,
                         elapsed: 0.0,
                     });
                 }
             }
 
-            Action::Spin { turns } => {
+            Action::Spin { turns, .. } => {
                 if let Ok(transform) = query.get(event.entity) {
                     let total_angle = *turns * std::f32::consts::TAU;
                     commands.entity(event.entity).insert(RotationTween {
                         start_rotation: transform.rotation,
                         total_angle,
-                        duration: turns.abs() * 0.8, // ~0.8s per full turn
+                        duration: tween_duration,
+                        easing,
                         elapsed: 0.0,
                     });
                 }
             }
 
-            Action::Wait { duration: _ } => {
-                // Wait is handled implicitly by the elapsed time check
+            Action::Call { .. } => {
+                // Handled by execute_keyframes, which owns the call stack
+            }
+
+            Action::Wait { .. } => {
+                // Handled by execute_keyframes, which holds back the rest
+                // of the track until the wait ends
             }
 
             Action::Random { scripts } => {
-                if let Some(selected) = scripts.choose(&mut rand::rng()) {
-                    switch_script_events.write(SwitchScriptEvent {
-                        script_id: selected.clone(),
+                if let Ok(selected) = scripts.choose_weighted(&mut rng.0, |c| c.weight) {
+                    messages.switch_script.write(SwitchScriptEvent {
+                        script: selected.call(),
                         force: false,
                     });
                 }
             }
 
-            Action::Bounce { height } => {
+            Action::Branch { .. } => {
+                let state = activity.snapshot(time.elapsed_secs());
+                if let Some(choices) = event.action.branch_choices(&state)
+                    && let Ok(selected) = choices.choose_weighted(&mut rng.0, |c| c.weight)
+                {
+                    messages.switch_script.write(SwitchScriptEvent {
+                        script: selected.call(),
+                        force: false,
+                    });
+                }
+            }
+
+            Action::Bounce { height, .. } => {
                 if let Ok(transform) = query.get(event.entity) {
                     commands.entity(event.entity).insert(BounceTween {
                         base_y: transform.translation.y,
                         height: *height,
-                        duration: 0.4,
+                        duration: tween_duration,
+                        easing,
                         elapsed: 0.0,
                     });
                 }
             }
 
-            Action::SetExpression { expression: _ } => {
-                // Not implemented in Phase 3
+            Action::SetExpression { expression, .. } => {
+                messages.set_expression.write(SetExpressionEvent {
+                    entity: event.entity,
+                    expression: expression.clone(),
+                    duration: tween_duration,
+                    easing,
+                });
+            }
+
+            Action::PlaySound { name, volume } => {
+                messages.play_sound.write(PlaySoundEvent {
+                    name: name.clone(),
+                    volume: volume.unwrap_or(1.0),
+                });
+            }
+
+            Action::Say { text, .. } => {
+                messages.say.write(SayEvent {
+                    text: text.clone(),
+                    duration: event.action.speech_duration().unwrap_or_default(),
+                });
+            }
+
+            Action::LookAt { target, .. } => {
+                messages.look_at.write(LookAtEvent {
+                    entity: event.entity,
+                    target: *target,
+                    duration: event.action.look_duration().unwrap_or_default(),
+                });
+            }
+
+            Action::TrackCursor { enabled } => {
+                messages.track_cursor.write(TrackCursorEvent {
+                    entity: event.entity,
+                    enabled: *enabled,
+                });
+            }
+
+            Action::FollowCursor { speed, distance } => {
+                messages.follow_cursor.write(FollowCursorEvent {
+                    speed: speed.unwrap_or(DEFAULT_FOLLOW_SPEED),
+                    distance: distance.unwrap_or(DEFAULT_FOLLOW_DISTANCE),
+                });
             }
         }
     }
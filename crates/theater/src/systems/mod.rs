@@ -2,6 +2,7 @@
 
 mod animation;
 mod bounce;
+mod expression;
 mod keyframe;
 mod movement;
 mod rotation;
@@ -10,6 +11,7 @@ mod script_transition;
 
 pub use animation::*;
 pub use bounce::*;
+pub use expression::*;
 pub use keyframe::*;
 pub use movement::*;
 pub use rotation::*;
@@ -4,11 +4,6 @@ use bevy::prelude::*;
 
 use crate::components::MovementTween;
 
-/// Ease-out cubic function for smooth deceleration
-fn ease_out_cubic(t: f32) -> f32 {
-    1.0 - (1.0 - t).powi(3)
-}
-
 /// Update movement tweens
 pub fn update_movement_tween(
     mut commands: Commands,
@@ -19,7 +14,7 @@ pub fn update_movement_tween(
         tween.elapsed += time.delta_secs();
 
         let progress = (tween.elapsed / tween.duration).clamp(0.0, 1.0);
-        let eased_progress = ease_out_cubic(progress);
+        let eased_progress = tween.easing.apply(progress);
 
         transform.translation = tween
             .start_position
@@ -1,11 +1,18 @@
 //! Script transition and completion systems
 
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
-use tracing::{info, warn};
+use common::triggers::Trigger;
+use rand::prelude::IndexedRandom;
+use std::collections::HashSet;
+use tracing::{debug, info, warn};
 
 use crate::components::ReplayState;
-use crate::events::{PetClickedEvent, ScriptCompletedEvent, SwitchScriptEvent};
-use crate::resources::ScriptLibrary;
+use crate::events::{
+    PetClickedEvent, PetDoubleClickedEvent, PetDragEndedEvent, PetDragStartedEvent,
+    PetHoveredEvent, PetLongPressedEvent, ScriptCompletedEvent, SwitchScriptEvent, TriggerEvent,
+};
+use crate::resources::{DOUBLE_CLICK_INTERVAL, PetActivity, ScriptLibrary, ScriptRng, Triggers};
 
 /// Check if scripts have completed and emit completion events
 pub fn check_script_completion(
@@ -26,12 +33,18 @@ pub fn check_script_completion(
         let is_completed = match script.duration {
             Some(duration) => state.elapsed_time >= duration,
             None => {
-                // For looping scripts, check if all keyframes have been executed
-                state.next_keyframe_index >= script.keyframes.len()
+                // For looping scripts, check if every track has run out of keyframes
+                state.tracks_finished(script)
             }
         };
 
         if is_completed {
+            if state.iteration + 1 < script.play_count() {
+                state.iteration += 1;
+                state.restart();
+                continue;
+            }
+
             state.completed = true;
             completed_events.write(ScriptCompletedEvent {
                 entity,
@@ -47,77 +60,197 @@ pub fn handle_script_transition(
     mut completed_events: MessageReader<ScriptCompletedEvent>,
     mut switch_events: MessageReader<SwitchScriptEvent>,
     mut query: Query<(Entity, &mut ReplayState)>,
-    script_library: Res<ScriptLibrary>,
+    mut script_library: ResMut<ScriptLibrary>,
 ) {
     // Handle script completions
     for event in completed_events.read() {
-        if let Some(ref next_script_id) = event.next_script {
-            // Switch to next script if it exists
-            if script_library.get(next_script_id).is_some() {
-                for (entity, mut state) in query.iter_mut() {
-                    if entity == event.entity {
-                        state.switch_to(next_script_id.clone());
-                        info!(
-                            "Script '{}' completed, switching to '{}'",
-                            event.script_id, next_script_id
-                        );
-                        break;
-                    }
+        let Ok((_entity, mut state)) = query.get_mut(event.entity) else {
+            continue;
+        };
+
+        // Sub-scripts resume their caller instead of following `next`
+        if state.return_from_call() {
+            info!(
+                "Script '{}' completed, returning to '{}'",
+                event.script_id, state.script_id
+            );
+            continue;
+        }
+
+        if let Some(ref next_script) = event.next_script {
+            // Switch to next script if it resolves
+            match script_library.resolve(next_script) {
+                Ok(next_script_id) => {
+                    info!(
+                        "Script '{}' completed, switching to '{}'",
+                        event.script_id, next_script_id
+                    );
+                    state.switch_to(next_script_id);
                 }
+                Err(e) => warn!(
+                    "Script '{}' completed, cannot switch to '{}': {e}",
+                    event.script_id, next_script.script
+                ),
             }
-        } else {
+        } else if let Some(script) = script_library.get(&state.script_id)
+            && script.duration.is_none()
+        {
             // No next script specified - for looping scripts, restart
-            for (entity, mut state) in query.iter_mut() {
-                if entity == event.entity {
-                    if let Some(script) = script_library.get(&state.script_id)
-                        && script.duration.is_none()
-                    {
-                        // Looping script - restart
-                        let current_id = state.script_id.clone();
-                        state.switch_to(current_id.clone());
-                        info!("Looping script '{}' restarted", current_id);
-                    }
-                    break;
-                }
-            }
+            let current_id = state.script_id.clone();
+            state.switch_to(current_id.clone());
+            info!("Looping script '{}' restarted", current_id);
         }
     }
 
     // Handle manual switch requests
     for event in switch_events.read() {
-        if script_library.get(&event.script_id).is_none() {
-            warn!("Script '{}' not found in library", event.script_id);
-            continue;
-        }
-
         for (_entity, mut state) in query.iter_mut() {
-            // Check if current script can be interrupted
+            // Check if current script can be interrupted; a sub-script
+            // cannot be interrupted while any of its callers forbids it
             if !event.force
-                && let Some(current_script) = script_library.get(&state.script_id)
-                && !current_script.interruptible
                 && !state.completed
+                && let Some(blocking) = state.script_stack().find(|id| {
+                    script_library
+                        .get(id)
+                        .is_some_and(|script| !script.interruptible)
+                })
             {
-                info!(
-                    "Cannot interrupt script '{}' (not interruptible)",
-                    state.script_id
-                );
+                info!("Cannot interrupt script '{}' (not interruptible)", blocking);
                 continue;
             }
 
-            state.switch_to(event.script_id.clone());
-            info!("Switched to script '{}'", event.script_id);
+            // Templates are expanded only once the switch is allowed, so a
+            // running instance is never replaced underneath its replay state
+            match script_library.resolve(&event.script) {
+                Ok(script_id) => {
+                    info!("Switched to script '{}'", script_id);
+                    state.switch_to(script_id);
+                }
+                Err(e) => warn!("Cannot switch to script '{}': {e}", event.script.script),
+            }
         }
     }
 }
 
-/// Handle pet click events by switching to the happy script
-pub fn handle_pet_click(
+/// Forget expanded templates that no pet is playing or will return to, so
+/// calls with ever new arguments don't pile up in the library
+pub fn forget_unused_instances(
+    query: Query<&ReplayState>,
+    mut script_library: ResMut<ScriptLibrary>,
+) {
+    let in_use: HashSet<&str> = query.iter().flat_map(ReplayState::script_stack).collect();
+    script_library.retain_instances(|id| in_use.contains(id));
+}
+
+/// Turn clicks into `click` triggers. When the pet reacts to double
+/// clicks, each click is held back for `DOUBLE_CLICK_INTERVAL` first, and
+/// two in a row become a single `double_click`.
+///
+/// The window reports a double click as a click followed by a
+/// `PetDoubleClickedEvent`, which ends the held back click the same way a
+/// second click does.
+pub fn detect_clicks(
+    time: Res<Time>,
     mut click_events: MessageReader<PetClickedEvent>,
-    mut switch_events: MessageWriter<SwitchScriptEvent>,
+    mut double_click_events: MessageReader<PetDoubleClickedEvent>,
+    mut triggers: ResMut<Triggers>,
+    mut trigger_events: MessageWriter<TriggerEvent>,
 ) {
+    let now = time.elapsed_secs();
+    if triggers
+        .pending_click
+        .is_some_and(|clicked_at| now - clicked_at > DOUBLE_CLICK_INTERVAL)
+    {
+        triggers.pending_click = None;
+        trigger_events.write(TriggerEvent(Trigger::Click));
+    }
+
     for _event in click_events.read() {
+        if !triggers.table.handles(Trigger::DoubleClick) {
+            trigger_events.write(TriggerEvent(Trigger::Click));
+        } else if triggers.pending_click.take().is_some() {
+            trigger_events.write(TriggerEvent(Trigger::DoubleClick));
+        } else {
+            triggers.pending_click = Some(now);
+        }
+    }
+
+    for _event in double_click_events.read() {
+        if !triggers.table.handles(Trigger::DoubleClick) {
+            trigger_events.write(TriggerEvent(Trigger::Click));
+        } else {
+            triggers.pending_click = None;
+            trigger_events.write(TriggerEvent(Trigger::DoubleClick));
+        }
+    }
+}
+
+/// Gestures that map directly to triggers
+#[derive(SystemParam)]
+pub struct TriggerGestures<'w, 's> {
+    drag_started: MessageReader<'w, 's, PetDragStartedEvent>,
+    drag_ended: MessageReader<'w, 's, PetDragEndedEvent>,
+    long_pressed: MessageReader<'w, 's, PetLongPressedEvent>,
+    hovered: MessageReader<'w, 's, PetHoveredEvent>,
+}
+
+/// Turn drags, long presses and hovering into triggers
+pub fn detect_gesture_triggers(
+    mut gestures: TriggerGestures,
+    mut trigger_events: MessageWriter<TriggerEvent>,
+) {
+    for _event in gestures.drag_started.read() {
+        trigger_events.write(TriggerEvent(Trigger::DragStart));
+    }
+    for _event in gestures.drag_ended.read() {
+        trigger_events.write(TriggerEvent(Trigger::Drop));
+    }
+    for _event in gestures.long_pressed.read() {
+        trigger_events.write(TriggerEvent(Trigger::LongPress));
+    }
+    for event in gestures.hovered.read() {
+        if event.hovering {
+            trigger_events.write(TriggerEvent(Trigger::HoverEnter));
+        }
+    }
+}
+
+/// Trigger `idle_timeout` once nobody has interacted with the pet for the
+/// trigger table's timeout, and again only after the next interaction
+pub fn check_idle_timeout(
+    time: Res<Time>,
+    activity: Res<PetActivity>,
+    mut triggers: ResMut<Triggers>,
+    mut trigger_events: MessageWriter<TriggerEvent>,
+) {
+    let idle_since = activity.last_interaction;
+    if time.elapsed_secs() - idle_since < triggers.table.idle_timeout
+        || triggers.idle_triggered_for == Some(idle_since)
+    {
+        return;
+    }
+    triggers.idle_triggered_for = Some(idle_since);
+    trigger_events.write(TriggerEvent(Trigger::IdleTimeout));
+}
+
+/// Switch to a script from the trigger table's reaction to each trigger
+pub fn handle_triggers(
+    mut trigger_events: MessageReader<TriggerEvent>,
+    mut switch_events: MessageWriter<SwitchScriptEvent>,
+    triggers: Res<Triggers>,
+    mut rng: ResMut<ScriptRng>,
+) {
+    for TriggerEvent(trigger) in trigger_events.read() {
+        let Ok(selected) = triggers
+            .table
+            .reaction(*trigger)
+            .choose_weighted(&mut rng.0, |c| c.weight)
+        else {
+            continue;
+        };
+        debug!("Trigger '{trigger}' starts '{}'", selected.script);
         switch_events.write(SwitchScriptEvent {
-            script_id: "happy".to_string(),
+            script: selected.call(),
             force: false,
         });
     }